@@ -0,0 +1,217 @@
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// The first line of an ASCII armored OpenPGP message.
+pub const BEGIN_MESSAGE: &str = "-----BEGIN PGP MESSAGE-----";
+/// The last line of an ASCII armored OpenPGP message.
+pub const END_MESSAGE: &str = "-----END PGP MESSAGE-----";
+/// The length of each line of base64, as gpg writes them.
+const LINE_LENGTH: usize = 64;
+const CRC24_INIT: u32 = 0xb704ce;
+const CRC24_POLY: u32 = 0x1864cfb;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ArmorError {
+    #[error("it does not start with `{BEGIN_MESSAGE}`")]
+    MissingHeader,
+    #[error("it does not end with `{END_MESSAGE}`")]
+    MissingFooter,
+    #[error("its contents are not valid base64")]
+    InvalidBase64,
+    #[error("its checksum does not match its contents")]
+    ChecksumMismatch,
+}
+
+/// How the OpenPGP messages of entries are written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Binary,
+    Armored,
+}
+
+impl Encoding {
+    /// `message` in this encoding, or `None` if it is already.
+    pub fn encode(self, message: &[u8]) -> Result<Option<Vec<u8>>, ArmorError> {
+        match (self, is_armored(message)) {
+            (Encoding::Binary, true) => dearmor(message).map(Some),
+            (Encoding::Armored, false) => Ok(Some(armor(message))),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "armored" => Ok(Self::Armored),
+            _ => Err(format!("expected one of `binary` or `armored`, got `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Binary => write!(f, "binary"),
+            Encoding::Armored => write!(f, "ASCII armored"),
+        }
+    }
+}
+
+/// Whether `bytes` start as an ASCII armored OpenPGP message does.
+pub fn is_armored(bytes: &[u8]) -> bool {
+    bytes.starts_with(BEGIN_MESSAGE.as_bytes())
+}
+
+/// ASCII armor the binary OpenPGP message `bytes`, as gpg does in armor
+/// mode.
+pub fn armor(bytes: &[u8]) -> Vec<u8> {
+    let encoded = base64::encode(bytes);
+    let mut armored = format!("{BEGIN_MESSAGE}\n\n");
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push('=');
+    armored.push_str(&base64::encode(&crc24(bytes).to_be_bytes()[1..]));
+    armored.push('\n');
+    armored.push_str(END_MESSAGE);
+    armored.push('\n');
+    armored.into_bytes()
+}
+
+/// Read the binary OpenPGP message out of the ASCII armored one in
+/// `bytes`, skipping any armor headers and checking its checksum if it has
+/// one.
+pub fn dearmor(bytes: &[u8]) -> Result<Vec<u8>, ArmorError> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut lines = text.lines().map(str::trim_end);
+    if lines.next() != Some(BEGIN_MESSAGE) {
+        return Err(ArmorError::MissingHeader);
+    }
+    // Headers, such as `Comment: ...`, end with a blank line.
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+    }
+    let mut encoded = String::new();
+    let mut checksum = None;
+    let mut has_footer = false;
+    for line in lines {
+        if line == END_MESSAGE {
+            has_footer = true;
+            break;
+        }
+        match line.strip_prefix('=') {
+            Some(line) => checksum = Some(line.to_owned()),
+            None => encoded.push_str(line.trim()),
+        }
+    }
+    if !has_footer {
+        return Err(ArmorError::MissingFooter);
+    }
+    let decoded = base64::decode(encoded).map_err(|_| ArmorError::InvalidBase64)?;
+    if let Some(checksum) = checksum {
+        let checksum = base64::decode(checksum).map_err(|_| ArmorError::InvalidBase64)?;
+        if checksum != crc24(&decoded).to_be_bytes()[1..] {
+            return Err(ArmorError::ChecksumMismatch);
+        }
+    }
+    Ok(decoded)
+}
+
+/// Read as much of the binary OpenPGP message as the start of the ASCII
+/// armored one in `bytes` holds, such as the first few kilobytes of a file.
+/// Neither the footer nor the checksum need be there, so neither is checked.
+pub fn dearmor_head(bytes: &[u8]) -> Result<Vec<u8>, ArmorError> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut lines = text.lines().map(str::trim_end);
+    if lines.next() != Some(BEGIN_MESSAGE) {
+        return Err(ArmorError::MissingHeader);
+    }
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+    }
+    let mut encoded = lines
+        .take_while(|line| *line != END_MESSAGE && !line.starts_with('='))
+        .map(str::trim)
+        .collect::<String>();
+    // The last line may have been cut part way through.
+    encoded.truncate(encoded.len() / 4 * 4);
+    base64::decode(encoded).map_err(|_| ArmorError::InvalidBase64)
+}
+
+/// The CRC-24 checksum of `bytes`, as defined by RFC 4880.
+fn crc24(bytes: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for byte in bytes {
+        crc ^= u32::from(*byte) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+#[cfg(test)]
+mod test {
+    use super::{armor, dearmor, is_armored, ArmorError, Encoding};
+
+    /// The bytes `hello`, ASCII armored with a header.
+    const ARMORED: &str = "-----BEGIN PGP MESSAGE-----\nComment: test\n\naGVsbG8=\n=R/WK\n-----END PGP MESSAGE-----\n";
+
+    #[test]
+    fn should_dearmor_skipping_headers() {
+        assert!(is_armored(ARMORED.as_bytes()));
+        assert_eq!(dearmor(ARMORED.as_bytes()).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn armor_should_round_trip_long_messages() {
+        let bytes = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        let armored = armor(&bytes);
+        assert!(is_armored(&armored));
+        assert!(std::str::from_utf8(&armored)
+            .unwrap()
+            .lines()
+            .all(|line| line.len() <= 64));
+        assert_eq!(dearmor(&armored).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encode_should_only_convert_messages_in_the_other_encoding() {
+        let armored = ARMORED.as_bytes();
+        assert_eq!(Encoding::Binary.encode(armored).unwrap().unwrap(), b"hello");
+        assert_eq!(Encoding::Armored.encode(armored).unwrap(), None);
+        assert_eq!(Encoding::Binary.encode(b"hello").unwrap(), None);
+        assert!(is_armored(
+            &Encoding::Armored.encode(b"hello").unwrap().unwrap()
+        ));
+        assert_eq!("armored".parse(), Ok(Encoding::Armored));
+        assert!("ascii".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn dearmor_should_reject_invalid_messages() {
+        assert_eq!(dearmor(b"hello"), Err(ArmorError::MissingHeader));
+        let truncated = &ARMORED[..ARMORED.len() - 27];
+        assert_eq!(
+            dearmor(truncated.as_bytes()),
+            Err(ArmorError::MissingFooter)
+        );
+        let tampered = ARMORED.replace("aGVsbG8=", "aGVsbG0=");
+        assert_eq!(
+            dearmor(tampered.as_bytes()),
+            Err(ArmorError::ChecksumMismatch)
+        );
+    }
+}
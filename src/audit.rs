@@ -0,0 +1,182 @@
+use crate::keys::KeyInfo;
+use serde::Serialize;
+use std::fmt;
+
+/// Whether a key an entry is encrypted to can still be relied on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    Valid,
+    Expired,
+    Revoked,
+    /// The key is not in the keyring, so nothing is known about it.
+    Unknown,
+}
+
+impl KeyStatus {
+    pub fn of(key: Option<&KeyInfo>) -> Self {
+        match key {
+            Some(key) if key.revoked => KeyStatus::Revoked,
+            Some(key) if key.expired => KeyStatus::Expired,
+            Some(_) => KeyStatus::Valid,
+            None => KeyStatus::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for KeyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            KeyStatus::Valid => "valid",
+            KeyStatus::Expired => "expired",
+            KeyStatus::Revoked => "revoked",
+            KeyStatus::Unknown => "unknown",
+        };
+        f.write_str(status)
+    }
+}
+
+/// A key an entry is encrypted to.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Recipient {
+    /// The long key id from the message, usually that of a subkey.
+    pub key_id: String,
+    pub user_id: Option<String>,
+    pub status: KeyStatus,
+}
+
+/// Who can decrypt an entry, compared with who should be able to.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct EntryAudit {
+    pub name: String,
+    /// The key ids in the entry's `.gpg-id` file.
+    pub expected: Vec<String>,
+    pub recipients: Vec<Recipient>,
+    /// Keys from the `.gpg-id` file the entry is not encrypted to.
+    pub missing: Vec<String>,
+    /// Key ids the entry is encrypted to which are not from its `.gpg-id`
+    /// file.
+    pub unexpected: Vec<String>,
+    /// Why the recipients could not be read, if they could not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl EntryAudit {
+    /// Whether the entry is encrypted to exactly the keys in its `.gpg-id`
+    /// file, each of which is valid.
+    pub fn is_compliant(&self) -> bool {
+        self.error.is_none()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self
+                .recipients
+                .iter()
+                .all(|recipient| recipient.status == KeyStatus::Valid)
+    }
+}
+
+/// Compare the recipients of a message with the keys it should be encrypted
+/// to, given as each key id from a `.gpg-id` file with the long key ids of
+/// its subkeys. Messages list the subkey they are encrypted to, so a key is
+/// a recipient if any of its subkeys are. Returns the keys which are not
+/// recipients, and the recipients which are not from any of the keys.
+pub fn compare<S: AsRef<str>>(
+    expected: &[(S, Vec<String>)],
+    recipients: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut covered = Vec::new();
+    let mut missing = Vec::new();
+    for (key_id, subkey_ids) in expected {
+        let matched = recipients
+            .iter()
+            .filter(|recipient| subkey_ids.contains(recipient))
+            .collect::<Vec<_>>();
+        if matched.is_empty() {
+            missing.push(key_id.as_ref().to_owned());
+        }
+        covered.extend(matched);
+    }
+    let unexpected = recipients
+        .iter()
+        .filter(|recipient| !covered.contains(recipient))
+        .cloned()
+        .collect();
+    (missing, unexpected)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare, EntryAudit, KeyStatus, Recipient};
+    use crate::keys::{KeyInfo, Trust};
+
+    fn subkeys(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn keys_should_match_through_any_of_their_subkeys() {
+        let expected = [
+            ("alice@example.com", subkeys(&["A1", "A2"])),
+            ("bob@example.com", subkeys(&["B1"])),
+        ];
+        let (missing, unexpected) = compare(&expected, &subkeys(&["A2", "B1"]));
+        assert!(missing.is_empty());
+        assert!(unexpected.is_empty());
+    }
+
+    #[test]
+    fn should_find_missing_and_unexpected_recipients() {
+        let expected = [
+            ("alice@example.com", subkeys(&["A1"])),
+            ("bob@example.com", subkeys(&["B1"])),
+        ];
+        let (missing, unexpected) = compare(&expected, &subkeys(&["A1", "C1"]));
+        assert_eq!(missing, vec!["bob@example.com"]);
+        assert_eq!(unexpected, vec!["C1"]);
+    }
+
+    #[test]
+    fn revocation_should_take_precedence_over_expiry() {
+        let key = KeyInfo {
+            fingerprint: "F00".to_owned(),
+            user_id: None,
+            expires: None,
+            expired: true,
+            revoked: true,
+            trust: Trust::Full,
+        };
+        assert_eq!(KeyStatus::of(Some(&key)), KeyStatus::Revoked);
+        assert_eq!(KeyStatus::of(None), KeyStatus::Unknown);
+    }
+
+    #[test]
+    fn entries_with_an_expired_recipient_should_not_be_compliant() {
+        let mut audit = EntryAudit {
+            name: "api".to_owned(),
+            expected: subkeys(&["alice@example.com"]),
+            recipients: vec![Recipient {
+                key_id: "A1".to_owned(),
+                user_id: Some("alice@example.com".to_owned()),
+                status: KeyStatus::Valid,
+            }],
+            ..EntryAudit::default()
+        };
+        assert!(audit.is_compliant());
+        audit.recipients[0].status = KeyStatus::Expired;
+        assert!(!audit.is_compliant());
+    }
+
+    #[test]
+    fn should_serialize_status_in_lower_case() {
+        let recipient = Recipient {
+            key_id: "A1".to_owned(),
+            user_id: None,
+            status: KeyStatus::Revoked,
+        };
+        assert_eq!(
+            serde_json::to_string(&recipient).unwrap(),
+            r#"{"key_id":"A1","user_id":null,"status":"revoked"}"#
+        );
+    }
+}
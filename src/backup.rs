@@ -0,0 +1,236 @@
+use crate::{
+    input::{GPG_ID_LIST_FILE, GPG_ID_SIGNATURE_FILE},
+    store::ARMORED_ENTRY_EXTENSION,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{Cursor, Read},
+    path::{Component, Path},
+};
+use tar::{Archive, Builder, EntryType, Header};
+use thiserror::Error;
+
+/// Name of the manifest, always the first file in a backup.
+pub const MANIFEST: &str = "MANIFEST";
+const MANIFEST_HEADER: &str = "sec-backup 1";
+const FILE_MODE: u32 = 0o600;
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Failed to read or write the archive")]
+    Io(#[from] std::io::Error),
+    #[error("The backup has no manifest")]
+    MissingManifest,
+    #[error("The manifest is invalid, {0}")]
+    InvalidManifest(String),
+    #[error("The checksum of `{0}` does not match the manifest")]
+    ChecksumMismatch(String),
+    #[error("`{0}` is listed in the manifest but missing from the backup")]
+    MissingFile(String),
+    #[error("`{0}` is in the backup but not listed in the manifest")]
+    UnlistedFile(String),
+    #[error("`{0}` is not an entry, `.gpg-id` file or `.gpg-id` signature")]
+    UnexpectedPath(String),
+}
+
+/// A file in a backup, its path is relative to the store.
+#[derive(Debug, PartialEq)]
+pub struct BackupFile {
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+/// Build a tar archive of `files`, preceded by a manifest listing the
+/// SHA-256 checksum of each.
+pub fn pack(files: &[BackupFile]) -> Result<Vec<u8>, BackupError> {
+    let mut manifest = format!("{MANIFEST_HEADER}\n");
+    for file in files {
+        manifest.push_str(&format!("{}  {}\n", checksum(&file.contents), file.path));
+    }
+    let mut builder = Builder::new(Vec::new());
+    append(&mut builder, MANIFEST, manifest.as_bytes())?;
+    for file in files {
+        append(&mut builder, &file.path, &file.contents)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Read the files from a tar archive built by [`pack`]. Nothing is returned
+/// unless every file is listed in the manifest with a matching checksum,
+/// every listed file is present, and every path stays within the store.
+pub fn unpack(archive: &[u8]) -> Result<Vec<BackupFile>, BackupError> {
+    let mut archive = Archive::new(Cursor::new(archive));
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let raw_path = entry.path()?;
+        let path = match raw_path.to_str() {
+            Some(path) if entry.header().entry_type() == EntryType::Regular => path.to_owned(),
+            _ => return Err(BackupError::UnexpectedPath(raw_path.display().to_string())),
+        };
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push(BackupFile { path, contents });
+    }
+    if files.first().map(|file| file.path.as_str()) != Some(MANIFEST) {
+        return Err(BackupError::MissingManifest);
+    }
+    let manifest = files.remove(0);
+    let listed = parse_manifest(&manifest.contents)?;
+    for file in &files {
+        let expected = listed
+            .iter()
+            .find(|(_, path)| *path == file.path)
+            .ok_or_else(|| BackupError::UnlistedFile(file.path.clone()))?;
+        if expected.0 != checksum(&file.contents) {
+            return Err(BackupError::ChecksumMismatch(file.path.clone()));
+        }
+    }
+    if let Some((_, path)) = listed
+        .iter()
+        .find(|(_, path)| !files.iter().any(|file| file.path == *path))
+    {
+        return Err(BackupError::MissingFile((*path).to_owned()));
+    }
+    Ok(files)
+}
+
+/// Whether `path` is an entry, `.gpg-id` file or `.gpg-id` signature inside
+/// the store.
+pub fn is_store_path(path: &str) -> bool {
+    let is_relative = Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let is_entry = !file_name.starts_with('.')
+        && (file_name.ends_with(".gpg") || file_name.ends_with(ARMORED_ENTRY_EXTENSION));
+    is_relative && (is_entry || file_name == GPG_ID_LIST_FILE || file_name == GPG_ID_SIGNATURE_FILE)
+}
+
+fn append<W: std::io::Write>(
+    builder: &mut Builder<W>,
+    path: &str,
+    contents: &[u8],
+) -> Result<(), BackupError> {
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(FILE_MODE);
+    header.set_entry_type(EntryType::Regular);
+    builder.append_data(&mut header, path, contents)?;
+    Ok(())
+}
+
+fn parse_manifest(contents: &[u8]) -> Result<Vec<(&str, &str)>, BackupError> {
+    let invalid = |reason: &str| BackupError::InvalidManifest(reason.to_owned());
+    let manifest = std::str::from_utf8(contents).map_err(|_| invalid("it is not utf-8"))?;
+    let mut lines = manifest.lines();
+    if lines.next() != Some(MANIFEST_HEADER) {
+        return Err(invalid("it has an unknown header"));
+    }
+    lines
+        .map(|line| {
+            let (checksum, path) = line
+                .split_once("  ")
+                .ok_or_else(|| invalid("expected `<checksum>  <path>` lines"))?;
+            if !is_store_path(path) {
+                return Err(BackupError::UnexpectedPath(path.to_owned()));
+            }
+            Ok((checksum, path))
+        })
+        .collect()
+}
+
+fn checksum(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_store_path, pack, unpack, BackupError, BackupFile, MANIFEST};
+    use sha2::{Digest, Sha256};
+    use tar::{Builder, Header};
+
+    fn files() -> Vec<BackupFile> {
+        vec![
+            BackupFile {
+                path: ".gpg-id".to_owned(),
+                contents: b"key".to_vec(),
+            },
+            BackupFile {
+                path: "prod/db.gpg".to_owned(),
+                contents: b"ciphertext".to_vec(),
+            },
+        ]
+    }
+
+    fn build_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (path, contents) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o600);
+            builder.append_data(&mut header, path, *contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unpack_should_return_the_packed_files() {
+        let archive = pack(&files()).unwrap();
+        assert_eq!(unpack(&archive).unwrap(), files());
+    }
+
+    #[test]
+    fn unpack_should_reject_a_changed_file() {
+        let manifest = format!(
+            "sec-backup 1\n{:x}  db.gpg\n",
+            Sha256::digest(b"ciphertext")
+        );
+        let archive = build_archive(&[(MANIFEST, manifest.as_bytes()), ("db.gpg", b"tampered")]);
+        assert!(matches!(
+            unpack(&archive),
+            Err(BackupError::ChecksumMismatch(path)) if path == "db.gpg"
+        ));
+    }
+
+    #[test]
+    fn unpack_should_reject_missing_and_unlisted_files() {
+        let manifest = format!(
+            "sec-backup 1\n{:x}  db.gpg\n",
+            Sha256::digest(b"ciphertext")
+        );
+        let archive = build_archive(&[(MANIFEST, manifest.as_bytes())]);
+        assert!(matches!(unpack(&archive), Err(BackupError::MissingFile(_))));
+        let archive = build_archive(&[
+            (MANIFEST, manifest.as_bytes()),
+            ("db.gpg", b"ciphertext"),
+            ("api.gpg", b"extra"),
+        ]);
+        assert!(matches!(
+            unpack(&archive),
+            Err(BackupError::UnlistedFile(_))
+        ));
+    }
+
+    #[test]
+    fn unpack_should_require_a_manifest() {
+        let archive = build_archive(&[("db.gpg", b"ciphertext")]);
+        assert!(matches!(
+            unpack(&archive),
+            Err(BackupError::MissingManifest)
+        ));
+    }
+
+    #[test]
+    fn only_entries_and_gpg_id_files_inside_the_store_are_allowed() {
+        assert!(is_store_path("prod/db.gpg"));
+        assert!(is_store_path("prod/.gpg-id"));
+        assert!(is_store_path("prod/.gpg-id.sig"));
+        assert!(is_store_path("prod/api.asc"));
+        assert!(!is_store_path("../db.gpg"));
+        assert!(!is_store_path("/etc/db.gpg"));
+        assert!(!is_store_path("prod/notes.txt"));
+        assert!(!is_store_path(".gpg"));
+        assert!(!is_store_path("prod/.asc"));
+    }
+}
@@ -0,0 +1,668 @@
+use crate::{
+    crypto::CryptoBackend,
+    error::Error,
+    fs::FileSystemOperator,
+    fuzzy,
+    generate::DEFAULT_PASSWORD_LENGTH,
+    input::Handler,
+    picker::RawScreen,
+    secrets::{SecretReader, ZeroizedString},
+    store::Store,
+    tree::{EntryTree, TreeRow},
+};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    terminal::{Clear, ClearType},
+};
+use std::io::{self, Write};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+const MASK: &str = "********";
+const HELP: &str = "q quit  r reveal  c copy  e edit  d delete  g generate  / search  ↑/↓ move";
+
+/// The store operations the browser is built on.
+pub trait Operations {
+    fn entry_names(&self) -> Result<Vec<String>, Error>;
+    fn decrypt_entry(&self, name: &str) -> Result<ZeroizedString, Error>;
+    fn save(&self, name: &str, plaintext: &[u8]) -> Result<(), Error>;
+    fn delete(&self, name: &str) -> Result<(), Error>;
+    fn generate(&self, name: &str, length: usize) -> Result<ZeroizedString, Error>;
+}
+
+impl<F, R, S, C> Operations for Handler<F, R, S, C>
+where
+    R: SecretReader,
+    S: Store,
+    F: FileSystemOperator,
+    C: CryptoBackend,
+{
+    fn entry_names(&self) -> Result<Vec<String>, Error> {
+        Handler::entry_names(self)
+    }
+
+    fn decrypt_entry(&self, name: &str) -> Result<ZeroizedString, Error> {
+        Handler::decrypt_entry(self, name)
+    }
+
+    fn save(&self, name: &str, plaintext: &[u8]) -> Result<(), Error> {
+        Handler::save(self, name, plaintext)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), Error> {
+        Handler::delete(self, name)
+    }
+
+    fn generate(&self, name: &str, length: usize) -> Result<ZeroizedString, Error> {
+        Handler::generate(self, name, length)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Mode {
+    Browse,
+    Search,
+    Edit,
+    Generate,
+    ConfirmDelete,
+}
+
+/// What the event loop should do after a key press.
+#[derive(PartialEq)]
+pub enum Outcome {
+    Continue,
+    Copy(ZeroizedString),
+    Quit,
+}
+
+/// State of the store browser, independent of the terminal so it can be
+/// driven by simulated key events.
+pub struct Browser {
+    names: Vec<String>,
+    rows: Vec<TreeRow>,
+    selected: usize,
+    preview: Option<ZeroizedString>,
+    revealed: bool,
+    mode: Mode,
+    query: String,
+    input: Zeroizing<String>,
+    status: String,
+}
+
+impl Browser {
+    pub fn new<O: Operations>(ops: &O) -> Result<Self, Error> {
+        let mut browser = Self {
+            names: Vec::new(),
+            rows: Vec::new(),
+            selected: 0,
+            preview: None,
+            revealed: false,
+            mode: Mode::Browse,
+            query: String::new(),
+            input: Zeroizing::new(String::new()),
+            status: HELP.to_owned(),
+        };
+        browser.reload(ops)?;
+        Ok(browser)
+    }
+
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    /// The name of the selected entry, [`None`] if a folder is selected.
+    pub fn selected_name(&self) -> Option<&str> {
+        self.rows.get(self.selected)?.name.as_deref()
+    }
+
+    /// The preview of the selected entry, masked unless revealed.
+    pub fn preview(&self) -> &str {
+        match (&self.preview, self.revealed) {
+            (Some(plaintext), true) => plaintext,
+            _ if self.selected_name().is_some() => MASK,
+            _ => "",
+        }
+    }
+
+    pub fn handle_key<O: Operations>(&mut self, ops: &O, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            return Outcome::Quit;
+        }
+        let result = match self.mode {
+            Mode::Browse => return self.handle_browse_key(ops, key),
+            Mode::Search => {
+                self.handle_search_key(key);
+                Ok(())
+            }
+            Mode::Edit => self.handle_edit_key(ops, key, ctrl),
+            Mode::Generate => self.handle_generate_key(ops, key),
+            Mode::ConfirmDelete => self.handle_delete_key(ops, key),
+        };
+        if let Err(e) = result {
+            self.status = e.to_string();
+        }
+        Outcome::Continue
+    }
+
+    /// Drop all plaintext held by the browser.
+    pub fn clear(&mut self) {
+        self.preview = None;
+        self.revealed = false;
+        self.input.zeroize();
+    }
+
+    fn handle_browse_key<O: Operations>(&mut self, ops: &O, key: KeyEvent) -> Outcome {
+        let result = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Outcome::Quit,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.select(self.selected.saturating_sub(1));
+                Ok(())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.select((self.selected + 1).min(self.rows.len().saturating_sub(1)));
+                Ok(())
+            }
+            KeyCode::Char('r') => self.toggle_reveal(ops),
+            KeyCode::Char('c') => match self.copy(ops) {
+                Ok(Some(value)) => return Outcome::Copy(value),
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            },
+            KeyCode::Char('e') => self.start_edit(ops),
+            KeyCode::Char('d') if self.selected_name().is_some() => {
+                self.mode = Mode::ConfirmDelete;
+                self.status = "Delete the selected entry? (y/n)".to_owned();
+                Ok(())
+            }
+            KeyCode::Char('g') => {
+                self.mode = Mode::Generate;
+                self.input.zeroize();
+                self.status = "Name of the entry to generate: ".to_owned();
+                Ok(())
+            }
+            KeyCode::Char('/') => {
+                self.mode = Mode::Search;
+                self.status = format!("/{}", self.query);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.status = e.to_string();
+        }
+        Outcome::Continue
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.query.clear();
+                self.mode = Mode::Browse;
+            }
+            KeyCode::Enter => self.mode = Mode::Browse,
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Char(c) => self.query.push(c),
+            _ => return,
+        }
+        self.rebuild_rows();
+        self.status = match self.mode {
+            Mode::Search => format!("/{}", self.query),
+            _ => HELP.to_owned(),
+        };
+    }
+
+    fn handle_edit_key<O: Operations>(
+        &mut self,
+        ops: &O,
+        key: KeyEvent,
+        ctrl: bool,
+    ) -> Result<(), Error> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input.zeroize();
+                self.mode = Mode::Browse;
+                self.status = HELP.to_owned();
+            }
+            KeyCode::Char('s') if ctrl => {
+                if let Some(name) = self.selected_name().map(ToOwned::to_owned) {
+                    ops.save(&name, self.input.as_bytes())?;
+                    self.preview = Some(ZeroizedString::new(self.input.to_string()));
+                    self.input.zeroize();
+                    self.mode = Mode::Browse;
+                    self.status = format!("Saved {name}.");
+                }
+            }
+            KeyCode::Enter => self.input.push('\n'),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_generate_key<O: Operations>(&mut self, ops: &O, key: KeyEvent) -> Result<(), Error> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Browse;
+                self.status = HELP.to_owned();
+            }
+            KeyCode::Enter if !self.input.is_empty() => {
+                let name = self.input.to_string();
+                self.mode = Mode::Browse;
+                let password = ops.generate(&name, DEFAULT_PASSWORD_LENGTH)?;
+                self.reload(ops)?;
+                if let Some(row) = self
+                    .rows
+                    .iter()
+                    .position(|r| r.name.as_ref() == Some(&name))
+                {
+                    self.select(row);
+                    self.preview = Some(password);
+                }
+                self.status = format!("Generated {name}.");
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.status = format!("Name of the entry to generate: {}", *self.input);
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.status = format!("Name of the entry to generate: {}", *self.input);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_delete_key<O: Operations>(&mut self, ops: &O, key: KeyEvent) -> Result<(), Error> {
+        self.mode = Mode::Browse;
+        self.status = HELP.to_owned();
+        if let (KeyCode::Char('y'), Some(name)) = (key.code, self.selected_name()) {
+            let name = name.to_owned();
+            ops.delete(&name)?;
+            self.reload(ops)?;
+            self.status = format!("Deleted {name}.");
+        }
+        Ok(())
+    }
+
+    fn toggle_reveal<O: Operations>(&mut self, ops: &O) -> Result<(), Error> {
+        if self.revealed {
+            self.revealed = false;
+            return Ok(());
+        }
+        if self.load_preview(ops)? {
+            self.revealed = true;
+        }
+        Ok(())
+    }
+
+    /// The first line of the selected entry, which holds the password.
+    fn copy<O: Operations>(&mut self, ops: &O) -> Result<Option<ZeroizedString>, Error> {
+        if !self.load_preview(ops)? {
+            return Ok(None);
+        }
+        let password = self
+            .preview
+            .as_ref()
+            .and_then(|plaintext| plaintext.lines().next())
+            .unwrap_or_default();
+        self.status = "Copied to the clipboard.".to_owned();
+        Ok(Some(ZeroizedString::new(password.to_owned())))
+    }
+
+    fn start_edit<O: Operations>(&mut self, ops: &O) -> Result<(), Error> {
+        if !self.load_preview(ops)? {
+            return Ok(());
+        }
+        self.input.zeroize();
+        if let Some(plaintext) = &self.preview {
+            // Reserve up front so edits rarely reallocate, which would leave
+            // copies of the plaintext behind.
+            self.input.reserve(plaintext.len() + 1024);
+            self.input.push_str(plaintext);
+        }
+        self.mode = Mode::Edit;
+        self.status = "Editing, ctrl-s to save, esc to cancel.".to_owned();
+        Ok(())
+    }
+
+    /// Decrypt the selected entry if it is not already, returns false if a
+    /// folder is selected.
+    fn load_preview<O: Operations>(&mut self, ops: &O) -> Result<bool, Error> {
+        let name = match self.selected_name() {
+            Some(name) => name.to_owned(),
+            None => return Ok(false),
+        };
+        if self.preview.is_none() {
+            self.preview = Some(ops.decrypt_entry(&name)?);
+        }
+        Ok(true)
+    }
+
+    fn select(&mut self, row: usize) {
+        if row != self.selected {
+            self.selected = row;
+            self.preview = None;
+            self.revealed = false;
+        }
+    }
+
+    fn reload<O: Operations>(&mut self, ops: &O) -> Result<(), Error> {
+        self.names = ops.entry_names()?;
+        self.rebuild_rows();
+        Ok(())
+    }
+
+    fn rebuild_rows(&mut self) {
+        let names = self
+            .names
+            .iter()
+            .filter(|name| fuzzy::score(&self.query, name).is_some());
+        self.rows = EntryTree::new(names).rows();
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+        self.preview = None;
+        self.revealed = false;
+    }
+}
+
+/// Run the browser full-screen until the user quits, the screen is cleared and
+/// all plaintext is zeroized on exit.
+pub fn browse<O: Operations>(ops: &O) -> anyhow::Result<()> {
+    let mut browser = Browser::new(ops)?;
+    let _screen = RawScreen::enter(io::stdout())?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = run(&mut browser, ops, &mut terminal);
+    browser.clear();
+    // Draw blank frames into both of the terminal's buffers, so no rendered
+    // plaintext is left behind in memory.
+    terminal.draw(|_| {})?;
+    terminal.draw(|_| {})?;
+    terminal.clear()?;
+    execute!(terminal.backend_mut(), Clear(ClearType::All))?;
+    result
+}
+
+fn run<O, W>(
+    browser: &mut Browser,
+    ops: &O,
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+) -> anyhow::Result<()>
+where
+    O: Operations,
+    W: Write,
+{
+    loop {
+        terminal.draw(|f| draw(f, browser))?;
+        if let Event::Key(key) = event::read()? {
+            match browser.handle_key(ops, key) {
+                Outcome::Continue => {}
+                Outcome::Copy(value) => copy_to_clipboard(terminal.backend_mut(), &value)?,
+                Outcome::Quit => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Copy to the system clipboard via the OSC 52 terminal escape sequence, so
+/// the value never leaves the process other than through the terminal.
+fn copy_to_clipboard<W: Write>(out: &mut W, value: &str) -> io::Result<()> {
+    let encoded = Zeroizing::new(base64::encode(value));
+    out.write_all(b"\x1b]52;c;")?;
+    out.write_all(encoded.as_bytes())?;
+    out.write_all(b"\x07")?;
+    out.flush()
+}
+
+fn draw<B: tui::backend::Backend>(f: &mut Frame<B>, browser: &Browser) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(f.size());
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(layout[0]);
+    let items = browser
+        .rows()
+        .iter()
+        .map(|row| {
+            let suffix = if row.name.is_some() { "" } else { "/" };
+            ListItem::new(format!("{}{}{suffix}", "  ".repeat(row.depth), row.label))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Entries"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select((!browser.rows().is_empty()).then_some(browser.selected));
+    f.render_stateful_widget(list, panes[0], &mut state);
+    let preview = match browser.mode() {
+        Mode::Edit => &**browser.input,
+        _ => browser.preview(),
+    };
+    let title = browser.selected_name().unwrap_or("Preview");
+    let preview =
+        Paragraph::new(preview).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(preview, panes[1]);
+    f.render_widget(Paragraph::new(browser.status()), layout[1]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Browser, Mode, Operations, Outcome, MASK};
+    use crate::{error::Error, secrets::ZeroizedString, store::StoreError};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::{cell::RefCell, collections::BTreeMap};
+
+    /// Plaintext in-memory operations, nothing is encrypted.
+    #[derive(Default)]
+    struct FakeOperations {
+        entries: RefCell<BTreeMap<String, String>>,
+    }
+
+    impl FakeOperations {
+        fn with(entries: &[(&str, &str)]) -> Self {
+            Self {
+                entries: RefCell::new(
+                    entries
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    impl Operations for FakeOperations {
+        fn entry_names(&self) -> Result<Vec<String>, Error> {
+            Ok(self.entries.borrow().keys().cloned().collect())
+        }
+
+        fn decrypt_entry(&self, name: &str) -> Result<ZeroizedString, Error> {
+            self.entries
+                .borrow()
+                .get(name)
+                .map(|v| ZeroizedString::new(v.clone()))
+                .ok_or_else(|| StoreError::EntryDoesNotExist(name.to_owned()).into())
+        }
+
+        fn save(&self, name: &str, plaintext: &[u8]) -> Result<(), Error> {
+            self.entries.borrow_mut().insert(
+                name.to_owned(),
+                String::from_utf8(plaintext.to_vec()).unwrap(),
+            );
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<(), Error> {
+            self.entries.borrow_mut().remove(name);
+            Ok(())
+        }
+
+        fn generate(&self, name: &str, length: usize) -> Result<ZeroizedString, Error> {
+            let password = "x".repeat(length);
+            self.save(name, password.as_bytes())?;
+            Ok(ZeroizedString::new(password))
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn press(browser: &mut Browser, ops: &FakeOperations, codes: &[KeyCode]) {
+        for code in codes {
+            browser.handle_key(ops, key(*code));
+        }
+    }
+
+    fn type_text(browser: &mut Browser, ops: &FakeOperations, text: &str) {
+        for c in text.chars() {
+            browser.handle_key(ops, key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn should_show_entries_as_tree_rows() {
+        let ops = FakeOperations::with(&[("api", "a"), ("prod/db", "b")]);
+        let browser = Browser::new(&ops).unwrap();
+        let labels = browser
+            .rows()
+            .iter()
+            .map(|row| row.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["api", "prod", "db"]);
+    }
+
+    #[test]
+    fn preview_should_be_masked_until_revealed() {
+        let ops = FakeOperations::with(&[("api", "secret")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        assert_eq!(browser.preview(), MASK);
+        press(&mut browser, &ops, &[KeyCode::Char('r')]);
+        assert_eq!(browser.preview(), "secret");
+        press(&mut browser, &ops, &[KeyCode::Char('r')]);
+        assert_eq!(browser.preview(), MASK);
+    }
+
+    #[test]
+    fn moving_the_selection_should_hide_the_preview() {
+        let ops = FakeOperations::with(&[("api", "a"), ("web", "b")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        press(&mut browser, &ops, &[KeyCode::Char('r'), KeyCode::Down]);
+        assert_eq!(browser.selected_name(), Some("web"));
+        assert_eq!(browser.preview(), MASK);
+    }
+
+    #[test]
+    fn copy_should_return_the_first_line() {
+        let ops = FakeOperations::with(&[("api", "password\nuser: admin")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        let outcome = browser.handle_key(&ops, key(KeyCode::Char('c')));
+        assert!(outcome == Outcome::Copy(ZeroizedString::new("password".to_owned())));
+    }
+
+    #[test]
+    fn search_should_filter_the_tree() {
+        let ops = FakeOperations::with(&[("api", "a"), ("prod/db", "b")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        press(&mut browser, &ops, &[KeyCode::Char('/')]);
+        type_text(&mut browser, &ops, "db");
+        press(&mut browser, &ops, &[KeyCode::Enter]);
+        assert_eq!(*browser.mode(), Mode::Browse);
+        assert_eq!(browser.rows().len(), 2);
+        assert_eq!(browser.rows()[1].name.as_deref(), Some("prod/db"));
+    }
+
+    #[test]
+    fn edit_should_save_the_new_value() {
+        let ops = FakeOperations::with(&[("api", "old")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        press(&mut browser, &ops, &[KeyCode::Char('e')]);
+        assert_eq!(*browser.mode(), Mode::Edit);
+        press(&mut browser, &ops, &[KeyCode::Backspace; 3]);
+        type_text(&mut browser, &ops, "new");
+        browser.handle_key(
+            &ops,
+            KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+        );
+        assert_eq!(*browser.mode(), Mode::Browse);
+        assert_eq!(ops.entries.borrow()["api"], "new");
+    }
+
+    #[test]
+    fn delete_should_remove_the_entry_once_confirmed() {
+        let ops = FakeOperations::with(&[("api", "a"), ("web", "b")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        press(
+            &mut browser,
+            &ops,
+            &[KeyCode::Char('d'), KeyCode::Char('n')],
+        );
+        assert_eq!(ops.entries.borrow().len(), 2);
+        press(
+            &mut browser,
+            &ops,
+            &[KeyCode::Char('d'), KeyCode::Char('y')],
+        );
+        assert_eq!(ops.entries.borrow().keys().collect::<Vec<_>>(), vec!["web"]);
+        assert_eq!(browser.rows().len(), 1);
+    }
+
+    #[test]
+    fn generate_should_create_and_select_the_entry() {
+        let ops = FakeOperations::default();
+        let mut browser = Browser::new(&ops).unwrap();
+        press(&mut browser, &ops, &[KeyCode::Char('g')]);
+        type_text(&mut browser, &ops, "prod/db");
+        press(&mut browser, &ops, &[KeyCode::Enter]);
+        assert_eq!(browser.selected_name(), Some("prod/db"));
+        assert!(ops.entries.borrow().contains_key("prod/db"));
+    }
+
+    #[test]
+    fn errors_should_be_shown_in_the_status_line() {
+        let ops = FakeOperations::with(&[("api", "a")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        ops.entries.borrow_mut().clear();
+        press(&mut browser, &ops, &[KeyCode::Char('r')]);
+        assert_eq!(browser.status(), r#"The entry "api" does not exist!"#);
+    }
+
+    #[test]
+    fn clear_should_drop_plaintext() {
+        let ops = FakeOperations::with(&[("api", "secret")]);
+        let mut browser = Browser::new(&ops).unwrap();
+        press(&mut browser, &ops, &[KeyCode::Char('r')]);
+        browser.clear();
+        assert_eq!(browser.preview(), MASK);
+    }
+
+    #[test]
+    fn q_should_quit() {
+        let ops = FakeOperations::default();
+        let mut browser = Browser::new(&ops).unwrap();
+        assert!(browser.handle_key(&ops, key(KeyCode::Char('q'))) == Outcome::Quit);
+    }
+}
@@ -1,26 +1,446 @@
+use crate::armor::Encoding;
+use crate::entry::EntryRef;
+use crate::import::ConflictStrategy;
+use crate::secrets::Pinentry;
 use clap::Parser;
+use std::str::FromStr;
 
 #[derive(Parser, Debug)]
 pub struct Args {
+    #[clap(flatten)]
+    pub global: GlobalOptions,
     #[clap(subcommand)]
     pub action: Action,
 }
 
+/// Options which apply to every command.
+#[derive(clap::Args, Debug, Default)]
+pub struct GlobalOptions {
+    /// GnuPG home directory whose keyring to use, in place of the one set in
+    /// your config
+    #[clap(long, global = true)]
+    pub gpg_home: Option<String>,
+    /// how passphrases of secret keys are asked for: `ask` leaves it to
+    /// gpg-agent's pinentry, `loopback` prompts on the terminal and `cancel`
+    /// never asks [default: ask, or loopback with --passphrase-fd]
+    #[clap(long, global = true)]
+    pub pinentry: Option<Pinentry>,
+    /// read passphrases from this file descriptor, one line each in the order
+    /// they are asked for, in loopback mode
+    #[clap(long, global = true)]
+    pub passphrase_fd: Option<i32>,
+}
+
+impl GlobalOptions {
+    /// The pinentry mode to use, reading the passphrase from a file
+    /// descriptor implies loopback mode.
+    pub fn pinentry(&self) -> Pinentry {
+        self.pinentry.unwrap_or(match self.passphrase_fd {
+            Some(_) => Pinentry::Loopback,
+            None => Pinentry::Ask,
+        })
+    }
+}
+
 #[derive(clap::Subcommand, Debug)]
 pub enum Action {
-    /// Initialize the store with the given key id.
+    /// Initialize the store with the given key id, or with an age X25519
+    /// recipient when built with the `age` feature.
     Initialize { key_id: String },
     /// Insert a value of the given name.
     Insert {
         /// name of the entry
         name: String,
         /// key id for the key used to encrypt this entry
-        key_id: String,
+        #[clap(required_unless_present = "symmetric")]
+        key_id: Option<String>,
+        /// encrypt with a passphrase rather than a key, to share the entry
+        /// with someone who has no key
+        #[clap(long, conflicts_with = "key-id")]
+        symmetric: bool,
+        /// read the value from this file, or from stdin if `-`, rather than
+        /// prompting for it, encrypting it as it is read
+        #[clap(short, long, conflicts_with = "symmetric")]
+        file: Option<String>,
     },
 
     /// Retrieve the value of the given name.
     Retrieve {
-        /// name of the secret to retrieve
-        name: String,
+        /// name of the secret to retrieve, pick it interactively if not given
+        name: Option<String>,
+        /// write the value to this file, created with 0600 permissions,
+        /// rather than printing it, decrypting it as it is written
+        #[clap(short, long, requires = "name")]
+        output: Option<String>,
+    },
+
+    /// List all entries in the store.
+    List,
+
+    /// Browse the store in a full-screen terminal interface.
+    Tui,
+
+    /// Find entries whose names match any of the given patterns.
+    Find {
+        /// glob patterns, or fuzzy patterns if they contain no glob characters
+        #[clap(required = true)]
+        patterns: Vec<String>,
+    },
+
+    /// Run a command with secrets injected as environment variables.
+    Run {
+        /// variable to set, as `NAME=entry` or `NAME=entry:field`, without a
+        /// field the first line of the entry is used
+        #[clap(long = "env", required = true)]
+        env: Vec<EnvBinding>,
+        /// the command to run, followed by its arguments
+        #[clap(required = true, last = true)]
+        command: Vec<String>,
+    },
+
+    /// Render a template, replacing placeholders with decrypted values.
+    ///
+    /// Placeholders are written as `{{ sec "entry" }}` or
+    /// `{{ sec "entry" field="name" }}`, without a field the first line of the
+    /// entry is used.
+    Render {
+        /// path of the template to render
+        template: String,
+        /// path to write the rendered template to, created with 0600 permissions
+        #[clap(short, long, required_unless_present = "check")]
+        output: Option<String>,
+        /// only report references to entries which do not exist, nothing is
+        /// decrypted
+        #[clap(long)]
+        check: bool,
+    },
+
+    /// Export entries to, or import them from, `.env` files.
+    Env {
+        #[clap(subcommand)]
+        action: EnvAction,
+    },
+
+    /// Create, or restore from, an encrypted backup of the whole store.
+    Backup {
+        #[clap(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Import entries from another password manager.
+    Import {
+        #[clap(subcommand)]
+        source: ImportSource,
+    },
+
+    /// Search the decrypted contents of every entry.
+    Grep {
+        /// text to search for
+        pattern: String,
+        /// treat the pattern as a regular expression
+        #[clap(short = 'E', long)]
+        regex: bool,
+        /// ignore case when matching
+        #[clap(short, long)]
+        ignore_case: bool,
+    },
+
+    /// Check every entry is intact and encrypted to the keys in its
+    /// `.gpg-id` file, and look for stray files, loose permissions and empty
+    /// folders.
+    Fsck {
+        /// repair what can be without losing anything, corrupt entries and
+        /// stray files are only reported
+        #[clap(long)]
+        repair: bool,
+    },
+
+    /// Report on the store for compliance.
+    Audit {
+        #[clap(subcommand)]
+        report: AuditReport,
+    },
+
+    /// List, import and export keys in the GnuPG keyring.
+    Keys {
+        #[clap(subcommand)]
+        action: KeysAction,
+    },
+
+    /// Rewrite every entry as a binary or ASCII armored message, without
+    /// decrypting it, and encrypt new entries that way from then on.
+    Convert {
+        /// `binary` for `.gpg` files, or `armored` for `.asc` files
+        encoding: Encoding,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum KeysAction {
+    /// List the keys entries can be encrypted to, with their fingerprint,
+    /// user id, expiry and trust.
+    List,
+    /// Import the keys in a file, armored or not, into the keyring.
+    Import {
+        /// path of the file of keys to import
+        file: String,
+    },
+    /// Print a public key, ASCII armored, to share with others.
+    Export {
+        /// fingerprint, key id or user id of the key to export
+        key_id: String,
+    },
+    /// Replace a key in every `.gpg-id` file listing it, and re-encrypt the
+    /// entries they apply to.
+    Rotate {
+        /// the key to replace
+        old: String,
+        /// the key to replace it with
+        new: String,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum AuditReport {
+    /// Show who each entry is encrypted to, without decrypting it, along
+    /// with keys missing from or not in its `.gpg-id` file and keys which
+    /// have expired or been revoked.
+    Recipients {
+        /// folder whose entries to report on, every entry if not given
+        folder: Option<String>,
+        /// write the report as JSON rather than a table
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum EnvAction {
+    /// Print every entry in a folder as `KEY=value` lines.
+    Export {
+        /// folder whose entries to export, names are converted to
+        /// SCREAMING_SNAKE_CASE
+        folder: String,
+    },
+    /// Save each variable in a `.env` file as its own entry.
+    Import {
+        /// path of the `.env` file to import
+        file: String,
+        /// folder to save the entries in
+        #[clap(long)]
+        prefix: Option<String>,
+        /// replace entries which already exist in the store
+        #[clap(long)]
+        force: bool,
     },
 }
+
+#[derive(clap::Subcommand, Debug)]
+pub enum BackupAction {
+    /// Write every entry and `.gpg-id` file, with a manifest of their
+    /// checksums, to a single archive encrypted to the backup key.
+    Create {
+        /// path to write the backup to, such as `backup.tar.gpg`
+        output: String,
+        /// key id of the key to encrypt the backup to
+        #[clap(long)]
+        key: String,
+    },
+    /// Restore entries and `.gpg-id` files from a backup, once its manifest
+    /// has been verified.
+    Restore {
+        /// path of the backup to restore
+        file: String,
+        /// replace entries which already exist in the store
+        #[clap(long)]
+        overwrite: bool,
+        /// restore `.gpg-id` files which are not signed by a trusted signing
+        /// key, signing them
+        #[clap(long)]
+        trust_gpg_id: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ImportSource {
+    /// Import every entry and `.gpg-id` file from a `pass` password store.
+    Pass {
+        /// the password store directory, usually `~/.password-store`
+        dir: String,
+        /// replace `.gpg-id` files which already exist in the store and list
+        /// other keys
+        #[clap(long)]
+        replace_gpg_ids: bool,
+        /// import `.gpg-id` files which are not signed by a trusted signing
+        /// key, signing them
+        #[clap(long)]
+        trust_gpg_id: bool,
+        #[clap(flatten)]
+        options: ImportOptions,
+    },
+    /// Import a KeePass 2 XML export.
+    #[clap(name = "keepass")]
+    KeePass(ExportArgs),
+    /// Import an unencrypted Bitwarden JSON export.
+    Bitwarden(ExportArgs),
+    /// Import a 1Password CSV export.
+    #[clap(name = "1password")]
+    OnePassword(ExportArgs),
+    /// Import a Chrome CSV export.
+    Chrome(ExportArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// path of the exported file
+    pub file: String,
+    #[clap(flatten)]
+    pub options: ImportOptions,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ImportOptions {
+    /// report what would be imported without changing the store
+    #[clap(long)]
+    pub dry_run: bool,
+    /// what to do with entries which already exist, `skip`, `overwrite` or
+    /// `rename`
+    #[clap(long, default_value = "skip")]
+    pub on_conflict: ConflictStrategy,
+    /// folder to import the entries into
+    #[clap(long)]
+    pub prefix: Option<String>,
+}
+
+impl ImportOptions {
+    /// `name` placed under the prefix, if one was given.
+    pub fn prefixed(&self, name: &str) -> String {
+        match self
+            .prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_matches('/'))
+        {
+            Some(prefix) if !prefix.is_empty() => format!("{prefix}/{name}"),
+            _ => name.to_owned(),
+        }
+    }
+}
+
+/// An environment variable bound to an entry, or one of its fields, written as
+/// `NAME=entry` or `NAME=entry:field`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvBinding {
+    pub var: String,
+    pub entry: EntryRef,
+}
+
+impl FromStr for EnvBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (var, entry) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `NAME=entry[:field]`, got `{s}`"))?;
+        if var.is_empty() {
+            return Err(format!("no variable name given in `{s}`"));
+        }
+        Ok(Self {
+            var: var.to_owned(),
+            entry: entry.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Args, Encoding, EnvBinding};
+    use crate::secrets::Pinentry;
+    use clap::Parser;
+
+    #[test]
+    fn should_parse_env_binding() {
+        let binding: EnvBinding = "API_KEY=prod/api:token".parse().unwrap();
+        assert_eq!(binding.var, "API_KEY");
+        assert_eq!(binding.entry.name, "prod/api");
+        assert_eq!(binding.entry.field.as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn should_reject_env_binding_without_variable() {
+        assert!("prod/db".parse::<EnvBinding>().is_err());
+        assert!("=prod/db".parse::<EnvBinding>().is_err());
+    }
+
+    #[test]
+    fn usage_errors_should_be_told_apart_from_help() {
+        let err = Args::try_parse_from(["sec", "retrieve", "--no-such-flag"]).unwrap_err();
+        assert!(err.use_stderr());
+        let err = Args::try_parse_from(["sec", "--help"]).unwrap_err();
+        assert!(!err.use_stderr());
+    }
+
+    #[test]
+    fn gpg_home_should_be_accepted_before_or_after_the_command() {
+        for args in [
+            ["sec", "--gpg-home", "/srv/keyring", "list"],
+            ["sec", "list", "--gpg-home", "/srv/keyring"],
+        ] {
+            let args = Args::try_parse_from(args).unwrap();
+            assert!(matches!(args.action, Action::List));
+            assert_eq!(args.global.gpg_home.as_deref(), Some("/srv/keyring"));
+        }
+    }
+
+    #[test]
+    fn passphrase_fd_should_imply_loopback_pinentry() {
+        let args = Args::try_parse_from(["sec", "list"]).unwrap();
+        assert_eq!(args.global.pinentry(), Pinentry::Ask);
+        let args = Args::try_parse_from(["sec", "--passphrase-fd", "3", "list"]).unwrap();
+        assert_eq!(args.global.pinentry(), Pinentry::Loopback);
+        let args = Args::try_parse_from(["sec", "--pinentry", "cancel", "list"]).unwrap();
+        assert_eq!(args.global.pinentry(), Pinentry::Cancel);
+    }
+
+    #[test]
+    fn convert_should_parse_the_encoding() {
+        let args = Args::try_parse_from(["sec", "convert", "armored"]).unwrap();
+        assert!(matches!(
+            args.action,
+            Action::Convert {
+                encoding: Encoding::Armored
+            }
+        ));
+        assert!(Args::try_parse_from(["sec", "convert", "ascii"]).is_err());
+    }
+
+    #[test]
+    fn files_should_only_be_streamed_for_named_entries() {
+        let args = Args::try_parse_from(["sec", "insert", "db", "key", "-f", "dump.sql"]).unwrap();
+        assert!(matches!(
+            args.action,
+            Action::Insert { file: Some(file), .. } if file == "dump.sql"
+        ));
+        assert!(Args::try_parse_from(["sec", "insert", "--symmetric", "db", "-f", "-"]).is_err());
+        let args = Args::try_parse_from(["sec", "retrieve", "db", "-o", "dump.sql"]).unwrap();
+        assert!(matches!(
+            args.action,
+            Action::Retrieve { output: Some(output), .. } if output == "dump.sql"
+        ));
+        assert!(Args::try_parse_from(["sec", "retrieve", "-o", "dump.sql"]).is_err());
+    }
+
+    #[test]
+    fn insert_should_require_a_key_id_unless_symmetric() {
+        assert!(Args::try_parse_from(["sec", "insert", "name"]).is_err());
+        assert!(Args::try_parse_from(["sec", "insert", "name", "key", "--symmetric"]).is_err());
+        let args = Args::try_parse_from(["sec", "insert", "--symmetric", "name"]).unwrap();
+        assert!(matches!(
+            args.action,
+            Action::Insert {
+                key_id: None,
+                symmetric: true,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,310 @@
+use std::{path::Path, time::Duration};
+use thiserror::Error;
+
+/// Name of the file, at the root of a store, holding its settings.
+pub const CONFIG_FILE: &str = ".sec-config";
+/// Path of the file, within the user's config directory, holding the
+/// settings which are theirs alone rather than shared through the store.
+pub const USER_CONFIG_FILE: &str = "sec/config";
+/// How many days before a recipient's key expires to start warning about
+/// it, unless the store's config says otherwise.
+pub const DEFAULT_EXPIRY_WARNING_DAYS: u64 = 30;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read the config file")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: expected `key = value`")]
+    InvalidLine { line: usize },
+    #[error("line {line}: unknown setting `{key}`")]
+    UnknownKey { line: usize, key: String },
+    #[error("line {line}: `{key}` must be a whole number")]
+    InvalidNumber { line: usize, key: String },
+    #[error("line {line}: `{key}` must be `true` or `false`")]
+    InvalidBool { line: usize, key: String },
+    #[error("line {line}: `{key}` can only be set in your own config, not the store's")]
+    UserSetting { line: usize, key: String },
+    #[error("line {line}: `{key}` must be an absolute path")]
+    RelativePath { line: usize, key: String },
+    #[error("line {line}: `{key}` must not be inside the store")]
+    InsideStore { line: usize, key: String },
+}
+
+/// Settings of a store, shared by everyone using it as they live in the
+/// store itself.
+#[derive(Debug, Default, PartialEq)]
+pub struct StoreConfig {
+    /// How many days before a recipient's key expires to start warning.
+    pub expiry_warning_days: Option<u64>,
+    /// Whether entries are encrypted to ASCII armored messages, saved as
+    /// `.asc` files, rather than binary ones.
+    pub armor: bool,
+}
+
+impl StoreConfig {
+    /// Read the config file of the store at `store_dir`, a store without one
+    /// has the default settings.
+    pub fn load(store_dir: &str) -> Result<Self, ConfigError> {
+        let path = Path::new(store_dir).join(CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// How long before a recipient's key expires to start warning about it.
+    pub fn expiry_warning(&self) -> Duration {
+        let days = self
+            .expiry_warning_days
+            .unwrap_or(DEFAULT_EXPIRY_WARNING_DAYS);
+        Duration::from_secs(days * 24 * 60 * 60)
+    }
+}
+
+/// Settings of the user running `sec`, which anyone able to write to the
+/// store must not be able to change, such as whose keyring is trusted.
+#[derive(Debug, Default, PartialEq)]
+pub struct UserConfig {
+    /// The GnuPG home directory whose keyring is used for the store.
+    pub gpg_home: Option<String>,
+}
+
+impl UserConfig {
+    /// Read the user's config file at `path`, the default settings are used
+    /// without one. Paths must be absolute and outside the store at
+    /// `store_dir`, so no one else can choose the keyring.
+    pub fn load(path: &Path, store_dir: &str) -> Result<Self, ConfigError> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let mut config = Self::default();
+        for setting in settings(&std::fs::read_to_string(path)?) {
+            let (line, key, value) = setting?;
+            match key {
+                "gpg-home" => {
+                    let key = key.to_owned();
+                    if !Path::new(value).is_absolute() {
+                        return Err(ConfigError::RelativePath { line, key });
+                    }
+                    if is_inside(Path::new(value), Path::new(store_dir)) {
+                        return Err(ConfigError::InsideStore { line, key });
+                    }
+                    config.gpg_home = Some(value.to_owned());
+                }
+                _ => {
+                    return Err(ConfigError::UnknownKey {
+                        line,
+                        key: key.to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Whether `path` is `dir` or inside it, resolving `..` and symbolic links
+/// as far as `path` exists.
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_owned());
+    path.ancestors()
+        .find_map(|ancestor| {
+            let rest = path.strip_prefix(ancestor).ok()?;
+            Some(ancestor.canonicalize().ok()?.join(rest))
+        })
+        .unwrap_or_else(|| path.to_owned())
+        .starts_with(dir)
+}
+
+/// Set `key` to `value` in the config file of the store at `store_dir`,
+/// replacing the line which sets it if there is one, and leaving the rest of
+/// the file as it was.
+pub fn save_setting(store_dir: &str, key: &str, value: &str) -> Result<(), ConfigError> {
+    let path = Path::new(store_dir).join(CONFIG_FILE);
+    let contents = if path.is_file() {
+        std::fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    let setting = format!("{key} = {value}");
+    let mut saved = false;
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let sets_key = !line.trim_start().starts_with('#')
+            && line
+                .split_once('=')
+                .is_some_and(|(line_key, _)| line_key.trim() == key);
+        if sets_key {
+            lines.push(setting.as_str());
+            saved = true;
+        } else {
+            lines.push(line);
+        }
+    }
+    if !saved {
+        lines.push(&setting);
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    parse(&contents)?;
+    Ok(std::fs::write(path, contents)?)
+}
+
+/// The settings in the contents of a config file, one `key = value` setting
+/// per line, with the number of the line each is on. Blank lines and `#`
+/// comments are skipped.
+fn settings(contents: &str) -> impl Iterator<Item = Result<(usize, &str, &str), ConfigError>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            line.split_once('=')
+                .map(|(key, value)| (line_number, key.trim(), value.trim()))
+                .filter(|(_, key, value)| !key.is_empty() && !value.is_empty())
+                .ok_or(ConfigError::InvalidLine { line: line_number })
+        })
+}
+
+/// Parse the contents of a store's config file, see [`settings`].
+pub fn parse(contents: &str) -> Result<StoreConfig, ConfigError> {
+    let mut config = StoreConfig::default();
+    for setting in settings(contents) {
+        let (line_number, key, value) = setting?;
+        match key {
+            "gpg-home" => {
+                return Err(ConfigError::UserSetting {
+                    line: line_number,
+                    key: key.to_owned(),
+                })
+            }
+            "expiry-warning-days" => {
+                let days = value.parse().map_err(|_| ConfigError::InvalidNumber {
+                    line: line_number,
+                    key: key.to_owned(),
+                })?;
+                config.expiry_warning_days = Some(days);
+            }
+            "armor" => {
+                config.armor = value.parse().map_err(|_| ConfigError::InvalidBool {
+                    line: line_number,
+                    key: key.to_owned(),
+                })?;
+            }
+            _ => {
+                return Err(ConfigError::UnknownKey {
+                    line: line_number,
+                    key: key.to_owned(),
+                })
+            }
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, save_setting, ConfigError, StoreConfig, UserConfig, CONFIG_FILE};
+    use std::{path::Path, time::Duration};
+    use tempfile::tempdir;
+
+    #[test]
+    fn should_parse_settings_skipping_comments_and_blank_lines() {
+        let config = parse("# warn a week ahead\n\nexpiry-warning-days = 7\n").unwrap();
+        assert_eq!(config.expiry_warning_days, Some(7));
+    }
+
+    #[test]
+    fn should_reject_unknown_settings_and_invalid_lines() {
+        assert!(matches!(
+            parse("expiry_warning_days = 7"),
+            Err(ConfigError::UnknownKey { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse("\narmor"),
+            Err(ConfigError::InvalidLine { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn expiry_warning_should_default_to_thirty_days() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(StoreConfig::default().expiry_warning(), 30 * day);
+        let config = parse("expiry-warning-days = 7").unwrap();
+        assert_eq!(config.expiry_warning(), 7 * day);
+        assert!(matches!(
+            parse("expiry-warning-days = soon"),
+            Err(ConfigError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn armor_should_be_off_unless_set() {
+        assert!(!StoreConfig::default().armor);
+        assert!(parse("armor = true").unwrap().armor);
+        assert!(matches!(
+            parse("armor = yes"),
+            Err(ConfigError::InvalidBool { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn save_setting_should_replace_only_the_line_setting_it() {
+        let dir = tempdir().unwrap();
+        let store_dir = dir.path().to_str().unwrap();
+        save_setting(store_dir, "armor", "true").unwrap();
+        assert!(StoreConfig::load(store_dir).unwrap().armor);
+        let path = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &path,
+            "# armor = true\narmor = true\nexpiry-warning-days = 7\n",
+        )
+        .unwrap();
+        save_setting(store_dir, "armor", "false").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "# armor = true\narmor = false\nexpiry-warning-days = 7\n"
+        );
+    }
+
+    #[test]
+    fn gpg_home_should_only_be_set_by_the_user_outside_the_store() {
+        let dir = tempdir().unwrap();
+        let store_dir = dir.path().join("store");
+        std::fs::create_dir(&store_dir).unwrap();
+        let store_dir = store_dir.to_str().unwrap();
+        std::fs::write(
+            Path::new(store_dir).join(CONFIG_FILE),
+            "gpg-home = /srv/keyring\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            StoreConfig::load(store_dir),
+            Err(ConfigError::UserSetting { line: 1, .. })
+        ));
+        let path = dir.path().join("config");
+        assert_eq!(
+            UserConfig::load(&path, store_dir).unwrap(),
+            UserConfig::default()
+        );
+        let load_with = |contents: String| {
+            std::fs::write(&path, contents).unwrap();
+            UserConfig::load(&path, store_dir)
+        };
+        assert!(matches!(
+            load_with("gpg-home = .gnupg\n".to_owned()),
+            Err(ConfigError::RelativePath { line: 1, .. })
+        ));
+        assert!(matches!(
+            load_with(format!("gpg-home = {store_dir}/.gnupg\n")),
+            Err(ConfigError::InsideStore { line: 1, .. })
+        ));
+        assert!(matches!(
+            load_with(format!("gpg-home = {store_dir}/../store/.gnupg\n")),
+            Err(ConfigError::InsideStore { line: 1, .. })
+        ));
+        let config = load_with("gpg-home = /srv/keyring\n".to_owned()).unwrap();
+        assert_eq!(config.gpg_home.as_deref(), Some("/srv/keyring"));
+    }
+}
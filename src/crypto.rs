@@ -0,0 +1,235 @@
+#[cfg(feature = "age")]
+pub mod age;
+#[cfg(feature = "sequoia")]
+pub mod sequoia;
+
+#[cfg(feature = "gpg")]
+use crate::gpg::{Gpg, GpgError};
+use crate::secrets::ZeroizedByteVec;
+use std::{
+    error::Error as StdError,
+    io::{Read, Write},
+    time::SystemTime,
+};
+use thiserror::Error;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("Unable to find key with id {0}!")]
+    KeyNotFound(String),
+    #[error("`{0}` is not a valid recipient")]
+    InvalidRecipient(String),
+    #[error("`{recipient}` cannot be encrypted to, {reason}")]
+    UnusableRecipient { recipient: String, reason: String },
+    #[error("Failed to decrypt the given ciphertext")]
+    DecryptionFailed(#[source] BoxError),
+    #[error("Encountered an error in the encryption backend")]
+    Backend(#[source] BoxError),
+}
+
+#[cfg(feature = "gpg")]
+impl From<GpgError> for CryptoError {
+    fn from(error: GpgError) -> Self {
+        match error {
+            GpgError::KeyNotFound(key_id) => CryptoError::KeyNotFound(key_id),
+            GpgError::UnusableKey { key_id, problem } => CryptoError::UnusableRecipient {
+                recipient: key_id,
+                reason: problem.to_string(),
+            },
+            GpgError::DecryptionFailed(e) => CryptoError::DecryptionFailed(Box::new(e)),
+            GpgError::NotUtf8(e) => CryptoError::DecryptionFailed(Box::new(e)),
+            GpgError::Gpgme(e) => CryptoError::Backend(Box::new(e)),
+        }
+    }
+}
+
+/// A recipient as it was given, such as the user id of a key, resolved to
+/// what entries are encrypted to.
+#[derive(Debug, PartialEq)]
+pub struct ResolvedRecipient {
+    /// What entries are encrypted to, such as the fingerprint of a key.
+    pub recipient: String,
+    /// Other recipients matched by what was given, when it was ambiguous.
+    pub alternatives: Vec<String>,
+}
+
+/// The encryption entries are stored with. Each backend has its own file
+/// extension for entries, and its own file listing the recipients of the
+/// entries in a folder, so stores of different backends are never mixed up.
+pub trait CryptoBackend: Sync {
+    /// The extension of entry files, such as `.gpg`.
+    const ENTRY_EXTENSION: &'static str;
+    /// The name of the file listing the recipients of a folder, such as
+    /// `.gpg-id`.
+    const RECIPIENTS_FILE: &'static str;
+
+    /// Encrypt `plaintext` so any of the `recipients` can decrypt it.
+    fn encrypt<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Decrypt `ciphertext` with any of the identities available.
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, CryptoError>;
+
+    /// Encrypt everything read from `plaintext` as [`CryptoBackend::encrypt`]
+    /// does, writing the result to `ciphertext`. By default the plaintext is
+    /// read into memory first, backends which can encrypt as they read
+    /// override this.
+    fn encrypt_stream<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &mut (dyn Read + Send),
+        ciphertext: &mut (dyn Write + Send),
+    ) -> Result<(), CryptoError> {
+        let mut buf = ZeroizedByteVec::new(Vec::new());
+        plaintext
+            .read_to_end(&mut buf)
+            .map_err(|e| CryptoError::Backend(Box::new(e)))?;
+        ciphertext
+            .write_all(&self.encrypt(recipients, &buf)?)
+            .map_err(|e| CryptoError::Backend(Box::new(e)))
+    }
+
+    /// Decrypt everything read from `ciphertext` as
+    /// [`CryptoBackend::decrypt`] does, writing the result to `plaintext`. By
+    /// default the ciphertext is read into memory first, backends which can
+    /// decrypt as they read override this.
+    fn decrypt_stream(
+        &self,
+        ciphertext: &mut (dyn Read + Send),
+        plaintext: &mut (dyn Write + Send),
+    ) -> Result<(), CryptoError> {
+        let mut buf = Vec::new();
+        ciphertext
+            .read_to_end(&mut buf)
+            .map_err(|e| CryptoError::Backend(Box::new(e)))?;
+        plaintext
+            .write_all(&self.decrypt(&buf)?)
+            .map_err(|e| CryptoError::Backend(Box::new(e)))
+    }
+
+    /// Check `recipient` is one entries can be encrypted to.
+    fn validate_recipient(&self, recipient: &str) -> Result<(), CryptoError>;
+
+    /// Resolve `recipient` to what is written to recipients files and
+    /// encrypted to. By default it is checked and used as given.
+    fn resolve_recipient(&self, recipient: &str) -> Result<ResolvedRecipient, CryptoError> {
+        self.validate_recipient(recipient)?;
+        Ok(ResolvedRecipient {
+            recipient: recipient.to_owned(),
+            alternatives: Vec::new(),
+        })
+    }
+
+    /// When `recipient` expires, if it ever does.
+    fn expires(&self, _recipient: &str) -> Option<SystemTime> {
+        None
+    }
+
+    /// The fingerprint of the key `recipient` identifies, whether or not it
+    /// can still be encrypted to, for backends whose recipients are keys.
+    fn fingerprint(&self, _recipient: &str) -> Option<String> {
+        None
+    }
+
+    /// The primary user id of the key `recipient` identifies, such as
+    /// `Name <email>`, for backends whose recipients are keys.
+    fn user_id(&self, _recipient: &str) -> Option<String> {
+        None
+    }
+
+    /// The OpenPGP implementation, for what only OpenPGP stores support,
+    /// such as signed `.gpg-id` files and reading the recipients of a
+    /// message without decrypting it.
+    #[cfg(feature = "gpg")]
+    fn openpgp(&self) -> Option<&Gpg> {
+        None
+    }
+}
+
+#[cfg(feature = "gpg")]
+impl CryptoBackend for Gpg {
+    const ENTRY_EXTENSION: &'static str = ".gpg";
+    const RECIPIENTS_FILE: &'static str = ".gpg-id";
+
+    fn encrypt<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        Ok(Gpg::encrypt(self, recipients, plaintext)?)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, CryptoError> {
+        Ok(self.decrypt_bytes(ciphertext)?)
+    }
+
+    fn encrypt_stream<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &mut (dyn Read + Send),
+        ciphertext: &mut (dyn Write + Send),
+    ) -> Result<(), CryptoError> {
+        Ok(Gpg::encrypt_stream(
+            self, recipients, plaintext, ciphertext,
+        )?)
+    }
+
+    fn decrypt_stream(
+        &self,
+        ciphertext: &mut (dyn Read + Send),
+        plaintext: &mut (dyn Write + Send),
+    ) -> Result<(), CryptoError> {
+        Ok(Gpg::decrypt_stream(self, ciphertext, plaintext)?)
+    }
+
+    fn validate_recipient(&self, recipient: &str) -> Result<(), CryptoError> {
+        Ok(self.does_key_exist(recipient)?)
+    }
+
+    /// Resolves user ids and key ids to the fingerprint of the key, so a
+    /// different key with the same user id is never picked up later.
+    fn resolve_recipient(&self, recipient: &str) -> Result<ResolvedRecipient, CryptoError> {
+        let mut fingerprints = self.fingerprints(recipient)?;
+        let recipient = fingerprints.remove(0);
+        Ok(ResolvedRecipient {
+            recipient,
+            alternatives: fingerprints,
+        })
+    }
+
+    fn expires(&self, recipient: &str) -> Option<SystemTime> {
+        self.key_info(recipient).ok()?.expires
+    }
+
+    fn fingerprint(&self, recipient: &str) -> Option<String> {
+        Some(self.key_info(recipient).ok()?.fingerprint)
+    }
+
+    fn user_id(&self, recipient: &str) -> Option<String> {
+        self.key_info(recipient).ok()?.user_id
+    }
+
+    fn openpgp(&self) -> Option<&Gpg> {
+        Some(self)
+    }
+}
+
+#[cfg(all(test, feature = "gpg"))]
+mod test {
+    use super::CryptoError;
+    use crate::gpg::GpgError;
+
+    #[test]
+    fn gpg_errors_should_keep_their_meaning() {
+        let error = CryptoError::from(GpgError::KeyNotFound("key".to_owned()));
+        assert!(matches!(&error, CryptoError::KeyNotFound(key_id) if key_id == "key"));
+        assert_eq!(error.to_string(), "Unable to find key with id key!");
+        let error = CryptoError::from(GpgError::DecryptionFailed(gpgme::Error::DECRYPT_FAILED));
+        assert!(matches!(error, CryptoError::DecryptionFailed(_)));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    crypto::{CryptoBackend, CryptoError},
+    secrets::ZeroizedByteVec,
+};
+use age::x25519;
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+use zeroize::Zeroizing;
+
+/// Name of the file listing the X25519 recipients of a folder's entries.
+pub const AGE_RECIPIENTS_FILE: &str = ".age-recipients";
+
+/// Encryption with `age`, to X25519 recipients such as those printed by
+/// `age-keygen`, as used by `passage`.
+pub struct Age {
+    /// File of the identities entries are decrypted with, it is read on
+    /// each decryption so it is only needed when something is decrypted.
+    identity_file: PathBuf,
+}
+
+impl Age {
+    pub fn new<P: Into<PathBuf>>(identity_file: P) -> Self {
+        Self {
+            identity_file: identity_file.into(),
+        }
+    }
+
+    /// Read the X25519 identities from the identity file, in the format
+    /// written by `age-keygen`, one `AGE-SECRET-KEY-1` key per line with
+    /// blank lines and `#` comments skipped.
+    fn identities(&self) -> Result<Vec<x25519::Identity>, CryptoError> {
+        let path = self.identity_file.display();
+        let contents =
+            Zeroizing::new(std::fs::read_to_string(&self.identity_file).map_err(|e| {
+                CryptoError::Backend(format!("failed to read the identity file {path}, {e}").into())
+            })?);
+        let identities = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                x25519::Identity::from_str(line).map_err(|reason| {
+                    CryptoError::Backend(
+                        format!("the identity file {path} has an invalid identity, {reason}")
+                            .into(),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if identities.is_empty() {
+            return Err(CryptoError::Backend(
+                format!("the identity file {path} has no identities").into(),
+            ));
+        }
+        Ok(identities)
+    }
+}
+
+impl CryptoBackend for Age {
+    const ENTRY_EXTENSION: &'static str = ".age";
+    const RECIPIENTS_FILE: &'static str = AGE_RECIPIENTS_FILE;
+
+    fn encrypt<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let recipients = recipients
+            .iter()
+            .map(|recipient| parse_recipient(recipient.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let encryptor = age::Encryptor::with_recipients(
+            recipients
+                .iter()
+                .map(|recipient| recipient as &dyn age::Recipient),
+        )
+        .map_err(|e| CryptoError::Backend(Box::new(e)))?;
+        let mut ciphertext = Vec::new();
+        let write = || -> std::io::Result<()> {
+            let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+            writer.write_all(plaintext)?;
+            writer.finish()?;
+            Ok(())
+        };
+        write().map_err(|e| CryptoError::Backend(Box::new(e)))?;
+        Ok(ciphertext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, CryptoError> {
+        let identities = self.identities()?;
+        let decryptor = age::Decryptor::new_buffered(ciphertext)
+            .map_err(|e| CryptoError::DecryptionFailed(Box::new(e)))?;
+        let mut reader = decryptor
+            .decrypt(
+                identities
+                    .iter()
+                    .map(|identity| identity as &dyn age::Identity),
+            )
+            .map_err(|e| CryptoError::DecryptionFailed(Box::new(e)))?;
+        let mut plaintext = ZeroizedByteVec::new(Vec::new());
+        reader
+            .read_to_end(&mut plaintext)
+            .map_err(|e| CryptoError::DecryptionFailed(Box::new(e)))?;
+        Ok(plaintext)
+    }
+
+    fn validate_recipient(&self, recipient: &str) -> Result<(), CryptoError> {
+        parse_recipient(recipient).map(|_| ())
+    }
+}
+
+/// Whether `recipient` is an X25519 recipient, so a store initialized with
+/// it uses `age`.
+pub fn is_recipient(recipient: &str) -> bool {
+    parse_recipient(recipient).is_ok()
+}
+
+fn parse_recipient(recipient: &str) -> Result<x25519::Recipient, CryptoError> {
+    x25519::Recipient::from_str(recipient)
+        .map_err(|_| CryptoError::InvalidRecipient(recipient.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_recipient, Age};
+    use crate::crypto::{CryptoBackend, CryptoError};
+    use age::{secrecy::ExposeSecret, x25519::Identity};
+    use tempfile::{tempdir, TempDir};
+
+    /// An identity file with a new identity, and its recipient.
+    fn identity_file() -> (TempDir, Age, String) {
+        let dir = tempdir().unwrap();
+        let identity = Identity::generate();
+        let path = dir.path().join("identities");
+        let contents = format!(
+            "# created: for the tests\n{}\n",
+            identity.to_string().expose_secret()
+        );
+        std::fs::write(&path, contents).unwrap();
+        (dir, Age::new(path), identity.to_public().to_string())
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_should_be_isomorphic() {
+        let (_dir, age, recipient) = identity_file();
+        let ciphertext = age.encrypt(&[&recipient], b"hunter2").unwrap();
+        let plaintext = age.decrypt(&ciphertext).unwrap();
+        assert_eq!(plaintext.as_ref(), b"hunter2");
+    }
+
+    #[test]
+    fn should_not_decrypt_entries_for_other_identities() {
+        let (_dir, age, _) = identity_file();
+        let (_other_dir, other, recipient) = identity_file();
+        let ciphertext = other.encrypt(&[recipient], b"hunter2").unwrap();
+        assert!(matches!(
+            age.decrypt(&ciphertext),
+            Err(CryptoError::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn only_x25519_recipients_should_be_valid() {
+        let (_dir, age, recipient) = identity_file();
+        assert!(age.validate_recipient(&recipient).is_ok());
+        assert!(is_recipient(&recipient));
+        assert!(matches!(
+            age.validate_recipient("passrs-tests@nocht.io"),
+            Err(CryptoError::InvalidRecipient(_))
+        ));
+    }
+}
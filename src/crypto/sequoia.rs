@@ -0,0 +1,454 @@
+use crate::{
+    crypto::{CryptoBackend, CryptoError, ResolvedRecipient},
+    secrets::{ZeroizedByteVec, ZeroizedString},
+};
+use anyhow::anyhow;
+use sequoia_openpgp::{
+    cert::CertParser,
+    crypto::{Password, SessionKey},
+    packet::{
+        key::{SecretParts, UnspecifiedRole},
+        Key, PKESK, SKESK,
+    },
+    parse::{
+        stream::{DecryptionHelper, DecryptorBuilder, MessageStructure, VerificationHelper},
+        Parse,
+    },
+    policy::StandardPolicy,
+    serialize::stream::{Armorer, Encryptor2, LiteralWriter, Message},
+    types::SymmetricAlgorithm,
+    Cert, Fingerprint, KeyHandle,
+};
+use std::{
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// Asked for the passphrase of the secret key with the given fingerprint,
+/// when one is needed to decrypt an entry.
+pub type PassphraseProvider = Box<dyn Fn(&str) -> io::Result<ZeroizedString> + Send + Sync>;
+
+/// A pure Rust implementation of the OpenPGP encryption `gpg` does for the
+/// store, with keys read from files rather than a GnuPG keyring. Entries are
+/// interchangeable with those encrypted by `gpg`.
+pub struct Sequoia {
+    certs: Vec<Cert>,
+    policy: StandardPolicy<'static>,
+    passphrase: PassphraseProvider,
+    /// Whether messages are ASCII armored rather than binary.
+    armor: bool,
+    /// Secret keys already unlocked with their passphrase, so it is only
+    /// asked for once.
+    unlocked: Mutex<Vec<Key<SecretParts, UnspecifiedRole>>>,
+}
+
+impl Sequoia {
+    pub fn new(certs: Vec<Cert>) -> Self {
+        Self {
+            certs,
+            policy: StandardPolicy::new(),
+            passphrase: Box::new(|fingerprint| {
+                Err(io::Error::other(format!(
+                    "no passphrase was given for the key {fingerprint}"
+                )))
+            }),
+            armor: false,
+            unlocked: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Read the keys in `paths`, each an exported key file, such as from
+    /// `gpg --export-secret-keys`, or a directory of them. A key in more than
+    /// one file, as with a public and a secret key export, is merged.
+    pub fn from_paths<I: IntoIterator<Item = PathBuf>>(paths: I) -> Result<Self, CryptoError> {
+        let mut certs: Vec<Cert> = Vec::new();
+        for path in paths {
+            for file in key_files(&path).map_err(|e| read_error(&path, e.into()))? {
+                let parser = CertParser::from_file(&file).map_err(|e| read_error(&file, e))?;
+                for cert in parser {
+                    let cert = cert.map_err(|e| read_error(&file, e))?;
+                    match certs
+                        .iter()
+                        .position(|existing| existing.fingerprint() == cert.fingerprint())
+                    {
+                        Some(index) => {
+                            let existing = certs.remove(index);
+                            let merged = existing
+                                .merge_public_and_secret(cert)
+                                .map_err(|e| read_error(&file, e))?;
+                            certs.push(merged);
+                        }
+                        None => certs.push(cert),
+                    }
+                }
+            }
+        }
+        Ok(Self::new(certs))
+    }
+
+    /// Ask `passphrase` for the passphrase of secret keys which are
+    /// protected by one.
+    pub fn with_passphrase<F>(mut self, passphrase: F) -> Self
+    where
+        F: Fn(&str) -> io::Result<ZeroizedString> + Send + Sync + 'static,
+    {
+        self.passphrase = Box::new(passphrase);
+        self
+    }
+
+    /// Write the messages encrypted with [`CryptoBackend::encrypt`] ASCII
+    /// armored if `armor` is set, so they can be read as text.
+    pub fn with_armor(mut self, armor: bool) -> Self {
+        self.armor = armor;
+        self
+    }
+
+    /// The key identified by `key_id`, as with `gpg` either the fingerprint
+    /// or key id of the key or one of its subkeys, or part of a user id.
+    fn find_cert(&self, key_id: &str) -> Option<&Cert> {
+        self.find_certs(key_id).next()
+    }
+
+    /// Every key identified by `key_id`, as [`Sequoia::find_cert`] finds
+    /// the first.
+    fn find_certs<'a>(&'a self, key_id: &str) -> impl Iterator<Item = &'a Cert> + 'a {
+        let handle = key_id.trim_start_matches("0x").parse::<KeyHandle>().ok();
+        let user_id = key_id.to_lowercase();
+        self.certs.iter().filter(move |cert| match &handle {
+            Some(handle) => cert
+                .keys()
+                .any(|key| key.key().key_handle().aliases(handle)),
+            None => cert.userids().any(|uid| {
+                String::from_utf8_lossy(uid.userid().value())
+                    .to_lowercase()
+                    .contains(&user_id)
+            }),
+        })
+    }
+
+    /// The expiry of each of the keys of `cert` which can be encrypted to,
+    /// `None` for those which never expire.
+    fn encryption_key_expiry(&self, cert: &Cert) -> Vec<Option<SystemTime>> {
+        cert.keys()
+            .with_policy(&self.policy, None)
+            .supported()
+            .alive()
+            .revoked(false)
+            .for_storage_encryption()
+            .for_transport_encryption()
+            .map(|key| key.key_expiration_time())
+            .collect()
+    }
+
+    /// The secret key `key`, unlocked with its passphrase if it has one.
+    fn unlock(
+        &self,
+        key: &Key<SecretParts, UnspecifiedRole>,
+    ) -> anyhow::Result<Key<SecretParts, UnspecifiedRole>> {
+        if key.has_unencrypted_secret() {
+            return Ok(key.clone());
+        }
+        let mut unlocked = self
+            .unlocked
+            .lock()
+            .map_err(|_| anyhow!("the unlocked keys are poisoned"))?;
+        if let Some(key) = unlocked
+            .iter()
+            .find(|unlocked| unlocked.fingerprint() == key.fingerprint())
+        {
+            return Ok(key.clone());
+        }
+        let passphrase = (self.passphrase)(&key.fingerprint().to_hex())?;
+        let key = key.clone().decrypt_secret(&Password::from(&*passphrase))?;
+        unlocked.push(key.clone());
+        Ok(key)
+    }
+}
+
+impl CryptoBackend for Sequoia {
+    const ENTRY_EXTENSION: &'static str = ".gpg";
+    const RECIPIENTS_FILE: &'static str = ".gpg-id";
+
+    fn encrypt<S: AsRef<str>>(
+        &self,
+        recipients: &[S],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let mut keys = Vec::new();
+        for recipient in recipients {
+            let recipient = recipient.as_ref();
+            let cert = self
+                .find_cert(recipient)
+                .ok_or_else(|| CryptoError::KeyNotFound(recipient.to_owned()))?;
+            let count = keys.len();
+            keys.extend(
+                cert.keys()
+                    .with_policy(&self.policy, None)
+                    .supported()
+                    .alive()
+                    .revoked(false)
+                    .for_storage_encryption()
+                    .for_transport_encryption(),
+            );
+            if keys.len() == count {
+                return Err(CryptoError::KeyNotFound(recipient.to_owned()));
+            }
+        }
+        let mut ciphertext = Vec::new();
+        let armor = self.armor;
+        let write = || -> anyhow::Result<()> {
+            let mut message = Message::new(&mut ciphertext);
+            if armor {
+                message = Armorer::new(message).build()?;
+            }
+            let message = Encryptor2::for_recipients(message, keys).build()?;
+            let mut message = LiteralWriter::new(message).build()?;
+            message.write_all(plaintext)?;
+            message.finalize()?;
+            Ok(())
+        };
+        write().map_err(|e| CryptoError::Backend(e.into()))?;
+        Ok(ciphertext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, CryptoError> {
+        let mut plaintext = ZeroizedByteVec::new(Vec::new());
+        let read = |plaintext: &mut Vec<u8>| -> anyhow::Result<()> {
+            let mut decryptor = DecryptorBuilder::from_bytes(ciphertext)?.with_policy(
+                &self.policy,
+                None,
+                Helper { sequoia: self },
+            )?;
+            decryptor.read_to_end(plaintext)?;
+            Ok(())
+        };
+        read(&mut *plaintext).map_err(|e| CryptoError::DecryptionFailed(e.into()))?;
+        Ok(plaintext)
+    }
+
+    fn validate_recipient(&self, recipient: &str) -> Result<(), CryptoError> {
+        let has_encryption_key = self
+            .find_cert(recipient)
+            .is_some_and(|cert| !self.encryption_key_expiry(cert).is_empty());
+        if !has_encryption_key {
+            return Err(CryptoError::KeyNotFound(recipient.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Resolves user ids and key ids to the fingerprint of the key, as with
+    /// gpg, skipping keys which cannot be encrypted to.
+    fn resolve_recipient(&self, recipient: &str) -> Result<ResolvedRecipient, CryptoError> {
+        let mut fingerprints = self
+            .find_certs(recipient)
+            .filter(|cert| !self.encryption_key_expiry(cert).is_empty())
+            .map(|cert| cert.fingerprint().to_hex())
+            .collect::<Vec<_>>();
+        if fingerprints.is_empty() {
+            return Err(CryptoError::KeyNotFound(recipient.to_owned()));
+        }
+        let recipient = fingerprints.remove(0);
+        Ok(ResolvedRecipient {
+            recipient,
+            alternatives: fingerprints,
+        })
+    }
+
+    /// When the last of the keys `recipient` can be encrypted to expires,
+    /// since it can still be encrypted to until then.
+    fn expires(&self, recipient: &str) -> Option<SystemTime> {
+        let expiry = self.encryption_key_expiry(self.find_cert(recipient)?);
+        expiry
+            .into_iter()
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+
+    fn fingerprint(&self, recipient: &str) -> Option<String> {
+        Some(self.find_cert(recipient)?.fingerprint().to_hex())
+    }
+
+    fn user_id(&self, recipient: &str) -> Option<String> {
+        let cert = self
+            .find_cert(recipient)?
+            .with_policy(&self.policy, None)
+            .ok()?;
+        let user_id = cert.primary_userid().ok()?;
+        Some(String::from_utf8_lossy(user_id.userid().value()).into_owned())
+    }
+}
+
+/// Finds the secret key a message is encrypted to while decrypting it.
+/// Entries are not signed, so there is nothing to verify.
+struct Helper<'a> {
+    sequoia: &'a Sequoia,
+}
+
+impl VerificationHelper for Helper<'_> {
+    fn get_certs(&mut self, _: &[KeyHandle]) -> anyhow::Result<Vec<Cert>> {
+        Ok(Vec::new())
+    }
+
+    fn check(&mut self, _: MessageStructure) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl DecryptionHelper for Helper<'_> {
+    fn decrypt<D>(
+        &mut self,
+        pkesks: &[PKESK],
+        _: &[SKESK],
+        sym_algo: Option<SymmetricAlgorithm>,
+        mut decrypt: D,
+    ) -> anyhow::Result<Option<Fingerprint>>
+    where
+        D: FnMut(SymmetricAlgorithm, &SessionKey) -> bool,
+    {
+        for pkesk in pkesks {
+            for cert in &self.sequoia.certs {
+                for key in cert.keys().secret() {
+                    let recipient = pkesk.recipient();
+                    if !recipient.is_wildcard() && *recipient != key.key().keyid() {
+                        continue;
+                    }
+                    let mut keypair = self.sequoia.unlock(key.key())?.into_keypair()?;
+                    let decrypted = pkesk
+                        .decrypt(&mut keypair, sym_algo)
+                        .is_some_and(|(algo, session_key)| decrypt(algo, &session_key));
+                    if decrypted {
+                        return Ok(Some(cert.fingerprint()));
+                    }
+                }
+            }
+        }
+        Err(anyhow!(
+            "there is no secret key for any of the keys the message is encrypted to"
+        ))
+    }
+}
+
+/// The files in `path` if it is a directory, skipping hidden files, or else
+/// `path` itself.
+fn key_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files = std::fs::read_dir(path)?
+        .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    files.retain(|file| {
+        file.is_file()
+            && !file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'))
+    });
+    files.sort();
+    Ok(files)
+}
+
+fn read_error(path: &Path, error: anyhow::Error) -> CryptoError {
+    CryptoError::Backend(format!("failed to read keys from {}, {error}", path.display()).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sequoia;
+    #[cfg(feature = "gpg")]
+    use crate::gpg::{test::import_keys, Gpg};
+    use crate::{
+        armor,
+        crypto::{CryptoBackend, CryptoError},
+        secrets::ZeroizedString,
+    };
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    const RESOURCES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/resources");
+    /// The user id of the test key.
+    const GPG_KEY_ID: &str = "passrs-tests@nocht.io";
+
+    /// The test key from its public and secret key exports, the secret key
+    /// is protected by the passphrase `abc`.
+    fn sequoia() -> Sequoia {
+        let paths = ["public.key", "secret.key"]
+            .iter()
+            .map(|file| PathBuf::from(RESOURCES).join(file));
+        Sequoia::from_paths(paths)
+            .unwrap()
+            .with_passphrase(|_| Ok(ZeroizedString::new("abc".to_owned())))
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_should_be_isomorphic() {
+        let sequoia = sequoia();
+        let ciphertext = sequoia.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        let plaintext = sequoia.decrypt(&ciphertext).unwrap();
+        assert_eq!(plaintext.as_ref(), b"hunter2");
+    }
+
+    #[test]
+    fn armored_messages_should_decrypt_as_binary_ones_do() {
+        let sequoia = sequoia().with_armor(true);
+        let ciphertext = sequoia.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        assert!(armor::is_armored(&ciphertext));
+        let plaintext = sequoia.decrypt(&ciphertext).unwrap();
+        assert_eq!(plaintext.as_ref(), b"hunter2");
+    }
+
+    #[test]
+    fn recipients_should_resolve_to_the_fingerprint_of_their_key() {
+        let sequoia = sequoia();
+        let resolved = sequoia.resolve_recipient(GPG_KEY_ID).unwrap();
+        assert_eq!(
+            Some(resolved.recipient.clone()),
+            sequoia.fingerprint(GPG_KEY_ID)
+        );
+        assert_eq!(resolved.recipient.len(), 40);
+        assert!(resolved.alternatives.is_empty());
+        assert!(sequoia.resolve_recipient("unknown@example.com").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "gpg")]
+    fn gpg_should_decrypt_what_sequoia_encrypts() {
+        import_keys();
+        let ciphertext = sequoia().encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        let plaintext = Gpg::new().decrypt(&ciphertext).unwrap();
+        assert_eq!(&*plaintext, "hunter2");
+    }
+
+    #[test]
+    #[cfg(feature = "gpg")]
+    fn sequoia_should_decrypt_what_gpg_encrypts() {
+        import_keys();
+        let ciphertext = Gpg::new().encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        let plaintext = sequoia().decrypt(&ciphertext).unwrap();
+        assert_eq!(plaintext.as_ref(), b"hunter2");
+    }
+
+    #[test]
+    fn should_read_keys_from_a_keyring_directory() {
+        let dir = tempdir().unwrap();
+        std::fs::copy(
+            PathBuf::from(RESOURCES).join("public.key"),
+            dir.path().join("public.key"),
+        )
+        .unwrap();
+        let sequoia = Sequoia::from_paths([dir.path().to_owned()]).unwrap();
+        assert!(sequoia.validate_recipient(GPG_KEY_ID).is_ok());
+        assert!(matches!(
+            sequoia.validate_recipient("unknown@example.com"),
+            Err(CryptoError::KeyNotFound(_))
+        ));
+        let ciphertext = sequoia.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        assert!(matches!(
+            sequoia.decrypt(&ciphertext),
+            Err(CryptoError::DecryptionFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::secrets::ZeroizedString;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+#[derive(Error, Debug)]
+pub enum DotenvError {
+    #[error("Failed to read the env file")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: expected `KEY=value`")]
+    InvalidLine { line: usize },
+    #[error("line {line}: `{key}` is not a valid variable name")]
+    InvalidKey { line: usize, key: String },
+    #[error("line {line}: missing closing `{quote}`")]
+    UnterminatedQuote { line: usize, quote: char },
+}
+
+/// A single `KEY=value` pair from a `.env` file.
+#[derive(PartialEq)]
+pub struct Variable {
+    pub key: String,
+    pub value: ZeroizedString,
+}
+
+/// Convert an entry name to a SCREAMING_SNAKE_CASE variable name, so
+/// `db/readOnly-password` becomes `DB_READ_ONLY_PASSWORD`.
+pub fn variable_name(name: &str) -> String {
+    let mut variable = String::with_capacity(name.len());
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !variable.is_empty() && !variable.ends_with('_') {
+                variable.push('_');
+            }
+        } else {
+            let is_boundary = c.is_ascii_uppercase()
+                && previous
+                    .filter(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+                    .is_some();
+            if is_boundary && !variable.ends_with('_') {
+                variable.push('_');
+            }
+            variable.push(c.to_ascii_uppercase());
+        }
+        previous = Some(c);
+    }
+    let variable = variable.trim_end_matches('_');
+    if variable.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{variable}")
+    } else {
+        variable.to_owned()
+    }
+}
+
+/// Format a variable as a `KEY=value` line. Values which would not survive
+/// being read back unquoted are double quoted, with `\`, `"`, `$` and newlines
+/// escaped.
+pub fn format_line(key: &str, value: &str) -> ZeroizedString {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '#' | '$' | '`'));
+    let mut line = Zeroizing::new(String::with_capacity(key.len() + value.len() * 2 + 4));
+    line.push_str(key);
+    line.push('=');
+    if needs_quotes {
+        line.push('"');
+        for c in value.chars() {
+            match c {
+                '\n' => line.push_str("\\n"),
+                '"' | '\\' | '$' | '`' => {
+                    line.push('\\');
+                    line.push(c);
+                }
+                c => line.push(c),
+            }
+        }
+        line.push('"');
+    } else {
+        line.push_str(value);
+    }
+    ZeroizedString::new(line.to_string())
+}
+
+/// Parse the contents of a `.env` file. Blank lines and `#` comments are
+/// skipped, an `export ` prefix is allowed, and values may be unquoted, single
+/// quoted (taken literally) or double quoted (with `\` escapes).
+pub fn parse(contents: &str) -> Result<Vec<Variable>, DotenvError> {
+    let mut variables = Vec::new();
+    for (i, raw) in contents.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let trimmed = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+        let (key, value) = trimmed
+            .split_once('=')
+            .ok_or(DotenvError::InvalidLine { line })?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(DotenvError::InvalidKey {
+                line,
+                key: key.to_owned(),
+            });
+        }
+        variables.push(Variable {
+            key: key.to_owned(),
+            value: parse_value(value.trim(), line)?,
+        });
+    }
+    Ok(variables)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(value: &str, line: usize) -> Result<ZeroizedString, DotenvError> {
+    let mut parsed = Zeroizing::new(String::with_capacity(value.len()));
+    match value.chars().next() {
+        Some(quote @ '\'') => {
+            let end = value[1..]
+                .find(quote)
+                .ok_or(DotenvError::UnterminatedQuote { line, quote })?;
+            parsed.push_str(&value[1..end + 1]);
+        }
+        Some(quote @ '"') => {
+            let mut chars = value[1..].chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => parsed.push('\n'),
+                        Some(c) => parsed.push(c),
+                        None => return Err(DotenvError::UnterminatedQuote { line, quote }),
+                    },
+                    Some(c) => parsed.push(c),
+                    None => return Err(DotenvError::UnterminatedQuote { line, quote }),
+                }
+            }
+        }
+        _ => {
+            let value = match value.find(" #") {
+                Some(comment) => value[..comment].trim_end(),
+                None => value,
+            };
+            parsed.push_str(value);
+        }
+    }
+    Ok(ZeroizedString::new(parsed.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_line, parse, variable_name, DotenvError};
+
+    #[test]
+    fn should_convert_names_to_screaming_snake_case() {
+        assert_eq!(variable_name("password"), "PASSWORD");
+        assert_eq!(
+            variable_name("db/readOnly-password"),
+            "DB_READ_ONLY_PASSWORD"
+        );
+        assert_eq!(variable_name("api.key"), "API_KEY");
+        assert_eq!(variable_name("2fa"), "_2FA");
+    }
+
+    #[test]
+    fn should_quote_values_only_when_needed() {
+        assert_eq!(&*format_line("TOKEN", "abc123"), "TOKEN=abc123");
+        assert_eq!(&*format_line("PASS", r#"a b"c$d"#), r#"PASS="a b\"c\$d""#);
+        assert_eq!(&*format_line("EMPTY", ""), r#"EMPTY="""#);
+    }
+
+    #[test]
+    fn should_parse_variables_skipping_comments_and_blank_lines() {
+        let variables = parse(
+            r#"
+# database
+export DB_USER=admin
+DB_PASS="hunter \"2\""
+LITERAL='$not\n escaped'
+PORT=5432 # default
+"#,
+        )
+        .unwrap();
+        let pairs = variables
+            .iter()
+            .map(|v| (v.key.as_str(), &*v.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                ("DB_USER", "admin"),
+                ("DB_PASS", r#"hunter "2""#),
+                ("LITERAL", r"$not\n escaped"),
+                ("PORT", "5432"),
+            ]
+        );
+    }
+
+    #[test]
+    fn formatted_lines_should_parse_back_to_the_same_value() {
+        let value = "multi\nline \"$value\" \\ with # hash";
+        let line = format_line("KEY", value);
+        let variables = parse(&line).unwrap();
+        assert_eq!(&*variables[0].value, value);
+    }
+
+    #[test]
+    fn should_error_on_invalid_lines() {
+        assert!(matches!(
+            parse("OK=1\nnot a variable"),
+            Err(DotenvError::InvalidLine { line: 2 })
+        ));
+        assert!(matches!(
+            parse("1KEY=value"),
+            Err(DotenvError::InvalidKey { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse(r#"KEY="open"#),
+            Err(DotenvError::UnterminatedQuote {
+                line: 1,
+                quote: '"'
+            })
+        ));
+    }
+}
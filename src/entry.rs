@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+/// Name of the field holding the first line of an entry.
+pub const PASSWORD_FIELD: &str = "password";
+
+/// A reference to an entry, or to a single field of an entry, written as
+/// `name` or `name:field`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryRef {
+    pub name: String,
+    pub field: Option<String>,
+}
+
+impl FromStr for EntryRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, field) = match s.split_once(':') {
+            Some((name, field)) => (name, Some(field.to_owned())),
+            None => (s, None),
+        };
+        if name.is_empty() {
+            return Err(format!("no entry name given in `{s}`"));
+        }
+        if field.as_deref() == Some("") {
+            return Err(format!("no field name given in `{s}`"));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            field,
+        })
+    }
+}
+
+/// Look up a field of a decrypted entry.
+///
+/// Entries follow the `pass` convention, the first line is the password and
+/// any following lines are `key: value` fields. The `password` field always
+/// refers to the first line.
+pub fn field<'a>(plaintext: &'a str, field: &str) -> Option<&'a str> {
+    let mut lines = plaintext.lines();
+    let password = lines.next();
+    if field == PASSWORD_FIELD {
+        return password;
+    }
+    lines.find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == field).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{field, EntryRef};
+
+    const ENTRY: &str = "hunter2\nusername: admin\nurl: https://db.local:5432";
+
+    #[test]
+    fn should_parse_entry_ref_without_field() {
+        let entry: EntryRef = "prod/db".parse().unwrap();
+        assert_eq!(entry.name, "prod/db");
+        assert_eq!(entry.field, None);
+    }
+
+    #[test]
+    fn should_parse_entry_ref_with_field() {
+        let entry: EntryRef = "prod/api:token".parse().unwrap();
+        assert_eq!(entry.name, "prod/api");
+        assert_eq!(entry.field.as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn should_reject_entry_ref_with_empty_parts() {
+        assert!(":token".parse::<EntryRef>().is_err());
+        assert!("prod/api:".parse::<EntryRef>().is_err());
+    }
+
+    #[test]
+    fn password_field_should_be_the_first_line() {
+        assert_eq!(field(ENTRY, "password"), Some("hunter2"));
+    }
+
+    #[test]
+    fn should_find_named_fields() {
+        assert_eq!(field(ENTRY, "username"), Some("admin"));
+        assert_eq!(field(ENTRY, "url"), Some("https://db.local:5432"));
+    }
+
+    #[test]
+    fn should_return_none_for_missing_field() {
+        assert_eq!(field(ENTRY, "token"), None);
+    }
+}
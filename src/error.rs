@@ -0,0 +1,285 @@
+#[cfg(feature = "gpg")]
+use crate::gpg::GpgError;
+use crate::{
+    armor::ArmorError, backup::BackupError, config::ConfigError, crypto::CryptoError,
+    dotenv::DotenvError, import::ImportError, store::StoreError, template::TemplateError,
+};
+use thiserror::Error;
+
+/// Exit code for any failure not covered by a more specific code.
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code when the requested entry does not exist in the store.
+pub const EXIT_ENTRY_DOES_NOT_EXIST: u8 = 2;
+/// Exit code when an entry could not be decrypted.
+pub const EXIT_DECRYPTION_FAILED: u8 = 3;
+/// Exit code when a key, or recipient, could not be found or cannot be
+/// encrypted to.
+pub const EXIT_KEY_NOT_FOUND: u8 = 4;
+/// Exit code when the store is not, or could not be, initialized.
+pub const EXIT_STORE_NOT_INITIALIZED: u8 = 5;
+/// Exit code when the arguments are invalid, as `EX_USAGE` in `sysexits.h`.
+/// clap exits with 2 on its own, which would read as a missing entry.
+pub const EXIT_USAGE: u8 = 64;
+
+/// Top level error returned by `sec`, each failure maps to an exit code via
+/// [`Error::exit_code`].
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("An error occurred when attempting to insert the entry `{name}`.")]
+    Insert {
+        name: String,
+        #[source]
+        source: StoreError,
+    },
+    #[error("An error occurred when attempting to retrieve the entry `{name}`.")]
+    Retrieve {
+        name: String,
+        #[source]
+        source: StoreError,
+    },
+    #[error(r#"The entry "{name}" could not be decrypted!"#)]
+    Decrypt {
+        name: String,
+        #[source]
+        source: CryptoError,
+    },
+    #[error(r#"The entry "{name}" holds binary data, retrieve it with `--output`!"#)]
+    BinaryEntry { name: String },
+    #[error(r#"The entry "{name}" has no field "{field}"!"#)]
+    FieldDoesNotExist { name: String, field: String },
+    #[error("Failed to run `{command}`")]
+    Run {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to render the template `{path}`")]
+    Template {
+        path: String,
+        #[source]
+        source: TemplateError,
+    },
+    #[error("The template references entries which do not exist: {}", .0.join(", "))]
+    UnresolvedReferences(Vec<String>),
+    #[error("Failed to import the env file `{path}`")]
+    Dotenv {
+        path: String,
+        #[source]
+        source: DotenvError,
+    },
+    #[error("Failed to import `{path}`")]
+    Import {
+        path: String,
+        #[source]
+        source: ImportError,
+    },
+    #[error("The backup `{path}` is invalid")]
+    Backup {
+        path: String,
+        #[source]
+        source: BackupError,
+    },
+    #[error("Refusing to encrypt to the keys in `{path}`")]
+    KeyIdSignature {
+        path: String,
+        #[source]
+        source: SignatureError,
+    },
+    #[error("The store config `{path}` is invalid")]
+    Config {
+        path: String,
+        #[source]
+        source: ConfigError,
+    },
+    #[error("`{setting}` is not supported when the store is encrypted with {backend}")]
+    UnsupportedSetting {
+        setting: String,
+        backend: &'static str,
+    },
+    #[error("The entry `{name}` could not be converted")]
+    Convert {
+        name: String,
+        #[source]
+        source: ArmorError,
+    },
+    #[error("Failed to write `{path}`")]
+    Write {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[cfg(feature = "gpg")]
+    #[error(transparent)]
+    Gpg(#[from] GpgError),
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+    #[error(transparent)]
+    Init(#[from] InitError),
+    #[error("The search pattern is invalid")]
+    Pattern(#[from] regex::Error),
+    #[error("The glob pattern is invalid")]
+    Glob(#[from] glob::PatternError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// The process exit code for this error.
+    ///
+    /// | code | meaning                                   |
+    /// |------|-------------------------------------------|
+    /// | 1    | unexpected failure                        |
+    /// | 2    | entry, or entry field, does not exist     |
+    /// | 3    | decryption failed                         |
+    /// | 4    | key not found                             |
+    /// | 5    | store not initialized or init failed      |
+    /// | 64   | invalid arguments, see [`EXIT_USAGE`]     |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Insert { source, .. }
+            | Error::Retrieve { source, .. }
+            | Error::Store(source) => store_exit_code(source),
+            Error::FieldDoesNotExist { .. } | Error::UnresolvedReferences(_) => {
+                EXIT_ENTRY_DOES_NOT_EXIST
+            }
+            Error::Decrypt { .. } => EXIT_DECRYPTION_FAILED,
+            #[cfg(feature = "gpg")]
+            Error::Gpg(source) => gpg_exit_code(source),
+            Error::Crypto(source) => crypto_exit_code(source),
+            Error::Init(_) => EXIT_STORE_NOT_INITIALIZED,
+            Error::Run { .. }
+            | Error::Template { .. }
+            | Error::Write { .. }
+            | Error::BinaryEntry { .. }
+            | Error::Dotenv { .. }
+            | Error::Import { .. }
+            | Error::Backup { .. }
+            | Error::Config { .. }
+            | Error::UnsupportedSetting { .. }
+            | Error::Convert { .. }
+            | Error::KeyIdSignature { .. }
+            | Error::Pattern(_)
+            | Error::Glob(_)
+            | Error::Other(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+fn store_exit_code(error: &StoreError) -> u8 {
+    match error {
+        StoreError::EntryDoesNotExist(_) => EXIT_ENTRY_DOES_NOT_EXIST,
+        StoreError::IoError(_) | StoreError::InvalidName(_) => EXIT_FAILURE,
+        StoreError::NotInitialized(_) => EXIT_STORE_NOT_INITIALIZED,
+    }
+}
+
+#[cfg(feature = "gpg")]
+fn gpg_exit_code(error: &GpgError) -> u8 {
+    match error {
+        GpgError::KeyNotFound(_) | GpgError::UnusableKey { .. } => EXIT_KEY_NOT_FOUND,
+        GpgError::DecryptionFailed(_) | GpgError::NotUtf8(_) => EXIT_DECRYPTION_FAILED,
+        GpgError::Gpgme(_) => EXIT_FAILURE,
+    }
+}
+
+fn crypto_exit_code(error: &CryptoError) -> u8 {
+    match error {
+        CryptoError::KeyNotFound(_)
+        | CryptoError::InvalidRecipient(_)
+        | CryptoError::UnusableRecipient { .. } => EXIT_KEY_NOT_FOUND,
+        CryptoError::DecryptionFailed(_) => EXIT_DECRYPTION_FAILED,
+        CryptoError::Backend(_) => EXIT_FAILURE,
+    }
+}
+
+/// Errors which can occur when initializing the store.
+#[derive(Error, Debug)]
+pub enum InitError {
+    #[error("no home directory could be found...")]
+    HomeDirectoryNotFound,
+    #[error("failed to convert path to utf-8 string")]
+    NonUtf8Path,
+    #[error("Failed to create the store at {path}")]
+    CreateStore {
+        path: String,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Why the signature of a `.gpg-id` file could not be trusted.
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("it has no signature")]
+    Missing,
+    #[error("its signature is invalid")]
+    Invalid(#[source] Option<CryptoError>),
+    #[error("it is signed by {}, which is not a trusted signing key", .0.join(", "))]
+    UntrustedSigner(Vec<String>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Error, InitError, EXIT_ENTRY_DOES_NOT_EXIST, EXIT_FAILURE, EXIT_KEY_NOT_FOUND,
+        EXIT_STORE_NOT_INITIALIZED,
+    };
+    use crate::{crypto::CryptoError, store::StoreError};
+    #[cfg(feature = "gpg")]
+    use crate::{error::EXIT_DECRYPTION_FAILED, gpg::GpgError};
+    use anyhow::anyhow;
+
+    #[test]
+    fn missing_entry_should_have_its_own_exit_code() {
+        let error = Error::Retrieve {
+            name: "name".to_owned(),
+            source: StoreError::EntryDoesNotExist("name".to_owned()),
+        };
+        assert_eq!(error.exit_code(), EXIT_ENTRY_DOES_NOT_EXIST);
+    }
+
+    #[test]
+    fn unresolved_template_references_should_use_the_missing_entry_exit_code() {
+        let error = Error::UnresolvedReferences(vec!["prod/db".to_owned()]);
+        assert_eq!(error.exit_code(), EXIT_ENTRY_DOES_NOT_EXIST);
+        assert_eq!(
+            error.to_string(),
+            "The template references entries which do not exist: prod/db"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "gpg")]
+    fn decryption_failure_should_have_its_own_exit_code() {
+        let error = Error::Decrypt {
+            name: "name".to_owned(),
+            source: GpgError::DecryptionFailed(gpgme::Error::DECRYPT_FAILED).into(),
+        };
+        assert_eq!(error.exit_code(), EXIT_DECRYPTION_FAILED);
+    }
+
+    #[test]
+    fn missing_key_should_have_its_own_exit_code() {
+        #[cfg(feature = "gpg")]
+        {
+            let error = Error::from(GpgError::KeyNotFound("key".to_owned()));
+            assert_eq!(error.exit_code(), EXIT_KEY_NOT_FOUND);
+        }
+        let error = Error::from(CryptoError::InvalidRecipient("age1".to_owned()));
+        assert_eq!(error.exit_code(), EXIT_KEY_NOT_FOUND);
+    }
+
+    #[test]
+    fn init_failure_should_have_its_own_exit_code() {
+        let error = Error::from(InitError::HomeDirectoryNotFound);
+        assert_eq!(error.exit_code(), EXIT_STORE_NOT_INITIALIZED);
+    }
+
+    #[test]
+    fn unexpected_errors_should_use_the_generic_exit_code() {
+        let error = Error::from(anyhow!("unexpected"));
+        assert_eq!(error.exit_code(), EXIT_FAILURE);
+    }
+}
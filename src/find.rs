@@ -0,0 +1,86 @@
+use crate::fuzzy;
+use glob::{Pattern, PatternError};
+
+/// A pattern matched against entry names, either a glob, if it contains any
+/// glob metacharacters, or a fuzzy pattern otherwise.
+pub enum NamePattern {
+    Glob(Pattern),
+    Fuzzy(String),
+}
+
+impl NamePattern {
+    const GLOB_METACHARACTERS: [char; 3] = ['*', '?', '['];
+
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        if pattern.contains(&Self::GLOB_METACHARACTERS[..]) {
+            Ok(Self::Glob(Pattern::new(pattern)?))
+        } else {
+            Ok(Self::Fuzzy(pattern.to_owned()))
+        }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => pattern.matches(name),
+            Self::Fuzzy(pattern) => fuzzy::score(pattern, name).is_some(),
+        }
+    }
+}
+
+/// Filter `names` down to those matching any of the `patterns`.
+pub fn find<'a>(patterns: &[NamePattern], names: &'a [String]) -> Vec<&'a String> {
+    names
+        .iter()
+        .filter(|name| patterns.iter().any(|pattern| pattern.is_match(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find, NamePattern};
+
+    fn names() -> Vec<String> {
+        vec!["api".to_owned(), "prod/db".to_owned(), "dev/db".to_owned()]
+    }
+
+    #[test]
+    fn should_treat_patterns_with_metacharacters_as_globs() {
+        assert!(matches!(
+            NamePattern::new("prod/*").unwrap(),
+            NamePattern::Glob(_)
+        ));
+        assert!(matches!(
+            NamePattern::new("prod").unwrap(),
+            NamePattern::Fuzzy(_)
+        ));
+    }
+
+    #[test]
+    fn should_find_names_matching_glob() {
+        let names = names();
+        let patterns = vec![NamePattern::new("prod/*").unwrap()];
+        assert_eq!(find(&patterns, &names), vec!["prod/db"]);
+    }
+
+    #[test]
+    fn should_find_names_matching_fuzzy_pattern() {
+        let names = names();
+        let patterns = vec![NamePattern::new("dvdb").unwrap()];
+        assert_eq!(find(&patterns, &names), vec!["dev/db"]);
+    }
+
+    #[test]
+    fn should_find_names_matching_any_pattern() {
+        let names = names();
+        let patterns = vec![
+            NamePattern::new("api").unwrap(),
+            NamePattern::new("dev/*").unwrap(),
+        ];
+        assert_eq!(find(&patterns, &names), vec!["api", "dev/db"]);
+    }
+
+    #[test]
+    fn should_error_on_invalid_glob() {
+        assert!(NamePattern::new("[").is_err());
+    }
+}
@@ -1,9 +1,17 @@
 use anyhow::Context;
 use std::{
-    fs::File,
+    fs::{DirBuilder, File, OpenOptions, Permissions},
+    io::{self, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
     path::{Path, PathBuf},
 };
 
+/// Files written by `sec`, in the store or out of it such as rendered
+/// templates and backups, are readable and writable only by their owner.
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+/// As with `pass`, folders in the store are accessible only by their owner.
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+
 pub trait FileSystemOperator {
     fn home_dir(&self) -> Option<PathBuf>;
     fn mkdir<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()>;
@@ -18,21 +26,155 @@ impl FileSystemOperator for FileSystemOperations {
     }
 
     fn mkdir<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
-        std::fs::create_dir(path).with_context(|| "failed to create directory {path}")
+        DirBuilder::new()
+            .mode(PRIVATE_DIR_MODE)
+            .create(path)
+            .with_context(|| "failed to create directory {path}")
     }
 
     fn touch<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<File> {
-        Ok(std::fs::File::create(path)?)
+        Ok(OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(PRIVATE_FILE_MODE)
+            .open(path)?)
+    }
+}
+
+/// Create `path` and any missing parents, accessible only by their owner.
+pub fn create_private_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(PRIVATE_DIR_MODE)
+        .create(path)
+}
+
+/// Create `path` to write to, readable and writable only by its owner. An
+/// existing file is truncated and has its permissions tightened before
+/// anything is written to it.
+pub fn create_private<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(PRIVATE_FILE_MODE)
+        .open(path)?;
+    file.set_permissions(Permissions::from_mode(PRIVATE_FILE_MODE))?;
+    Ok(file)
+}
+
+/// A new file, readable and writable only by its owner, written beside the
+/// file it is to replace. It is only renamed over that file once all of it
+/// is written, with [`ReplacingFile::persist`], and removed if it never is,
+/// as entries are written to the store.
+pub struct ReplacingFile {
+    path: PathBuf,
+    target: PathBuf,
+    file: File,
+    persisted: bool,
+}
+
+impl ReplacingFile {
+    /// Create the file to replace `target` with. It is given a name no
+    /// other file has, so nothing already there is ever written through.
+    pub fn beside<P: AsRef<Path>>(target: P) -> io::Result<Self> {
+        let target = target.as_ref().to_owned();
+        let file_name = target
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let path =
+            target.with_file_name(format!(".{file_name}.{:016x}.tmp", rand::random::<u64>()));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(PRIVATE_FILE_MODE)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            target,
+            file,
+            persisted: false,
+        })
+    }
+
+    /// Replace the target with everything written.
+    pub fn persist(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        std::fs::rename(&self.path, &self.target)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Write for ReplacingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for ReplacingFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
     }
 }
 
+/// Write `contents` to `path`, as created by [`create_private`].
+pub fn write_private<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let mut file = create_private(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::{fs, io::Write, os::unix::fs::PermissionsExt, path::Path};
 
-    use super::{FileSystemOperations, FileSystemOperator};
+    use super::{
+        create_private_dir_all, write_private, FileSystemOperations, FileSystemOperator,
+        ReplacingFile,
+    };
     use tempfile::tempdir;
 
+    #[test]
+    fn write_private_should_leave_file_readable_only_by_owner() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.conf");
+        fs::write(&path, "previous contents which are longer").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, b"password=hunter2").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "password=hunter2");
+    }
+
+    #[test]
+    fn replacing_files_should_only_replace_their_target_once_persisted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dump.sql");
+        fs::write(&path, "previous contents").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let mut file = ReplacingFile::beside(&path).unwrap();
+        file.write_all(b"part of a dump").unwrap();
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous contents");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let mut file = ReplacingFile::beside(&path).unwrap();
+        file.write_all(b"create table users;").unwrap();
+        file.persist().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "create table users;");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn should_create_dir() {
         let leaf = "leaf";
@@ -43,6 +185,15 @@ mod test {
         assert!(Path::new(&format!("{}/{}", base_dir, leaf)).exists());
     }
 
+    #[test]
+    fn created_dirs_should_be_accessible_only_by_owner() {
+        let tmpdir = tempdir().unwrap();
+        let path = tmpdir.path().join("store/prod");
+        create_private_dir_all(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
     #[test]
     fn should_create_file() {
         let leaf = "leaf";
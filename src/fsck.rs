@@ -0,0 +1,432 @@
+use crate::{
+    armor,
+    fs::{PRIVATE_DIR_MODE, PRIVATE_FILE_MODE},
+    input::GPG_ID_LIST_FILE,
+    message::{self, Message},
+    store::ARMORED_ENTRY_EXTENSION,
+};
+use std::{
+    fmt,
+    fs::{self, OpenOptions, Permissions},
+    io,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+const GPG_FILE_POSTFIX: &str = ".gpg";
+
+/// A problem with a file or folder in the store.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The entry is not an encrypted OpenPGP message, for the given reason.
+    Corrupt(String),
+    /// The entry has this many bytes after the end of its message, such as
+    /// those left over from a longer value.
+    TrailingData(usize),
+    /// The entry is not encrypted to exactly the keys in its `.gpg-id` file.
+    Recipients {
+        /// Keys from the `.gpg-id` file the entry is not encrypted to.
+        missing: Vec<String>,
+        /// Key ids the entry is encrypted to which are not from its
+        /// `.gpg-id` file.
+        unexpected: Vec<String>,
+    },
+    /// The file is neither an entry nor a `.gpg-id` file.
+    StrayFile,
+    /// The file or folder, with this mode, is accessible by other users.
+    Permissions(u32),
+    /// The folder has nothing in it but empty folders. Folders holding
+    /// hidden files or folders, such as `.git`, are never dangling.
+    DanglingFolder,
+}
+
+impl Problem {
+    /// Whether the problem can be repaired without losing anything. Corrupt
+    /// entries and stray files are left for the user to look at.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Problem::Corrupt(_) | Problem::StrayFile)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Corrupt(reason) => {
+                write!(f, "is not an encrypted OpenPGP message, {reason}")
+            }
+            Problem::TrailingData(bytes) => write!(f, "has {bytes} bytes of trailing data"),
+            Problem::Recipients {
+                missing,
+                unexpected,
+            } => {
+                let mut reasons = Vec::new();
+                if !missing.is_empty() {
+                    reasons.push(format!("is not encrypted to {}", missing.join(", ")));
+                }
+                if !unexpected.is_empty() {
+                    reasons.push(format!(
+                        "is encrypted to keys not in its .gpg-id file, {}",
+                        unexpected.join(", ")
+                    ));
+                }
+                write!(f, "{}", reasons.join(" and "))
+            }
+            Problem::StrayFile => write!(f, "is not an entry or .gpg-id file"),
+            Problem::Permissions(mode) => {
+                write!(f, "is accessible by other users, its mode is {mode:o}")
+            }
+            Problem::DanglingFolder => write!(f, "is an empty folder"),
+        }
+    }
+}
+
+/// Whether a problem was repaired.
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Found,
+    Repaired,
+    RepairFailed(String),
+}
+
+/// A problem found at `path`, relative to the store.
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub path: String,
+    pub problem: Problem,
+    pub status: Status,
+}
+
+impl Issue {
+    pub fn new<P: Into<String>>(path: P, problem: Problem) -> Self {
+        Self {
+            path: path.into(),
+            problem,
+            status: Status::Found,
+        }
+    }
+}
+
+/// An entry which is an encrypted message, with what could be read of it.
+#[derive(Debug)]
+pub struct Entry {
+    pub name: String,
+    /// The path of its file, relative to the store.
+    pub path: String,
+    pub message: Message,
+}
+
+/// The outcome of walking the store.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub issues: Vec<Issue>,
+    /// Every entry which is an encrypted message, so its recipients can be
+    /// checked.
+    pub entries: Vec<Entry>,
+}
+
+/// The name of the entry whose file is at `path`, either a binary or an
+/// ASCII armored message.
+pub fn entry_name(path: &str) -> Option<&str> {
+    path.strip_suffix(GPG_FILE_POSTFIX)
+        .or_else(|| path.strip_suffix(ARMORED_ENTRY_EXTENSION))
+}
+
+/// Walk the store at `base_dir`, checking every entry is an encrypted
+/// OpenPGP message, binary or ASCII armored, and looking for stray files, files and folders other
+/// users can access, and empty folders. Hidden files and folders other than
+/// `.gpg-id` files are left alone, as they belong to tools such as `git`.
+pub fn scan(base_dir: &Path) -> io::Result<Scan> {
+    let mut scan = Scan::default();
+    check_permissions(base_dir, ".", &mut scan)?;
+    walk(base_dir, base_dir, &mut scan)?;
+    // Only the topmost of nested empty folders is reported, removing it
+    // removes the rest.
+    let dangling = scan
+        .issues
+        .iter()
+        .filter(|issue| issue.problem == Problem::DanglingFolder)
+        .map(|issue| format!("{}/", issue.path))
+        .collect::<Vec<_>>();
+    scan.issues.retain(|issue| {
+        !dangling
+            .iter()
+            .any(|folder| issue.path.starts_with(folder.as_str()))
+    });
+    Ok(scan)
+}
+
+/// Repair an issue found by [`scan`]. Recipients can only be repaired by
+/// re-encrypting the entry, so are not handled here.
+pub fn repair(base_dir: &Path, issue: &Issue) -> io::Result<()> {
+    let path = base_dir.join(&issue.path);
+    match issue.problem {
+        Problem::TrailingData(bytes) => {
+            let file = OpenOptions::new().write(true).open(&path)?;
+            let len = file.metadata()?.len();
+            file.set_len(len - bytes as u64)?;
+            file.sync_all()
+        }
+        Problem::Permissions(_) => {
+            let mode = if path.is_dir() {
+                PRIVATE_DIR_MODE
+            } else {
+                PRIVATE_FILE_MODE
+            };
+            fs::set_permissions(&path, Permissions::from_mode(mode))
+        }
+        Problem::DanglingFolder => remove_empty_dirs(&path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("`{}` cannot be repaired", issue.path),
+        )),
+    }
+}
+
+/// Remove `dir` and the empty folders beneath it, deepest first. Anything
+/// else in it, such as a file written since the scan, makes this fail rather
+/// than be removed.
+fn remove_empty_dirs(dir: &Path) -> io::Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        if dir_entry.file_type()?.is_dir() {
+            remove_empty_dirs(&dir_entry.path())?;
+        }
+    }
+    fs::remove_dir(dir)
+}
+
+/// Walk `dir`, returning whether it has any files in it or beneath it, or
+/// anything hidden in it.
+fn walk(base_dir: &Path, dir: &Path, scan: &mut Scan) -> io::Result<bool> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+    let mut has_files = false;
+    for path in paths {
+        let relative = match path
+            .strip_prefix(base_dir)
+            .ok()
+            .and_then(|relative| relative.to_str())
+        {
+            Some(relative) => relative.to_owned(),
+            None => continue,
+        };
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_name.starts_with('.') {
+            if file_name == GPG_ID_LIST_FILE {
+                check_permissions(&path, &relative, scan)?;
+            }
+            // Hidden folders are never walked, so are kept as if they held
+            // files.
+            has_files = true;
+            continue;
+        }
+        if file_type.is_dir() {
+            check_permissions(&path, &relative, scan)?;
+            if walk(base_dir, &path, scan)? {
+                has_files = true;
+            } else {
+                scan.issues
+                    .push(Issue::new(relative, Problem::DanglingFolder));
+            }
+            continue;
+        }
+        has_files = true;
+        match entry_name(&relative) {
+            Some(name) if file_type.is_file() => {
+                check_permissions(&path, &relative, scan)?;
+                let bytes = fs::read(&path)?;
+                match message::inspect_entry(&bytes) {
+                    Ok(message) => {
+                        // Armored messages end at their footer, so only
+                        // binary ones can have data left over.
+                        if !armor::is_armored(&bytes) && message.len < bytes.len() {
+                            let trailing = bytes.len() - message.len;
+                            scan.issues
+                                .push(Issue::new(&relative, Problem::TrailingData(trailing)));
+                        }
+                        scan.entries.push(Entry {
+                            name: name.to_owned(),
+                            path: relative.clone(),
+                            message,
+                        });
+                    }
+                    Err(e) => scan
+                        .issues
+                        .push(Issue::new(&relative, Problem::Corrupt(e.to_string()))),
+                }
+            }
+            _ => scan.issues.push(Issue::new(relative, Problem::StrayFile)),
+        }
+    }
+    Ok(has_files)
+}
+
+fn check_permissions(path: &Path, relative: &str, scan: &mut Scan) -> io::Result<()> {
+    let mode = fs::metadata(path)?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        scan.issues
+            .push(Issue::new(relative, Problem::Permissions(mode)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{repair, scan, Issue, Problem};
+    use crate::armor;
+    use std::{
+        fs::{self, Permissions},
+        os::unix::fs::PermissionsExt,
+        path::Path,
+    };
+    use tempfile::tempdir;
+
+    /// A PKESK packet to `0123456789ABCDEF` followed by a SED packet.
+    const MESSAGE: &[u8] = &[
+        0xc1, 0x0b, 0x03, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x12, 0x00, 0xa4, 0x03,
+        0x01, 0x02, 0x03,
+    ];
+
+    fn write(base_dir: &Path, path: &str, contents: &[u8]) {
+        let path = base_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+    }
+
+    fn private_store() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::set_permissions(dir.path(), Permissions::from_mode(0o700)).unwrap();
+        write(dir.path(), ".gpg-id", b"key");
+        dir
+    }
+
+    #[test]
+    fn should_find_nothing_wrong_with_a_healthy_store() {
+        let dir = private_store();
+        write(dir.path(), "api.gpg", MESSAGE);
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let result = scan(dir.path()).unwrap();
+        assert_eq!(result.issues, vec![]);
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].name, "api");
+    }
+
+    #[test]
+    fn armored_entries_should_be_checked_as_entries() {
+        let dir = private_store();
+        write(dir.path(), "api.asc", &armor::armor(MESSAGE));
+        write(dir.path(), "db.asc", b"-----BEGIN PGP MESSAGE-----\n");
+        let result = scan(dir.path()).unwrap();
+        assert!(matches!(
+            result.issues.as_slice(),
+            [Issue {
+                problem: Problem::Corrupt(_),
+                ..
+            }] if result.issues[0].path == "db.asc"
+        ));
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].path, "api.asc");
+    }
+
+    #[test]
+    fn should_find_corrupt_entries_and_stray_files() {
+        let dir = private_store();
+        write(dir.path(), "prod/db.gpg", b"hunter2");
+        write(dir.path(), "prod/notes.txt", b"notes");
+        fs::set_permissions(dir.path().join("prod"), Permissions::from_mode(0o755)).unwrap();
+        let result = scan(dir.path()).unwrap();
+        let problems = result
+            .issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), &issue.problem))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            problems.as_slice(),
+            [
+                ("prod", Problem::Permissions(_)),
+                ("prod/db.gpg", Problem::Corrupt(_)),
+                ("prod/notes.txt", Problem::StrayFile),
+            ]
+        ));
+        assert!(result.entries.is_empty());
+    }
+
+    #[test]
+    fn should_repair_trailing_data() {
+        let dir = private_store();
+        let mut bytes = MESSAGE.to_vec();
+        bytes.extend_from_slice(b"left over");
+        write(dir.path(), "api.gpg", &bytes);
+        let result = scan(dir.path()).unwrap();
+        assert_eq!(
+            result.issues,
+            vec![Issue::new("api.gpg", Problem::TrailingData(9))]
+        );
+        repair(dir.path(), &result.issues[0]).unwrap();
+        assert_eq!(fs::read(dir.path().join("api.gpg")).unwrap(), MESSAGE);
+    }
+
+    #[test]
+    fn should_repair_permissions() {
+        let dir = private_store();
+        write(dir.path(), "api.gpg", MESSAGE);
+        let path = dir.path().join("api.gpg");
+        fs::set_permissions(&path, Permissions::from_mode(0o644)).unwrap();
+        let result = scan(dir.path()).unwrap();
+        assert_eq!(
+            result.issues,
+            vec![Issue::new("api.gpg", Problem::Permissions(0o644))]
+        );
+        repair(dir.path(), &result.issues[0]).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn should_report_and_remove_only_the_topmost_empty_folder() {
+        let dir = private_store();
+        fs::create_dir_all(dir.path().join("old/archive")).unwrap();
+        fs::set_permissions(dir.path().join("old"), Permissions::from_mode(0o700)).unwrap();
+        let result = scan(dir.path()).unwrap();
+        assert_eq!(
+            result.issues,
+            vec![Issue::new("old", Problem::DanglingFolder)]
+        );
+        repair(dir.path(), &result.issues[0]).unwrap();
+        assert!(!dir.path().join("old").exists());
+    }
+
+    #[test]
+    fn folders_holding_hidden_folders_should_not_be_dangling() {
+        let dir = private_store();
+        fs::create_dir_all(dir.path().join("vendored/.git/objects")).unwrap();
+        fs::set_permissions(dir.path().join("vendored"), Permissions::from_mode(0o700)).unwrap();
+        let result = scan(dir.path()).unwrap();
+        assert_eq!(result.issues, vec![]);
+    }
+
+    #[test]
+    fn repairing_a_dangling_folder_should_not_remove_files_written_since() {
+        let dir = private_store();
+        fs::create_dir_all(dir.path().join("old/archive")).unwrap();
+        fs::set_permissions(dir.path().join("old"), Permissions::from_mode(0o700)).unwrap();
+        let result = scan(dir.path()).unwrap();
+        write(dir.path(), "old/archive/db.gpg", MESSAGE);
+        assert!(repair(dir.path(), &result.issues[0]).is_err());
+        assert!(dir.path().join("old/archive/db.gpg").exists());
+    }
+
+    #[test]
+    fn corrupt_entries_and_stray_files_should_not_be_repairable() {
+        assert!(!Problem::Corrupt("reason".to_owned()).is_repairable());
+        assert!(!Problem::StrayFile.is_repairable());
+        assert!(Problem::TrailingData(1).is_repairable());
+    }
+}
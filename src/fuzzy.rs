@@ -0,0 +1,101 @@
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 5;
+const BOUNDARY_BONUS: i64 = 10;
+const GAP_PENALTY: i64 = 1;
+
+/// Score how well `candidate` matches `pattern`, where every character of the
+/// pattern must appear in the candidate in order, ignoring case.
+///
+/// Returns [`None`] if the candidate does not match. Higher scores are better,
+/// consecutive matches and matches at the start of a path segment or word
+/// score higher, every skipped character of the candidate lowers the score.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern = pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let char_score = |j: usize| {
+        let at_boundary = j == 0 || is_boundary(candidate[j - 1]);
+        MATCH_SCORE + if at_boundary { BOUNDARY_BONUS } else { 0 }
+    };
+    let is_match = |i: usize, j: usize| candidate[j].to_lowercase().eq(std::iter::once(pattern[i]));
+    // best[j] holds the best score with the current pattern character matched
+    // at candidate position j.
+    let mut best = (0..candidate.len())
+        .map(|j| is_match(0, j).then(|| char_score(j) - GAP_PENALTY * j as i64))
+        .collect::<Vec<_>>();
+    for i in 1..pattern.len() {
+        best = (0..candidate.len())
+            .map(|j| {
+                if !is_match(i, j) {
+                    return None;
+                }
+                (0..j)
+                    .filter_map(|k| {
+                        let transition = if k + 1 == j {
+                            CONSECUTIVE_BONUS
+                        } else {
+                            -GAP_PENALTY * (j - k - 1) as i64
+                        };
+                        Some(best[k]? + transition)
+                    })
+                    .max()
+                    .map(|previous| previous + char_score(j))
+            })
+            .collect();
+    }
+    best.iter()
+        .enumerate()
+        .filter_map(|(j, score)| {
+            let trailing = (candidate.len() - j - 1) as i64;
+            score.map(|score| score - GAP_PENALTY * trailing)
+        })
+        .max()
+}
+
+fn is_boundary(c: char) -> bool {
+    matches!(c, '/' | '-' | '_' | '.' | ' ')
+}
+
+#[cfg(test)]
+mod test {
+    use super::score;
+
+    #[test]
+    fn should_match_characters_in_order() {
+        assert!(score("pdb", "prod/db").is_some());
+    }
+
+    #[test]
+    fn should_not_match_characters_out_of_order() {
+        assert!(score("bdp", "prod/db").is_none());
+    }
+
+    #[test]
+    fn should_ignore_case() {
+        assert!(score("PROD", "prod/db").is_some());
+    }
+
+    #[test]
+    fn empty_pattern_should_match_everything() {
+        assert!(score("", "prod/db").is_some());
+    }
+
+    #[test]
+    fn consecutive_matches_should_score_higher() {
+        let consecutive = score("db", "prod/db").unwrap();
+        let scattered = score("db", "prod/dxb").unwrap();
+        assert!(consecutive > scattered);
+    }
+
+    #[test]
+    fn matches_at_segment_start_should_score_higher() {
+        let boundary = score("db", "prod/db").unwrap();
+        let inner = score("db", "prod/xdb").unwrap();
+        assert!(boundary > inner);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::secrets::ZeroizedString;
+use rand::{rngs::OsRng, Rng};
+
+pub const DEFAULT_PASSWORD_LENGTH: usize = 24;
+
+const CHARSET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!#$%&*+-=?@^_~";
+
+/// Generate a random password of the given length from the OS random source.
+pub fn password(length: usize) -> ZeroizedString {
+    let mut rng = OsRng;
+    ZeroizedString::new(
+        (0..length)
+            .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{password, CHARSET};
+
+    #[test]
+    fn should_generate_password_of_given_length() {
+        assert_eq!(password(32).len(), 32);
+    }
+
+    #[test]
+    fn should_only_use_characters_from_the_charset() {
+        assert!(password(64).bytes().all(|b| CHARSET.contains(&b)));
+    }
+
+    #[test]
+    fn should_generate_different_passwords() {
+        assert!(password(24) != password(24));
+    }
+}
@@ -1,10 +1,55 @@
-use crate::secrets::{ZeroizedByteVec, ZeroizedString};
-use anyhow::Context;
-use gpgme::{Data, Protocol};
+use crate::keys::{KeyInfo, Trust};
+use crate::secrets::{NotUtf8Error, Pinentry, SecretReader, ZeroizedByteVec, ZeroizedString};
+use gpgme::{Data, ExportMode, Key, PassphraseRequest, Protocol, Validity};
+use std::{
+    io::{Read, Write},
+    sync::{Mutex, PoisonError},
+    time::SystemTime,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GpgError {
+    #[error("Unable to find key with id {0}!")]
+    KeyNotFound(String),
+    #[error("The key {key_id} cannot be encrypted to, {problem}")]
+    UnusableKey { key_id: String, problem: KeyProblem },
+    #[error("Failed to decrypt the given ciphertext")]
+    DecryptionFailed(#[source] gpgme::Error),
+    #[error("Encountered an error when interacting with GPG")]
+    Gpgme(#[from] gpgme::Error),
+    #[error("The plaintext is not UTF-8 text")]
+    NotUtf8(#[from] NotUtf8Error),
+}
+
+/// Why a key in the keyring cannot be encrypted to.
+#[derive(Error, Clone, Copy, Debug, PartialEq)]
+pub enum KeyProblem {
+    #[error("it has been revoked")]
+    Revoked,
+    #[error("it has expired")]
+    Expired,
+    #[error("it has been disabled")]
+    Disabled,
+    #[error("it has no key capable of encryption")]
+    CannotEncrypt,
+    #[error("it is not trusted, set its owner trust with `gpg --edit-key`")]
+    Untrusted,
+}
 
 /// Wrapper for GPG functionality.
 pub struct Gpg {
     protocol: Protocol,
+    /// The GnuPG home directory, the default is used when not set.
+    home_dir: Option<String>,
+    pinentry: Pinentry,
+    /// Whether messages are ASCII armored rather than binary.
+    armor: bool,
+    /// Where the passphrase is read from in loopback mode.
+    passphrase_reader: Option<Box<dyn SecretReader + Send + Sync>>,
+    /// The passphrase last read, so it is only asked for once however many
+    /// entries are decrypted.
+    passphrase: Mutex<Option<ZeroizedString>>,
 }
 
 /// High level implementation of GPG functionality.
@@ -12,37 +57,448 @@ impl Gpg {
     pub fn new() -> Self {
         Self {
             protocol: Protocol::OpenPgp,
+            home_dir: None,
+            pinentry: Pinentry::default(),
+            armor: false,
+            passphrase_reader: None,
+            passphrase: Mutex::new(None),
         }
     }
 
-    /// Encrypt the given plaintext bytes with the key indentified by the key ID.
-    pub fn encrypt(&self, key_id: &str, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
-        let mut context = gpgme::Context::from_protocol(self.protocol)?;
-        let key = context.get_key(key_id)?;
+    /// Use the keyring, and agent, of the GnuPG home directory `home_dir`
+    /// rather than the default, as with `GNUPGHOME`.
+    pub fn with_home_dir<S: Into<String>>(mut self, home_dir: S) -> Self {
+        self.home_dir = Some(home_dir.into());
+        self
+    }
+
+    /// Ask for passphrases as `pinentry` says.
+    pub fn with_pinentry(mut self, pinentry: Pinentry) -> Self {
+        self.pinentry = pinentry;
+        self
+    }
+
+    /// Write the messages encrypted with [`Gpg::encrypt`] and
+    /// [`Gpg::encrypt_symmetric`] ASCII armored if `armor` is set, so they
+    /// can be read as text.
+    pub fn with_armor(mut self, armor: bool) -> Self {
+        self.armor = armor;
+        self
+    }
+
+    /// Read passphrases from `reader` in [`Pinentry::Loopback`] mode.
+    pub fn with_passphrase_reader<R>(mut self, reader: R) -> Self
+    where
+        R: SecretReader + Send + Sync + 'static,
+    {
+        self.passphrase_reader = Some(Box::new(reader));
+        self
+    }
+
+    /// Encrypt the given plaintext bytes with the keys indentified by the key IDs.
+    pub fn encrypt<S: AsRef<str>>(
+        &self,
+        key_ids: &[S],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, GpgError> {
+        let mut context = self.context()?;
+        let keys = Self::get_usable_keys(&mut context, key_ids)?;
+        context.set_armor(self.armor);
+        let mut ciphertext = Vec::new();
+        context.encrypt(&keys, plaintext, &mut ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    /// Encrypt everything read from `plaintext` as [`Gpg::encrypt`] does,
+    /// writing the message to `ciphertext` as it is encrypted, so neither is
+    /// ever held in memory whole.
+    pub fn encrypt_stream<S, R, W>(
+        &self,
+        key_ids: &[S],
+        plaintext: R,
+        ciphertext: W,
+    ) -> Result<(), GpgError>
+    where
+        S: AsRef<str>,
+        R: Read + Send,
+        W: Write + Send,
+    {
+        let mut context = self.context()?;
+        let keys = Self::get_usable_keys(&mut context, key_ids)?;
+        context.set_armor(self.armor);
+        let mut input = Data::from_reader(plaintext).map_err(|e| e.error())?;
+        let mut output = Data::from_writer(ciphertext).map_err(|e| e.error())?;
+        context.encrypt(&keys, &mut input, &mut output)?;
+        Ok(())
+    }
+
+    /// Encrypt `plaintext` with `passphrase` alone, so it can be decrypted
+    /// without any key.
+    pub fn encrypt_symmetric(
+        &self,
+        plaintext: &[u8],
+        passphrase: &str,
+    ) -> Result<Vec<u8>, GpgError> {
+        let mut context = self.context()?;
+        context.set_pinentry_mode(gpgme::PinentryMode::Loopback)?;
+        context.set_armor(self.armor);
         let mut ciphertext = Vec::new();
-        context.encrypt(&[key], plaintext, &mut ciphertext)?;
+        context.with_passphrase_provider(Self::provide(passphrase), |context| {
+            context.encrypt_symmetric(plaintext, &mut ciphertext)
+        })?;
         Ok(ciphertext)
     }
 
+    /// Decrypt `ciphertext` encrypted with [`Gpg::encrypt_symmetric`].
+    pub fn decrypt_symmetric(
+        &self,
+        ciphertext: &[u8],
+        passphrase: &str,
+    ) -> Result<ZeroizedByteVec, GpgError> {
+        let mut context = self.context()?;
+        context.set_pinentry_mode(gpgme::PinentryMode::Loopback)?;
+        let mut input = Data::from_bytes(ciphertext)?;
+        let mut output = ZeroizedByteVec::new(Vec::new());
+        context
+            .with_passphrase_provider(Self::provide(passphrase), |context| {
+                context.decrypt(&mut input, &mut *output)
+            })
+            .map_err(GpgError::DecryptionFailed)?;
+        Ok(output)
+    }
+
     /// Decrypt the given ciphertext.
-    pub fn decrypt(&self, ciphertext: &[u8]) -> anyhow::Result<ZeroizedString> {
-        let mut context = gpgme::Context::from_protocol(self.protocol)?;
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedString, GpgError> {
+        Ok(self.decrypt_bytes(ciphertext)?.into_zeroized_string()?)
+    }
+
+    /// Decrypt the given ciphertext, whose plaintext need not be utf-8.
+    pub fn decrypt_bytes(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, GpgError> {
+        let mut context = self.context()?;
         let mut input = Data::from_bytes(ciphertext)?;
         let mut output = ZeroizedByteVec::new(Vec::new());
-        context.decrypt(&mut input, &mut *output)?;
-        Ok(output.into_zeroized_string())
+        self.with_passphrase(&mut context, |context| {
+            context.decrypt(&mut input, &mut *output)
+        })
+        .map_err(GpgError::DecryptionFailed)?;
+        Ok(output)
+    }
+
+    /// Decrypt everything read from `ciphertext` as [`Gpg::decrypt_bytes`]
+    /// does, writing the plaintext to `plaintext` as it is decrypted, so
+    /// neither is ever held in memory whole.
+    pub fn decrypt_stream<R, W>(&self, ciphertext: R, plaintext: W) -> Result<(), GpgError>
+    where
+        R: Read + Send,
+        W: Write + Send,
+    {
+        let mut context = self.context()?;
+        let mut input = Data::from_reader(ciphertext).map_err(|e| e.error())?;
+        let mut output = Data::from_writer(plaintext).map_err(|e| e.error())?;
+        self.with_passphrase(&mut context, |context| {
+            context.decrypt(&mut input, &mut output)
+        })
+        .map_err(GpgError::DecryptionFailed)?;
+        Ok(())
+    }
+
+    /// Make a detached signature of `data` with each of the keys identified
+    /// by the key IDs.
+    pub fn sign_detached<S: AsRef<str>>(
+        &self,
+        key_ids: &[S],
+        data: &[u8],
+    ) -> Result<Vec<u8>, GpgError> {
+        let mut context = self.context()?;
+        for key_id in key_ids {
+            let key = context
+                .get_secret_key(key_id.as_ref())
+                .map_err(|_| GpgError::KeyNotFound(key_id.as_ref().to_owned()))?;
+            context.add_signer(&key)?;
+        }
+        let mut signature = Vec::new();
+        self.with_passphrase(&mut context, |context| {
+            context.sign_detached(data, &mut signature)
+        })?;
+        Ok(signature)
+    }
+
+    /// The fingerprints of the keys which made a valid signature of `data`
+    /// in the detached `signature`.
+    pub fn verify_detached(&self, signature: &[u8], data: &[u8]) -> Result<Vec<String>, GpgError> {
+        let mut context = self.context()?;
+        let result = context.verify_detached(signature, data)?;
+        Ok(result
+            .signatures()
+            .filter(|signature| signature.status().is_ok())
+            .filter_map(|signature| signature.fingerprint().ok().map(ToOwned::to_owned))
+            .collect())
+    }
+
+    /// Returns whether the key exists and can be encrypted to: it can
+    /// encrypt, has not expired or been revoked, and is trusted.
+    pub fn does_key_exist(&self, key_id: &str) -> Result<(), GpgError> {
+        let mut context = self.context()?;
+        Self::get_usable_key(&mut context, key_id).map(|_| ())
+    }
+
+    /// The fingerprints of the keys `key_id` identifies which can be
+    /// encrypted to, in the order gpg lists them. `key_id` may be a
+    /// fingerprint, key id or user id, and is ambiguous if there is more
+    /// than one.
+    pub fn fingerprints(&self, key_id: &str) -> Result<Vec<String>, GpgError> {
+        let mut context = self.context()?;
+        let keys = context
+            .find_keys([key_id])?
+            .collect::<Result<Vec<_>, _>>()?;
+        let fingerprints = keys
+            .iter()
+            .filter(|key| Self::problem(key).is_none())
+            .filter_map(|key| key.fingerprint().ok().map(ToOwned::to_owned))
+            .collect::<Vec<_>>();
+        if fingerprints.is_empty() {
+            // Tell apart keys which are missing from those which are unusable.
+            let key = Self::get_usable_key(&mut context, key_id)?;
+            return Ok(vec![key.fingerprint().unwrap_or_default().to_owned()]);
+        }
+        Ok(fingerprints)
+    }
+
+    /// The keys in the keyring which can encrypt, and have not expired or
+    /// been revoked, whether or not they are trusted.
+    pub fn encryption_keys(&self) -> Result<Vec<KeyInfo>, GpgError> {
+        let mut context = self.context()?;
+        let keys = context.keys()?.collect::<Result<Vec<_>, _>>()?;
+        Ok(keys
+            .iter()
+            .filter(|key| matches!(Self::problem(key), None | Some(KeyProblem::Untrusted)))
+            .map(|key| Self::info(key, None))
+            .collect())
+    }
+
+    /// Import the keys in `data`, which may be armored, into the keyring.
+    /// Returns the fingerprints of the keys imported, or already there.
+    pub fn import_keys(&self, data: &[u8]) -> Result<Vec<String>, GpgError> {
+        let mut context = self.context()?;
+        let result = context.import(data)?;
+        let mut fingerprints = result
+            .imports()
+            .filter(|import| import.result().is_ok())
+            .filter_map(|import| import.fingerprint().ok().map(ToOwned::to_owned))
+            .collect::<Vec<_>>();
+        fingerprints.sort();
+        fingerprints.dedup();
+        Ok(fingerprints)
+    }
+
+    /// The public key identified by `key_id`, ASCII armored.
+    pub fn export_key(&self, key_id: &str) -> Result<String, GpgError> {
+        let mut context = self.context()?;
+        context.set_armor(true);
+        let key = Self::get_key(&mut context, key_id)?;
+        let mut exported = Vec::new();
+        context.export_keys(&[key], ExportMode::empty(), &mut exported)?;
+        Ok(String::from_utf8_lossy(&exported).into_owned())
+    }
+
+    /// The long key ids of the key identified by `key_id` and of each of its
+    /// subkeys, as listed in the recipients of messages encrypted to it.
+    pub fn subkey_ids(&self, key_id: &str) -> Result<Vec<String>, GpgError> {
+        let mut context = self.context()?;
+        let key = Self::get_key(&mut context, key_id)?;
+        Ok(key
+            .subkeys()
+            .filter_map(|subkey| subkey.id().ok().map(ToOwned::to_owned))
+            .collect())
     }
 
-    /// Returns whether the key exists.
-    pub fn does_key_exist(&self, key_id: &str) -> anyhow::Result<()> {
+    /// Look up the key identified by `key_id`, which may be the id of one
+    /// of its subkeys. A key counts as expired or revoked if it is, or if
+    /// the subkey identified is.
+    pub fn key_info(&self, key_id: &str) -> Result<KeyInfo, GpgError> {
+        let mut context = self.context()?;
+        let key = Self::get_key(&mut context, key_id)?;
+        let key_id = key_id.to_uppercase();
+        let subkey = key.subkeys().find(|subkey| {
+            subkey
+                .fingerprint()
+                .is_ok_and(|fingerprint| fingerprint.ends_with(&key_id))
+        });
+        Ok(Self::info(&key, subkey))
+    }
+
+    fn info(key: &Key, subkey: Option<gpgme::Subkey<'_>>) -> KeyInfo {
+        KeyInfo {
+            fingerprint: key.fingerprint().unwrap_or_default().to_owned(),
+            user_id: key
+                .user_ids()
+                .next()
+                .and_then(|user_id| user_id.id().ok().map(ToOwned::to_owned)),
+            expires: Self::encryption_expiry(key),
+            expired: key.is_expired() || subkey.is_some_and(|subkey| subkey.is_expired()),
+            revoked: key.is_revoked() || subkey.is_some_and(|subkey| subkey.is_revoked()),
+            trust: trust(key),
+        }
+    }
+
+    /// When `key` can no longer be encrypted to, which is when the last of
+    /// its usable encryption subkeys expires, or the primary key does if
+    /// that is sooner. `None` if it never does.
+    fn encryption_expiry(key: &Key) -> Option<SystemTime> {
+        let subkeys = key
+            .subkeys()
+            .filter(|subkey| subkey.can_encrypt() && !subkey.is_expired() && !subkey.is_revoked())
+            .map(|subkey| subkey.expiration_time())
+            .collect::<Option<Vec<_>>>();
+        let primary_key = key
+            .primary_key()
+            .and_then(|primary_key| primary_key.expiration_time());
+        match (
+            subkeys.and_then(|subkeys| subkeys.into_iter().max()),
+            primary_key,
+        ) {
+            (Some(subkeys), Some(primary_key)) => Some(subkeys.min(primary_key)),
+            (subkeys, primary_key) => subkeys.or(primary_key),
+        }
+    }
+
+    /// Why `key` cannot be encrypted to, if it cannot.
+    fn problem(key: &Key) -> Option<KeyProblem> {
+        if key.is_revoked() {
+            Some(KeyProblem::Revoked)
+        } else if key.is_expired() {
+            Some(KeyProblem::Expired)
+        } else if key.is_disabled() {
+            Some(KeyProblem::Disabled)
+        } else if key.is_invalid() || !key.can_encrypt() {
+            Some(KeyProblem::CannotEncrypt)
+        } else if !trust(key).is_trusted() {
+            Some(KeyProblem::Untrusted)
+        } else {
+            None
+        }
+    }
+
+    fn context(&self) -> Result<gpgme::Context, GpgError> {
         let mut context = gpgme::Context::from_protocol(self.protocol)?;
+        if let Some(home_dir) = &self.home_dir {
+            context.set_engine_home_dir(home_dir.as_str())?;
+        }
+        match self.pinentry {
+            Pinentry::Ask => {}
+            Pinentry::Loopback => context.set_pinentry_mode(gpgme::PinentryMode::Loopback)?,
+            Pinentry::Cancel => context.set_pinentry_mode(gpgme::PinentryMode::Cancel)?,
+        }
+        Ok(context)
+    }
+
+    /// Run `f`, which may need the passphrase of a secret key, answering
+    /// gpg-agent ourselves in loopback mode.
+    fn with_passphrase<T>(
+        &self,
+        context: &mut gpgme::Context,
+        f: impl FnOnce(&mut gpgme::Context) -> T,
+    ) -> T {
+        match self.pinentry {
+            Pinentry::Loopback => context.with_passphrase_provider(
+                |request: PassphraseRequest<'_>, out: &mut dyn Write| {
+                    self.write_passphrase(request, out)
+                },
+                f,
+            ),
+            Pinentry::Ask | Pinentry::Cancel => f(context),
+        }
+    }
+
+    /// Answer gpg-agent with `passphrase`, once, as asking again would only
+    /// give the same wrong passphrase.
+    fn provide(
+        passphrase: &str,
+    ) -> impl FnMut(PassphraseRequest<'_>, &mut dyn Write) -> Result<(), gpgme::Error> + Send + '_
+    {
+        move |request, out| {
+            if request.prev_attempt_failed {
+                return Err(gpgme::Error::BAD_PASSPHRASE);
+            }
+            out.write_all(passphrase.as_bytes())?;
+            out.write_all(b"\n")?;
+            Ok(())
+        }
+    }
+
+    fn write_passphrase(
+        &self,
+        request: PassphraseRequest<'_>,
+        out: &mut dyn Write,
+    ) -> Result<(), gpgme::Error> {
+        let reader = self
+            .passphrase_reader
+            .as_ref()
+            .ok_or(gpgme::Error::CANCELED)?;
+        let mut passphrase = self
+            .passphrase
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if request.prev_attempt_failed {
+            *passphrase = None;
+        }
+        let passphrase = match &mut *passphrase {
+            Some(passphrase) => passphrase,
+            cached => cached.insert(
+                reader
+                    .read_secret()
+                    .ok()
+                    .and_then(|secret| secret.into_zeroized_string().ok())
+                    .ok_or(gpgme::Error::CANCELED)?,
+            ),
+        };
+        out.write_all(passphrase.as_bytes())?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn get_key(context: &mut gpgme::Context, key_id: &str) -> Result<Key, GpgError> {
         context
             .get_key(key_id)
-            .with_context(|| format!("Unable to find key with id {key_id}!"))
-            .map(|_| ())
+            .map_err(|_| GpgError::KeyNotFound(key_id.to_owned()))
+    }
+
+    fn get_usable_key(context: &mut gpgme::Context, key_id: &str) -> Result<Key, GpgError> {
+        let key = Self::get_key(context, key_id)?;
+        match Self::problem(&key) {
+            Some(problem) => Err(GpgError::UnusableKey {
+                key_id: key_id.to_owned(),
+                problem,
+            }),
+            None => Ok(key),
+        }
+    }
+
+    fn get_usable_keys<S: AsRef<str>>(
+        context: &mut gpgme::Context,
+        key_ids: &[S],
+    ) -> Result<Vec<Key>, GpgError> {
+        key_ids
+            .iter()
+            .map(|key_id| Self::get_usable_key(context, key_id.as_ref()))
+            .collect()
     }
 }
 
+/// The trust of the best trusted of the user ids of `key`.
+fn trust(key: &Key) -> Trust {
+    key.user_ids()
+        .map(|user_id| match user_id.validity() {
+            Validity::Ultimate => Trust::Ultimate,
+            Validity::Full => Trust::Full,
+            Validity::Marginal => Trust::Marginal,
+            Validity::Never => Trust::Never,
+            _ => Trust::Unknown,
+        })
+        .max_by_key(|trust| *trust as u8)
+        .unwrap_or(Trust::Unknown)
+}
+
 impl Default for Gpg {
     fn default() -> Self {
         Self::new()
@@ -53,20 +509,77 @@ impl Default for Gpg {
 pub mod test {
     use uuid::Uuid;
 
-    use crate::gpg::Gpg;
+    use crate::{
+        gpg::{Gpg, GpgError, KeyProblem},
+        keys::Trust,
+        secrets::{Pinentry, SecretReader, ZeroizedByteVec},
+    };
     use std::{
         env,
         io::Write,
+        ops::Deref,
+        path::Path,
         process::{Command, Stdio},
+        sync::OnceLock,
     };
+    use tempfile::TempDir;
 
     pub const GPG_KEY_ID: &str = "passrs-tests@nocht.io";
 
+    static KEYRING: OnceLock<GpgHome> = OnceLock::new();
+
+    /// Import the test keys into a keyring of their own, shared by every
+    /// test as `GNUPGHOME` is pointed at it, so the user's keyring is never
+    /// touched. It is kept for as long as the tests run.
     pub fn import_keys() {
+        KEYRING.get_or_init(|| {
+            let home = new_home();
+            env::set_var("GNUPGHOME", &*home);
+            import_test_keys(&home);
+            home
+        });
+    }
+
+    /// An empty GnuPG home directory, removed along with the gpg-agent
+    /// started for it once dropped.
+    pub struct GpgHome(TempDir);
+
+    impl Deref for GpgHome {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            self.0.path()
+        }
+    }
+
+    impl Drop for GpgHome {
+        fn drop(&mut self) {
+            // No agent is started for a home gpg never needed one for, and
+            // failing to stop one is not worth failing a test over.
+            let _ = Command::new("gpgconf")
+                .arg("--homedir")
+                .arg(self.0.path())
+                .args(["--kill", "gpg-agent"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+
+    /// A new, empty [`GpgHome`].
+    pub fn new_home() -> GpgHome {
+        let home = tempfile::Builder::new()
+            .prefix("sec-tests-")
+            .tempdir()
+            .unwrap();
+        GpgHome(home)
+    }
+
+    pub fn import_test_keys(home: &Path) {
         let public = include_bytes!("../tests/resources/public.key");
         let secret = include_bytes!("../tests/resources/secret.key");
-        import_key(public);
-        import_key(secret);
+        import_key(home, public);
+        import_key(home, secret);
     }
 
     const OWNERTRUST_PATH: &str = concat!(
@@ -74,9 +587,11 @@ pub mod test {
         "/tests/resources/ownertrust-gpg.txt"
     );
 
-    pub fn import_key(key: &[u8]) {
+    pub fn import_key(home: &Path, key: &[u8]) {
         let gpg = env::var_os("GPG").unwrap_or_else(|| "gpg".into());
         let mut child = Command::new(&gpg)
+            .arg("--homedir")
+            .arg(home)
             .arg("--no-permission-warning")
             .arg("--batch")
             .arg("--passphrase")
@@ -91,6 +606,8 @@ pub mod test {
         assert!(child.wait().unwrap().success());
         // The key is imported, now we need to trust it.
         let mut child = Command::new(&gpg)
+            .arg("--homedir")
+            .arg(home)
             .arg("--import-ownertrust")
             .arg(OWNERTRUST_PATH)
             .spawn()
@@ -98,18 +615,189 @@ pub mod test {
         assert!(child.wait().unwrap().success());
     }
 
+    #[test]
+    fn home_dir_should_choose_the_keyring_used() {
+        import_keys();
+        let home = new_home();
+        let gpg = Gpg::new().with_home_dir(home.to_str().unwrap());
+        assert!(matches!(
+            gpg.does_key_exist(GPG_KEY_ID),
+            Err(GpgError::KeyNotFound(_))
+        ));
+        import_test_keys(&home);
+        assert!(gpg.does_key_exist(GPG_KEY_ID).is_ok());
+    }
+
+    struct Passphrase(&'static str);
+
+    impl SecretReader for Passphrase {
+        fn read_secret(&self) -> anyhow::Result<ZeroizedByteVec> {
+            Ok(ZeroizedByteVec::new(self.0.as_bytes().to_vec()))
+        }
+    }
+
+    /// A keyring of its own, so gpg-agent has no passphrase cached. It is
+    /// removed once the home it is returned with is dropped.
+    fn locked_gpg(pinentry: Pinentry, passphrase: &'static str) -> (GpgHome, Gpg) {
+        let home = new_home();
+        import_test_keys(&home);
+        let gpg = Gpg::new()
+            .with_home_dir(home.to_str().unwrap())
+            .with_pinentry(pinentry)
+            .with_passphrase_reader(Passphrase(passphrase));
+        (home, gpg)
+    }
+
+    #[test]
+    fn loopback_should_decrypt_with_the_passphrase_read() {
+        let (_home, gpg) = locked_gpg(Pinentry::Loopback, "abc");
+        let ciphertext = gpg.encrypt(&[GPG_KEY_ID], b"test").unwrap();
+        let plaintext = gpg.decrypt(&ciphertext).unwrap();
+        assert_eq!(&*plaintext, "test");
+    }
+
+    #[test]
+    fn loopback_should_fail_to_decrypt_with_the_wrong_passphrase() {
+        let (_home, gpg) = locked_gpg(Pinentry::Loopback, "wrong");
+        let ciphertext = gpg.encrypt(&[GPG_KEY_ID], b"test").unwrap();
+        assert!(matches!(
+            gpg.decrypt(&ciphertext),
+            Err(GpgError::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn cancel_should_never_ask_for_the_passphrase() {
+        let (_home, gpg) = locked_gpg(Pinentry::Cancel, "abc");
+        let ciphertext = gpg.encrypt(&[GPG_KEY_ID], b"test").unwrap();
+        assert!(matches!(
+            gpg.decrypt(&ciphertext),
+            Err(GpgError::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn symmetric_entries_should_only_decrypt_with_their_passphrase() {
+        let encrypting_home = new_home();
+        let encrypting = Gpg::new().with_home_dir(encrypting_home.to_str().unwrap());
+        let ciphertext = encrypting.encrypt_symmetric(b"test", "abc").unwrap();
+        let message = crate::message::inspect(&ciphertext).unwrap();
+        assert!(message.symmetric && message.recipients.is_empty());
+        // A keyring of its own, so gpg-agent has not cached the passphrase.
+        let home = new_home();
+        let gpg = Gpg::new().with_home_dir(home.to_str().unwrap());
+        assert!(matches!(
+            gpg.decrypt_symmetric(&ciphertext, "wrong"),
+            Err(GpgError::DecryptionFailed(_))
+        ));
+        let plaintext = gpg.decrypt_symmetric(&ciphertext, "abc").unwrap();
+        assert_eq!(*plaintext, b"test");
+    }
+
+    #[test]
+    fn fingerprints_should_resolve_user_ids() {
+        import_keys();
+        let gpg = Gpg::new();
+        let fingerprint = gpg.key_info(GPG_KEY_ID).unwrap().fingerprint;
+        assert_eq!(
+            gpg.fingerprints(GPG_KEY_ID).unwrap(),
+            vec![fingerprint.clone()]
+        );
+        assert_eq!(gpg.fingerprints(&fingerprint).unwrap(), vec![fingerprint]);
+        assert!(matches!(
+            gpg.fingerprints("nobody@example.com"),
+            Err(GpgError::KeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn keys_should_only_be_encrypted_to_once_trusted() {
+        let home = new_home();
+        let gpg = Gpg::new().with_home_dir(home.to_str().unwrap());
+        let public = include_bytes!("../tests/resources/public.key");
+        let fingerprints = gpg.import_keys(public).unwrap();
+        assert_eq!(fingerprints.len(), 1);
+        assert!(matches!(
+            gpg.does_key_exist(GPG_KEY_ID),
+            Err(GpgError::UnusableKey {
+                problem: KeyProblem::Untrusted,
+                ..
+            })
+        ));
+        let keys = gpg.encryption_keys().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].fingerprint, fingerprints[0]);
+        assert_eq!(keys[0].trust, Trust::Unknown);
+        import_test_keys(&home);
+        assert!(gpg.does_key_exist(GPG_KEY_ID).is_ok());
+    }
+
+    #[test]
+    fn exported_keys_should_import_into_another_keyring() {
+        import_keys();
+        let exported = Gpg::new().export_key(GPG_KEY_ID).unwrap();
+        assert!(exported.starts_with("-----BEGIN PGP PUBLIC KEY BLOCK-----"));
+        let home = new_home();
+        let gpg = Gpg::new().with_home_dir(home.to_str().unwrap());
+        let fingerprints = gpg.import_keys(exported.as_bytes()).unwrap();
+        let fingerprint = Gpg::new().key_info(GPG_KEY_ID).unwrap().fingerprint;
+        assert_eq!(fingerprints, vec![fingerprint]);
+    }
+
     #[test]
     fn encrypt_and_decrypt_should_be_isomorphic() {
         import_keys();
         let expected = "test";
         let gpg = Gpg::new();
         let ciphertext = gpg
-            .encrypt(GPG_KEY_ID, expected.as_bytes())
+            .encrypt(&[GPG_KEY_ID], expected.as_bytes())
             .expect("ciphertext encryption error");
         let plaintext = gpg.decrypt(&ciphertext).expect("plaintext");
         assert_eq!(&*plaintext, expected);
     }
 
+    #[test]
+    fn armored_messages_should_decrypt_as_binary_ones_do() {
+        import_keys();
+        let gpg = Gpg::new().with_armor(true);
+        let ciphertext = gpg.encrypt(&[GPG_KEY_ID], b"test").unwrap();
+        assert!(crate::armor::is_armored(&ciphertext));
+        let binary = crate::armor::dearmor(&ciphertext).unwrap();
+        assert_eq!(&*Gpg::new().decrypt(&ciphertext).unwrap(), "test");
+        assert_eq!(&*Gpg::new().decrypt(&binary).unwrap(), "test");
+    }
+
+    #[test]
+    fn streamed_messages_should_decrypt_as_buffered_ones_do() {
+        import_keys();
+        let gpg = Gpg::new();
+        let plaintext = vec![7; 1 << 20];
+        let mut ciphertext = Vec::new();
+        gpg.encrypt_stream(&[GPG_KEY_ID], plaintext.as_slice(), &mut ciphertext)
+            .unwrap();
+        assert_eq!(*gpg.decrypt_bytes(&ciphertext).unwrap(), plaintext);
+        let mut decrypted = Vec::new();
+        gpg.decrypt_stream(ciphertext.as_slice(), &mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+        assert!(matches!(
+            gpg.decrypt_stream(&b"not a message"[..], Vec::new()),
+            Err(GpgError::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn verify_should_return_fingerprint_of_signer() {
+        import_keys();
+        let gpg = Gpg::new();
+        let signature = gpg.sign_detached(&[GPG_KEY_ID], b"key-id").unwrap();
+        let signers = gpg.verify_detached(&signature, b"key-id").unwrap();
+        let fingerprint = gpg.key_info(GPG_KEY_ID).unwrap().fingerprint;
+        assert_eq!(signers, vec![fingerprint]);
+        let signers = gpg.verify_detached(&signature, b"tampered").unwrap();
+        assert!(signers.is_empty());
+    }
+
     #[test]
     fn should_error_if_key_does_not_exist() {
         import_keys();
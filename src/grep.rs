@@ -0,0 +1,114 @@
+use crate::secrets::ZeroizedString;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// An entry whose decrypted contents matched a search pattern.
+#[derive(PartialEq)]
+pub struct GrepMatch {
+    pub name: String,
+    pub lines: Vec<MatchedLine>,
+}
+
+/// A single line of a decrypted entry, along with the byte ranges which
+/// matched the search pattern.
+#[derive(PartialEq)]
+pub struct MatchedLine {
+    pub line: ZeroizedString,
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// An entry which could not be searched, as it could not be read or
+/// decrypted.
+#[derive(Debug, PartialEq)]
+pub struct GrepFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Matches decrypted secrets against a literal or regular expression pattern.
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, is_regex: bool, ignore_case: bool) -> Result<Self, regex::Error> {
+        let pattern = if is_regex {
+            pattern.to_owned()
+        } else {
+            regex::escape(pattern)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        Ok(Self { regex })
+    }
+
+    /// Find the lines of `plaintext` which match, returns [`None`] if no line
+    /// matched.
+    pub fn find(&self, name: &str, plaintext: &ZeroizedString) -> Option<GrepMatch> {
+        let lines = plaintext
+            .lines()
+            .filter_map(|line| {
+                let ranges = self
+                    .regex
+                    .find_iter(line)
+                    .map(|m| m.range())
+                    .collect::<Vec<_>>();
+                (!ranges.is_empty()).then(|| MatchedLine {
+                    line: ZeroizedString::new(line.to_owned()),
+                    ranges,
+                })
+            })
+            .collect::<Vec<_>>();
+        (!lines.is_empty()).then(|| GrepMatch {
+            name: name.to_owned(),
+            lines,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Matcher;
+    use crate::secrets::ZeroizedString;
+
+    #[test]
+    fn should_match_literal_pattern_on_each_line() {
+        let matcher = Matcher::new("host", false, false).unwrap();
+        let plaintext = ZeroizedString::new("password\nhost: db.local\nuser: admin".to_owned());
+        let found = matcher.find("prod/db", &plaintext).unwrap();
+        assert_eq!(found.name, "prod/db");
+        assert_eq!(found.lines.len(), 1);
+        assert_eq!(&*found.lines[0].line, "host: db.local");
+        assert_eq!(found.lines[0].ranges, vec![0..4]);
+    }
+
+    #[test]
+    fn should_not_treat_literal_pattern_as_regex() {
+        let matcher = Matcher::new("db.local", false, false).unwrap();
+        let plaintext = ZeroizedString::new("dbxlocal".to_owned());
+        assert!(matcher.find("name", &plaintext).is_none());
+    }
+
+    #[test]
+    fn should_match_regex_pattern() {
+        let matcher = Matcher::new(r"user: \w+", true, false).unwrap();
+        let plaintext = ZeroizedString::new("password\nuser: admin".to_owned());
+        let found = matcher.find("name", &plaintext).unwrap();
+        assert_eq!(found.lines[0].ranges, vec![0..11]);
+    }
+
+    #[test]
+    fn should_ignore_case_if_asked() {
+        let matcher = Matcher::new("HOST", false, true).unwrap();
+        let plaintext = ZeroizedString::new("host: db.local".to_owned());
+        assert!(matcher.find("name", &plaintext).is_some());
+    }
+
+    #[test]
+    fn should_return_none_if_nothing_matched() {
+        let matcher = Matcher::new("missing", false, false).unwrap();
+        let plaintext = ZeroizedString::new("password".to_owned());
+        assert!(matcher.find("name", &plaintext).is_none());
+    }
+}
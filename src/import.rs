@@ -0,0 +1,261 @@
+mod bitwarden;
+mod csv;
+mod keepass;
+
+use crate::{entry::PASSWORD_FIELD, secrets::ZeroizedString};
+use std::str::FromStr;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+pub const USERNAME_FIELD: &str = "username";
+pub const URL_FIELD: &str = "url";
+pub const TOTP_FIELD: &str = "totp";
+const UNTITLED: &str = "untitled";
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Failed to read the export")]
+    Io(#[from] std::io::Error),
+    #[error("The export is not valid CSV")]
+    Csv(#[from] ::csv::Error),
+    #[error("The export is not valid JSON")]
+    Json(#[from] serde_json::Error),
+    #[error("The export is not valid XML")]
+    Xml(#[from] roxmltree::Error),
+    #[error("The export is not in the expected format, {0}")]
+    Format(String),
+}
+
+/// The formats entries can be imported from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    KeePass,
+    Bitwarden,
+    OnePassword,
+    Chrome,
+}
+
+impl Format {
+    /// Parse an export in this format into entries.
+    pub fn parse(self, export: &str) -> Result<Vec<ImportedEntry>, ImportError> {
+        match self {
+            Format::KeePass => keepass::parse(export),
+            Format::Bitwarden => bitwarden::parse(export),
+            Format::OnePassword | Format::Chrome => csv::parse(export),
+        }
+    }
+}
+
+/// What to do when an imported entry has the same name as an existing one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictStrategy {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "rename" => Ok(Self::Rename),
+            _ => Err(format!(
+                "expected one of `skip`, `overwrite` or `rename`, got `{s}`"
+            )),
+        }
+    }
+}
+
+impl ConflictStrategy {
+    /// The name to import `name` as, given the names already `taken`, or
+    /// `None` if it should be skipped. Renamed entries get the first free
+    /// `-1`, `-2`, ... suffix.
+    pub fn resolve(self, name: &str, taken: &[String]) -> Option<String> {
+        let is_taken = |name: &str| taken.iter().any(|taken| taken == name);
+        if !is_taken(name) {
+            return Some(name.to_owned());
+        }
+        match self {
+            Self::Skip => None,
+            Self::Overwrite => Some(name.to_owned()),
+            Self::Rename => (1..)
+                .map(|i| format!("{name}-{i}"))
+                .find(|candidate| !is_taken(candidate)),
+        }
+    }
+}
+
+/// An entry read from another password manager's export.
+#[derive(Default)]
+pub struct ImportedEntry {
+    pub name: String,
+    pub password: ZeroizedString,
+    /// `key: value` fields following the password, such as the username.
+    pub fields: Vec<(String, ZeroizedString)>,
+    pub notes: Option<ZeroizedString>,
+}
+
+impl ImportedEntry {
+    /// Name an entry after its title, placed under `folders`. Slashes in the
+    /// title are replaced so it stays a single entry, and leading dots are
+    /// dropped so it is not hidden.
+    pub fn new<S: AsRef<str>>(folders: &[S], title: &str) -> Self {
+        let segment = |s: &str| {
+            let s = s.trim().replace('/', "-");
+            s.trim_start_matches('.').to_owned()
+        };
+        let mut name = folders
+            .iter()
+            .flat_map(|folder| folder.as_ref().split('/'))
+            .map(segment)
+            .filter(|folder| !folder.is_empty())
+            .collect::<Vec<_>>();
+        let title = segment(title);
+        name.push(if title.is_empty() {
+            UNTITLED.to_owned()
+        } else {
+            title
+        });
+        Self {
+            name: name.join("/"),
+            ..Self::default()
+        }
+    }
+
+    /// Add a field, skipping empty values.
+    pub fn field<K: Into<String>>(&mut self, key: K, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.fields
+                .push((key.into(), ZeroizedString::new(value.to_owned())));
+        }
+    }
+
+    /// The entry in `sec`'s format, the password on the first line followed
+    /// by a `key: value` line per field, then any notes.
+    pub fn to_plaintext(&self) -> ZeroizedString {
+        // Sized up front so the buffer never reallocates, leaving no stray
+        // copies of the plaintext behind.
+        let length = self.password.len()
+            + self
+                .fields
+                .iter()
+                .map(|(key, value)| key.len() + value.len() + 3)
+                .sum::<usize>()
+            + self.notes.as_ref().map_or(0, |notes| notes.len() + 1);
+        let mut plaintext = Zeroizing::new(String::with_capacity(length));
+        plaintext.push_str(&self.password);
+        for (key, value) in &self.fields {
+            if key == PASSWORD_FIELD {
+                continue;
+            }
+            plaintext.push('\n');
+            plaintext.push_str(key);
+            plaintext.push_str(": ");
+            for c in value.chars() {
+                plaintext.push(if c == '\n' { ' ' } else { c });
+            }
+        }
+        if let Some(notes) = &self.notes {
+            plaintext.push('\n');
+            plaintext.push_str(notes);
+        }
+        ZeroizedString::new(plaintext.to_string())
+    }
+}
+
+/// The outcome of importing entries from another password manager, each
+/// entry either imports or fails on its own.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    /// Nothing was written to the store, the report is of what would be.
+    pub dry_run: bool,
+    pub imported: Vec<String>,
+    /// Entries which already existed and were left alone.
+    pub skipped: Vec<String>,
+    /// Entries imported under a new name, as `(name, new name)`.
+    pub renamed: Vec<(String, String)>,
+    pub failed: Vec<ImportFailure>,
+}
+
+/// An entry, or `.gpg-id` file, which could not be imported.
+#[derive(Debug, PartialEq)]
+pub struct ImportFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+impl ImportReport {
+    pub fn fail<N: Into<String>, R: ToString>(&mut self, name: N, reason: R) {
+        self.failed.push(ImportFailure {
+            name: name.into(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConflictStrategy, ImportedEntry};
+    use crate::secrets::ZeroizedString;
+
+    #[test]
+    fn should_name_entries_after_folders_and_title() {
+        let entry = ImportedEntry::new(&["Team", " Servers/Prod "], "db/primary");
+        assert_eq!(entry.name, "Team/Servers/Prod/db-primary");
+        let entry = ImportedEntry::new::<&str>(&[], " .hidden ");
+        assert_eq!(entry.name, "hidden");
+        let entry = ImportedEntry::new(&[""], "");
+        assert_eq!(entry.name, "untitled");
+    }
+
+    #[test]
+    fn plaintext_should_have_password_then_fields_then_notes() {
+        let mut entry = ImportedEntry::new(&["web"], "mail");
+        entry.password = ZeroizedString::new("hunter2".to_owned());
+        entry.field("username", "alice");
+        entry.field("url", "");
+        entry.notes = Some(ZeroizedString::new("line one\nline two".to_owned()));
+        assert_eq!(
+            &*entry.to_plaintext(),
+            "hunter2\nusername: alice\nline one\nline two"
+        );
+    }
+
+    #[test]
+    fn skip_should_only_keep_free_names() {
+        let taken = vec!["db".to_owned()];
+        assert_eq!(
+            ConflictStrategy::Skip.resolve("api", &taken).as_deref(),
+            Some("api")
+        );
+        assert_eq!(ConflictStrategy::Skip.resolve("db", &taken), None);
+    }
+
+    #[test]
+    fn overwrite_should_keep_the_name() {
+        let taken = vec!["db".to_owned()];
+        assert_eq!(
+            ConflictStrategy::Overwrite.resolve("db", &taken).as_deref(),
+            Some("db")
+        );
+    }
+
+    #[test]
+    fn rename_should_pick_the_first_free_suffix() {
+        let taken = vec!["db".to_owned(), "db-1".to_owned()];
+        assert_eq!(
+            ConflictStrategy::Rename.resolve("db", &taken).as_deref(),
+            Some("db-2")
+        );
+    }
+
+    #[test]
+    fn should_parse_conflict_strategy() {
+        assert_eq!("rename".parse(), Ok(ConflictStrategy::Rename));
+        assert!("merge".parse::<ConflictStrategy>().is_err());
+    }
+}
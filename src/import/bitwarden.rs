@@ -0,0 +1,142 @@
+//! Unencrypted Bitwarden JSON exports.
+
+use super::{ImportError, ImportedEntry, TOTP_FIELD, URL_FIELD, USERNAME_FIELD};
+use crate::secrets::ZeroizedString;
+use serde::Deserialize;
+
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: String,
+    folder_id: Option<String>,
+    notes: Option<String>,
+    #[serde(default)]
+    fields: Vec<Field>,
+    login: Option<Login>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Vec<Uri>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+/// Parse logins and secure notes, other item types such as cards and
+/// identities have no password to import and are left out.
+pub fn parse(export: &str) -> Result<Vec<ImportedEntry>, ImportError> {
+    let export: Export = serde_json::from_str(export)?;
+    if export.encrypted {
+        return Err(ImportError::Format(
+            "encrypted exports are not supported, export as unencrypted JSON".to_owned(),
+        ));
+    }
+    let entries = export
+        .items
+        .into_iter()
+        .filter(|item| matches!(item.kind, LOGIN | SECURE_NOTE))
+        .map(|item| {
+            let folder = item
+                .folder_id
+                .as_ref()
+                .and_then(|id| export.folders.iter().find(|folder| &folder.id == id))
+                .map(|folder| folder.name.as_str())
+                .into_iter()
+                .collect::<Vec<_>>();
+            let mut entry = ImportedEntry::new(&folder, &item.name);
+            if let Some(login) = &item.login {
+                entry.password = ZeroizedString::new(login.password.clone().unwrap_or_default());
+                entry.field(
+                    USERNAME_FIELD,
+                    login.username.as_deref().unwrap_or_default(),
+                );
+                if let Some(uri) = login.uris.iter().find_map(|uri| uri.uri.as_deref()) {
+                    entry.field(URL_FIELD, uri);
+                }
+                entry.field(TOTP_FIELD, login.totp.as_deref().unwrap_or_default());
+            }
+            for field in &item.fields {
+                if let (Some(name), Some(value)) = (&field.name, &field.value) {
+                    entry.field(name.to_lowercase(), value);
+                }
+            }
+            entry.notes = item
+                .notes
+                .filter(|notes| !notes.is_empty())
+                .map(ZeroizedString::new);
+            entry
+        })
+        .collect();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::import::ImportError;
+
+    const EXPORT: &str = include_str!("../../tests/resources/import/bitwarden.json");
+
+    #[test]
+    fn should_map_logins_and_secure_notes() {
+        let entries = parse(EXPORT).unwrap();
+        let names = entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Mail", "Servers/db", "Wifi"]);
+        assert_eq!(
+            &*entries[0].to_plaintext(),
+            "correct horse\n\
+             username: alice@example.com\n\
+             url: https://mail.example.com\n\
+             totp: JBSWY3DPEHPK3PXP\n\
+             recovery code: 1234-5678\n\
+             Shared inbox."
+        );
+        assert_eq!(&*entries[1].to_plaintext(), "hunter2\nusername: postgres");
+        assert_eq!(
+            &*entries[2].to_plaintext(),
+            "\nnetwork: office\npassphrase: letmein"
+        );
+    }
+
+    #[test]
+    fn should_reject_encrypted_exports() {
+        let result = parse(r#"{"encrypted": true, "items": []}"#);
+        assert!(matches!(result, Err(ImportError::Format(_))));
+    }
+}
@@ -0,0 +1,111 @@
+//! CSV exports from 1Password and Chrome, columns are found by their header
+//! so either layout, and those of similar tools, can be read.
+
+use super::{ImportError, ImportedEntry, TOTP_FIELD, URL_FIELD, USERNAME_FIELD};
+use crate::secrets::ZeroizedString;
+use csv::{ReaderBuilder, StringRecord};
+
+const TITLE_COLUMNS: [&str; 2] = ["title", "name"];
+const USERNAME_COLUMNS: [&str; 2] = ["username", "login"];
+const PASSWORD_COLUMNS: [&str; 1] = ["password"];
+const URL_COLUMNS: [&str; 3] = ["url", "website", "login_uri"];
+const NOTES_COLUMNS: [&str; 3] = ["notes", "note", "notesplain"];
+const TOTP_COLUMNS: [&str; 4] = ["totp", "otp", "otpauth", "one-time password"];
+
+pub fn parse(export: &str) -> Result<Vec<ImportedEntry>, ImportError> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(export.as_bytes());
+    let headers = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let (title, password) = match (column(&TITLE_COLUMNS), column(&PASSWORD_COLUMNS)) {
+        (Some(title), Some(password)) => (title, password),
+        _ => {
+            return Err(ImportError::Format(
+                "the header needs a title, or name, and a password column".to_owned(),
+            ))
+        }
+    };
+    let username = column(&USERNAME_COLUMNS);
+    let url = column(&URL_COLUMNS);
+    let notes = column(&NOTES_COLUMNS);
+    let totp = column(&TOTP_COLUMNS);
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let get = |column: Option<usize>| get(&record, column);
+        let mut entry = ImportedEntry::new::<&str>(&[], get(Some(title)));
+        entry.password = ZeroizedString::new(get(Some(password)).to_owned());
+        entry.field(USERNAME_FIELD, get(username));
+        entry.field(URL_FIELD, get(url));
+        entry.field(TOTP_FIELD, get(totp));
+        let notes = get(notes);
+        if !notes.is_empty() {
+            entry.notes = Some(ZeroizedString::new(notes.to_owned()));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn get(record: &StringRecord, column: Option<usize>) -> &str {
+    column
+        .and_then(|column| record.get(column))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::import::ImportError;
+
+    const ONE_PASSWORD: &str = include_str!("../../tests/resources/import/1password.csv");
+    const CHROME: &str = include_str!("../../tests/resources/import/chrome.csv");
+
+    #[test]
+    fn should_map_1password_columns() {
+        let entries = parse(ONE_PASSWORD).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Mail");
+        assert_eq!(
+            &*entries[0].to_plaintext(),
+            "correct horse\n\
+             username: alice@example.com\n\
+             url: https://mail.example.com\n\
+             totp: otpauth://totp/Mail:alice?secret=JBSWY3DPEHPK3PXP\n\
+             Shared inbox.\n\
+             Ask Bob for access."
+        );
+        assert_eq!(
+            &*entries[1].to_plaintext(),
+            "hunter2,with comma\nusername: postgres"
+        );
+    }
+
+    #[test]
+    fn should_map_chrome_columns() {
+        let entries = parse(CHROME).unwrap();
+        let names = entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["mail.example.com", "mail.example.com"]);
+        assert_eq!(
+            &*entries[1].to_plaintext(),
+            "tr0ub4dor\n\
+             username: bob@example.com\n\
+             url: https://mail.example.com/login\n\
+             work account"
+        );
+    }
+
+    #[test]
+    fn should_require_title_and_password_columns() {
+        let result = parse("site,secret\na,b\n");
+        assert!(matches!(result, Err(ImportError::Format(_))));
+    }
+}
@@ -0,0 +1,138 @@
+//! KeePass 2 XML exports, as written by KeePass and KeePassXC.
+
+use super::{ImportError, ImportedEntry, TOTP_FIELD, URL_FIELD, USERNAME_FIELD};
+use crate::secrets::ZeroizedString;
+use roxmltree::{Document, Node};
+
+/// Keys KeePass and its plugins store TOTP secrets under.
+const TOTP_KEYS: [&str; 2] = ["otp", "TimeOtp-Secret-Base32"];
+
+pub fn parse(export: &str) -> Result<Vec<ImportedEntry>, ImportError> {
+    let document = Document::parse(export)?;
+    let root = document.root_element();
+    if !root.has_tag_name("KeePassFile") {
+        return Err(ImportError::Format(
+            "the root element is not `KeePassFile`".to_owned(),
+        ));
+    }
+    let recycle_bin = child(root, "Meta")
+        .and_then(|meta| child_text(meta, "RecycleBinUUID"))
+        .unwrap_or_default();
+    let top = child(root, "Root")
+        .and_then(|root| child(root, "Group"))
+        .ok_or_else(|| ImportError::Format("there is no top level group".to_owned()))?;
+    let mut entries = Vec::new();
+    // The top level group is named after the database, so is left out of
+    // entry names.
+    collect_group(top, &mut Vec::new(), recycle_bin, &mut entries);
+    Ok(entries)
+}
+
+fn collect_group<'a>(
+    group: Node<'a, '_>,
+    folders: &mut Vec<&'a str>,
+    recycle_bin: &str,
+    entries: &mut Vec<ImportedEntry>,
+) {
+    for node in group.children().filter(Node::is_element) {
+        if node.has_tag_name("Entry") {
+            entries.push(entry(node, folders));
+        } else if node.has_tag_name("Group") {
+            if child_text(node, "UUID") == Some(recycle_bin) {
+                continue;
+            }
+            folders.push(child_text(node, "Name").unwrap_or_default());
+            collect_group(node, folders, recycle_bin, entries);
+            folders.pop();
+        }
+    }
+}
+
+/// Map an `Entry` element, only its direct `String` children are used so
+/// old versions kept in its `History` are ignored.
+fn entry(node: Node, folders: &[&str]) -> ImportedEntry {
+    let strings = node
+        .children()
+        .filter(|child| child.has_tag_name("String"))
+        .filter_map(|string| {
+            let key = child_text(string, "Key")?;
+            Some((key, child_text(string, "Value").unwrap_or_default()))
+        })
+        .collect::<Vec<_>>();
+    let value = |key: &str| {
+        strings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+            .unwrap_or_default()
+    };
+    let mut entry = ImportedEntry::new(folders, value("Title"));
+    entry.password = ZeroizedString::new(value("Password").to_owned());
+    entry.field(USERNAME_FIELD, value("UserName"));
+    entry.field(URL_FIELD, value("URL"));
+    if let Some((_, totp)) = strings.iter().find(|(key, _)| TOTP_KEYS.contains(key)) {
+        entry.field(TOTP_FIELD, totp);
+    }
+    for (key, value) in &strings {
+        let is_standard = matches!(*key, "Title" | "Password" | "UserName" | "URL" | "Notes");
+        if !is_standard && !TOTP_KEYS.contains(key) {
+            entry.field(key.to_lowercase(), value);
+        }
+    }
+    let notes = value("Notes");
+    if !notes.is_empty() {
+        entry.notes = Some(ZeroizedString::new(notes.to_owned()));
+    }
+    entry
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).map(|child| child.text().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::import::ImportError;
+
+    const EXPORT: &str = include_str!("../../tests/resources/import/keepass.xml");
+
+    #[test]
+    fn should_map_entries_in_nested_groups() {
+        let entries = parse(EXPORT).unwrap();
+        let names = entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Mail", "Servers/db-primary"]);
+        assert_eq!(
+            &*entries[0].to_plaintext(),
+            "correct horse\n\
+             username: alice@example.com\n\
+             url: https://mail.example.com\n\
+             totp: otpauth://totp/Mail:alice?secret=JBSWY3DPEHPK3PXP\n\
+             Shared inbox.\n\
+             Ask Bob for access."
+        );
+        assert_eq!(
+            &*entries[1].to_plaintext(),
+            "hunter2\nusername: postgres\nport: 5432"
+        );
+    }
+
+    #[test]
+    fn should_reject_other_xml_documents() {
+        assert!(matches!(
+            parse("<html></html>"),
+            Err(ImportError::Format(_))
+        ));
+        assert!(matches!(
+            parse("<KeePassFile><Root></KeePassFile>"),
+            Err(ImportError::Xml(_))
+        ));
+    }
+}
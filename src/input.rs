@@ -1,24 +1,198 @@
-use crate::cli::{Action, Args};
-use crate::fs::FileSystemOperator;
-use crate::gpg::Gpg;
+use crate::armor::Encoding;
+use crate::audit::EntryAudit;
+use crate::browser;
+use crate::cli::{
+    Action, Args, EnvAction, EnvBinding, ExportArgs, ImportOptions, ImportSource, KeysAction,
+};
+use crate::config::DEFAULT_EXPIRY_WARNING_DAYS;
+use crate::crypto::{CryptoBackend, CryptoError};
+use crate::dotenv::{self, DotenvError};
+use crate::entry::{self, EntryRef, PASSWORD_FIELD};
+use crate::error::{Error, InitError};
+use crate::find::{find, NamePattern};
+use crate::fs::{self, FileSystemOperator};
+use crate::fsck::Issue;
+use crate::generate;
+use crate::grep::{GrepFailure, GrepMatch, Matcher};
+use crate::import::{Format, ImportError, ImportReport};
+use crate::keys::KeyInfo;
+use crate::message;
+use crate::picker;
 use crate::secrets::{SecretReader, ZeroizedByteVec, ZeroizedString};
-use crate::store::Store;
+use crate::store::{self, Store, StoreError};
+use crate::template::{self, Segment};
+#[cfg(feature = "gpg")]
+use crate::{
+    armor,
+    audit::{self, KeyStatus, Recipient},
+    backup::{self, BackupFile},
+    cli::{AuditReport, BackupAction},
+    config::{self, CONFIG_FILE},
+    error::SignatureError,
+    fsck::{self, Problem, Status},
+    gpg::Gpg,
+    store::{OnDiskStore, ARMORED_ENTRY_EXTENSION},
+};
 use anyhow::{anyhow, Context};
-use std::io::Write;
+use crossterm::tty::IsTty;
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Write};
+#[cfg(feature = "gpg")]
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, SystemTime};
+use zeroize::Zeroizing;
 
 pub const PASSWORD_STORE_DIRECTORY: &str = ".password-store";
 pub const GPG_ID_LIST_FILE: &str = ".gpg-id";
+pub const GPG_ID_SIGNATURE_FILE: &str = ".gpg-id.sig";
+/// How much of the start of an entry is read to find whether it is
+/// encrypted with a passphrase alone, which is plenty for the session key
+/// packets of a message, armored or not.
+const ENTRY_HEAD_LENGTH: u64 = 8 * 1024;
 
-pub fn handle<F, R, S>(handler: &Handler<F, R, S>, args: &Args) -> anyhow::Result<HandlerResult>
+pub fn handle<F, R, S, C>(
+    handler: &Handler<F, R, S, C>,
+    args: &Args,
+) -> Result<HandlerResult, Error>
 where
     R: SecretReader,
     S: Store,
     F: FileSystemOperator,
+    C: CryptoBackend,
 {
     match &args.action {
-        Action::Insert { name, key_id } => handler.insert(name, key_id),
-        Action::Retrieve { name } => handler.retrieve(name),
+        #[cfg(not(feature = "gpg"))]
+        Action::Insert {
+            symmetric: true, ..
+        }
+        | Action::Backup { .. }
+        | Action::Import {
+            source: ImportSource::Pass { .. },
+        }
+        | Action::Fsck { .. }
+        | Action::Audit { .. }
+        | Action::Keys {
+            action: KeysAction::List | KeysAction::Import { .. } | KeysAction::Export { .. },
+        }
+        | Action::Convert { .. } => Err(unsupported("This command")),
+        #[cfg(feature = "gpg")]
+        Action::Insert {
+            name,
+            symmetric: true,
+            ..
+        } => handler.insert_symmetric(name),
+        Action::Insert {
+            name,
+            key_id: Some(key_id),
+            file: Some(file),
+            ..
+        } => handler.insert_file(name, key_id, file),
+        Action::Insert {
+            name,
+            key_id: Some(key_id),
+            ..
+        } => handler.insert(name, key_id),
+        Action::Insert { key_id: None, .. } => {
+            Err(anyhow!("No key id was given to encrypt the entry to.").into())
+        }
+        Action::Retrieve {
+            name: Some(name),
+            output: Some(output),
+        } => handler.retrieve_to_file(name, output),
+        Action::Retrieve {
+            name: Some(name), ..
+        } => handler.retrieve(name),
+        Action::Retrieve { name: None, .. } => handler.retrieve_interactively(),
         Action::Initialize { key_id } => handler.initialize(key_id),
+        Action::List => handler.list(),
+        Action::Tui => handler.browse(),
+        Action::Find { patterns } => handler.find(patterns),
+        Action::Run { env, command } => handler.run(env, command),
+        Action::Env {
+            action: EnvAction::Export { folder },
+        } => handler.export_env(folder),
+        Action::Env {
+            action:
+                EnvAction::Import {
+                    file,
+                    prefix,
+                    force,
+                },
+        } => handler.import_env(file, prefix.as_deref(), *force),
+        #[cfg(feature = "gpg")]
+        Action::Backup {
+            action: BackupAction::Create { output, key },
+        } => handler.backup_create(output, key),
+        #[cfg(feature = "gpg")]
+        Action::Backup {
+            action:
+                BackupAction::Restore {
+                    file,
+                    overwrite,
+                    trust_gpg_id,
+                },
+        } => handler.backup_restore(file, *overwrite, *trust_gpg_id),
+        #[cfg(feature = "gpg")]
+        Action::Import {
+            source:
+                ImportSource::Pass {
+                    dir,
+                    replace_gpg_ids,
+                    trust_gpg_id,
+                    options,
+                },
+        } => handler.import_pass(dir, *replace_gpg_ids, *trust_gpg_id, options),
+        Action::Import { source } => match source {
+            ImportSource::KeePass(args) => handler.import(Format::KeePass, args),
+            ImportSource::Bitwarden(args) => handler.import(Format::Bitwarden, args),
+            ImportSource::OnePassword(args) => handler.import(Format::OnePassword, args),
+            ImportSource::Chrome(args) => handler.import(Format::Chrome, args),
+            ImportSource::Pass { .. } => unreachable!("matched above"),
+        },
+        Action::Render {
+            template,
+            check: true,
+            ..
+        } => handler.check_template(template),
+        Action::Render {
+            template,
+            output: Some(output),
+            ..
+        } => handler.render(template, output),
+        Action::Render { output: None, .. } => {
+            Err(anyhow!("No output path was given to render the template to.").into())
+        }
+        Action::Grep {
+            pattern,
+            regex,
+            ignore_case,
+        } => handler.grep(pattern, *regex, *ignore_case),
+        #[cfg(feature = "gpg")]
+        Action::Fsck { repair } => handler.fsck(*repair),
+        #[cfg(feature = "gpg")]
+        Action::Audit {
+            report: AuditReport::Recipients { folder, json },
+        } => handler.audit_recipients(folder.as_deref(), *json),
+        #[cfg(feature = "gpg")]
+        Action::Keys {
+            action: KeysAction::List,
+        } => handler.keys_list(),
+        #[cfg(feature = "gpg")]
+        Action::Keys {
+            action: KeysAction::Import { file },
+        } => handler.keys_import(file),
+        #[cfg(feature = "gpg")]
+        Action::Keys {
+            action: KeysAction::Export { key_id },
+        } => handler.keys_export(key_id),
+        Action::Keys {
+            action: KeysAction::Rotate { old, new },
+        } => handler.keys_rotate(old, new),
+        #[cfg(feature = "gpg")]
+        Action::Convert { encoding } => handler.convert(*encoding),
     }
 }
 
@@ -26,302 +200,3274 @@ where
 pub enum HandlerResult {
     Insert(String),
     Retrieve(ZeroizedString),
+    RetrieveFile(String),
     Initialize(),
+    List {
+        names: Vec<String>,
+        /// The entries which can be decrypted with a passphrase alone.
+        symmetric: Vec<String>,
+    },
+    Find(Vec<String>),
+    Grep {
+        matches: Vec<GrepMatch>,
+        /// Entries which could not be searched, the rest still are.
+        failed: Vec<GrepFailure>,
+    },
+    Browse(),
+    Run(u8),
+    Render(String),
+    CheckTemplate(usize),
+    EnvExport(Vec<ZeroizedString>),
+    EnvImport(Vec<String>),
+    Import(ImportReport),
+    BackupCreate {
+        path: String,
+        entries: usize,
+    },
+    BackupRestore(usize),
+    Fsck(Vec<Issue>),
+    AuditRecipients {
+        entries: Vec<EntryAudit>,
+        json: bool,
+    },
+    KeysList(Vec<KeyInfo>),
+    KeysImport(Vec<String>),
+    KeysExport(String),
+    KeysRotate {
+        folders: usize,
+        entries: usize,
+    },
+    Convert {
+        encoding: Encoding,
+        entries: usize,
+    },
 }
 
-pub struct Handler<H, R, S> {
-    gpg: Gpg,
+pub struct Handler<H, R, S, C> {
+    backend: C,
     store: S,
     reader: R,
     fs_ops: H,
+    /// Fingerprints of the keys trusted to sign `.gpg-id` files. When there
+    /// are any, `.gpg-id` files are signed on initialize and verified before
+    /// encrypting to the keys in them.
+    signing_keys: Vec<String>,
+    /// How long before a recipient expires to warn about it when encrypting.
+    expiry_warning: Duration,
+    /// Where the passphrases of symmetrically encrypted entries are read
+    /// from.
+    passphrase_reader: Option<Box<dyn SecretReader>>,
+    /// Problems which did not stop the command, for the caller to report.
+    warnings: RefCell<Vec<String>>,
 }
 
-impl<F, R, S> Handler<F, R, S>
+#[cfg(feature = "gpg")]
+impl<F, R, S> Handler<F, R, S, Gpg>
 where
     R: SecretReader,
     S: Store,
     F: FileSystemOperator,
 {
     pub fn new(store: S, reader: R, fs_ops: F) -> Self {
+        Self::with_backend(store, reader, fs_ops, Gpg::default())
+    }
+}
+
+impl<F, R, S, C> Handler<F, R, S, C>
+where
+    R: SecretReader,
+    S: Store,
+    F: FileSystemOperator,
+    C: CryptoBackend,
+{
+    /// A handler which encrypts entries with `backend`, the store should use
+    /// the backend's file names, see [`OnDiskStore::with_file_names`].
+    pub fn with_backend(store: S, reader: R, fs_ops: F, backend: C) -> Self {
         Self {
-            gpg: Gpg::default(),
+            backend,
             store,
             reader,
             fs_ops,
+            signing_keys: Vec::new(),
+            expiry_warning: Duration::from_secs(DEFAULT_EXPIRY_WARNING_DAYS * 24 * 60 * 60),
+            passphrase_reader: None,
+            warnings: RefCell::default(),
+        }
+    }
+
+    /// Sign `.gpg-id` files with, and only trust signatures from, the keys
+    /// with the given fingerprints.
+    pub fn with_signing_keys(mut self, signing_keys: Vec<String>) -> Self {
+        self.signing_keys = signing_keys;
+        self
+    }
+
+    /// Warn about recipients which expire within `expiry_warning` whenever
+    /// something is encrypted to them.
+    pub fn with_expiry_warning(mut self, expiry_warning: Duration) -> Self {
+        self.expiry_warning = expiry_warning;
+        self
+    }
+
+    /// Read the passphrases of symmetrically encrypted entries from
+    /// `reader`.
+    pub fn with_passphrase_reader(mut self, reader: Box<dyn SecretReader>) -> Self {
+        self.passphrase_reader = Some(reader);
+        self
+    }
+
+    /// The warnings from the commands run so far, which are then cleared.
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    /// Keep `warning` for the caller, once however often it is given.
+    fn warn(&self, warning: String) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
         }
     }
 
     /// Create a file named with the value of `name` whose contents are taken
     /// from the [`Handler`]'s [`SecretReader`] instance, and encrypted via
-    /// the [`Gpg::encrypt`] call.
-    pub fn insert(&self, name: &str, key_id: &str) -> anyhow::Result<HandlerResult> {
+    /// the [`CryptoBackend::encrypt`] call to every key in the `.gpg-id`
+    /// file nearest to the entry, which must list `key_id`.
+    pub fn insert(&self, name: &str, key_id: &str) -> Result<HandlerResult, Error> {
+        store::validate_name(name)?;
+        self.ensure_initialized()?;
+        let recipients = self.recipients_including(name, key_id)?;
         let buf = &self.read_in_secret_value()?;
-        let ciphertext = self.gpg.encrypt(key_id, buf.as_ref())?;
+        let ciphertext = self.backend.encrypt(&recipients, buf.as_ref())?;
         self.write_out_value(name, &ciphertext)?;
         Ok(HandlerResult::Insert(name.to_owned()))
     }
 
-    fn read_in_secret_value(&self) -> anyhow::Result<ZeroizedByteVec> {
-        self.reader.read_secret()
+    /// Create the entry `name` as [`Handler::insert`] does, from the
+    /// contents of the file at `path`, or of stdin if it is `-`. The contents
+    /// are encrypted as they are read, so a file of any size is never held
+    /// in memory.
+    pub fn insert_file(
+        &self,
+        name: &str,
+        key_id: &str,
+        path: &str,
+    ) -> Result<HandlerResult, Error> {
+        store::validate_name(name)?;
+        self.ensure_initialized()?;
+        let recipients = self.recipients_including(name, key_id)?;
+        let mut input: Box<dyn Read + Send> = match path {
+            "-" => Box::new(io::stdin()),
+            path => Box::new(File::open(path).with_context(|| format!("Failed to read `{path}`"))?),
+        };
+        let to_error = |source: StoreError| Error::Insert {
+            name: name.to_owned(),
+            source,
+        };
+        let mut writer = self.store.writer(name).map_err(to_error)?;
+        self.backend
+            .encrypt_stream(&recipients, &mut *input, &mut writer)?;
+        self.store.commit(writer).map_err(to_error)?;
+        Ok(HandlerResult::Insert(name.to_owned()))
     }
 
-    fn write_out_value(&self, name: &str, ciphertext: &[u8]) -> anyhow::Result<()> {
-        self.store.insert(name, ciphertext).with_context(|| {
-            format!("An error occurred when attempting to insert the entry `{name}`.")
-        })
+    /// Create the entry `name` as [`Handler::insert`] does, but encrypted
+    /// with a passphrase rather than to any key, for sharing with those who
+    /// have none.
+    #[cfg(feature = "gpg")]
+    pub fn insert_symmetric(&self, name: &str) -> Result<HandlerResult, Error> {
+        store::validate_name(name)?;
+        self.ensure_initialized()?;
+        let gpg = self.openpgp("Symmetric encryption")?;
+        let buf = &self.read_in_secret_value()?;
+        let passphrase = self.read_passphrase()?;
+        let ciphertext = gpg.encrypt_symmetric(buf.as_ref(), &passphrase)?;
+        self.write_out_value(name, &ciphertext)?;
+        Ok(HandlerResult::Insert(name.to_owned()))
+    }
+
+    #[cfg(feature = "gpg")]
+    fn read_passphrase(&self) -> Result<ZeroizedString, Error> {
+        let reader = self
+            .passphrase_reader
+            .as_ref()
+            .ok_or_else(|| anyhow!("No passphrase can be read for symmetric entries."))?;
+        let passphrase = reader
+            .read_secret()?
+            .into_zeroized_string()
+            .context("The passphrase must be UTF-8 text")?;
+        if passphrase.is_empty() {
+            return Err(anyhow!("The passphrase must not be empty.").into());
+        }
+        Ok(passphrase)
+    }
+
+    /// Whether `ciphertext`, or the start of it, is an OpenPGP message
+    /// encrypted with a passphrase and to no key.
+    fn is_symmetric_only(&self, ciphertext: &[u8]) -> bool {
+        self.is_openpgp()
+            && message::inspect_head(ciphertext).is_ok_and(|message| message.is_symmetric_only())
+    }
+
+    /// Whether the entry `name` is encrypted with a passphrase alone, read
+    /// from the start of the entry rather than all of it.
+    fn is_symmetric_only_entry(&self, name: &str) -> Result<bool, Error> {
+        if !self.is_openpgp() {
+            return Ok(false);
+        }
+        let mut head = Vec::new();
+        self.store
+            .reader(name)?
+            .take(ENTRY_HEAD_LENGTH)
+            .read_to_end(&mut head)
+            .map_err(StoreError::from)?;
+        Ok(self.is_symmetric_only(&head))
+    }
+
+    /// The passphrase to decrypt `ciphertext`, or the start of it, with,
+    /// read from the passphrase reader if it is encrypted with one alone.
+    #[cfg(feature = "gpg")]
+    fn entry_passphrase(&self, ciphertext: &[u8]) -> Result<Option<ZeroizedString>, Error> {
+        if self.is_symmetric_only(ciphertext) {
+            Ok(Some(self.read_passphrase()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[cfg(not(feature = "gpg"))]
+    fn entry_passphrase(&self, _ciphertext: &[u8]) -> Result<Option<ZeroizedString>, Error> {
+        Ok(None)
+    }
+
+    fn read_in_secret_value(&self) -> Result<ZeroizedByteVec, Error> {
+        Ok(self.reader.read_secret()?)
+    }
+
+    fn write_out_value(&self, name: &str, ciphertext: &[u8]) -> Result<(), Error> {
+        self.store
+            .insert(name, ciphertext)
+            .map_err(|source| Error::Insert {
+                name: name.to_owned(),
+                source,
+            })
     }
 
     /// Retrieve a secret from the entry with the value of `name`.
-    pub fn retrieve(&self, name: &str) -> anyhow::Result<HandlerResult> {
-        let value = self.store.get(name).with_context(|| {
-            format!("An error occurred when attempting to retrieve the entry `{name}`.")
+    pub fn retrieve(&self, name: &str) -> Result<HandlerResult, Error> {
+        store::validate_name(name)?;
+        self.ensure_initialized()?;
+        Ok(HandlerResult::Retrieve(self.decrypt_entry(name)?))
+    }
+
+    /// Decrypt the entry `name` into the file at `output_path`, readable only
+    /// by its owner. The entry is decrypted as it is written, so an entry of
+    /// any size is never held in memory, into a new file which replaces any
+    /// at `output_path` only once all of it is decrypted. Entries encrypted
+    /// with a passphrase alone are decrypted as [`Handler::decrypt_entry`]
+    /// does.
+    pub fn retrieve_to_file(&self, name: &str, output_path: &str) -> Result<HandlerResult, Error> {
+        store::validate_name(name)?;
+        self.ensure_initialized()?;
+        let to_retrieve_error = |source| Error::Retrieve {
+            name: name.to_owned(),
+            source,
+        };
+        let mut reader = self.store.reader(name).map_err(to_retrieve_error)?;
+        let mut head = Vec::new();
+        (&mut reader)
+            .take(ENTRY_HEAD_LENGTH)
+            .read_to_end(&mut head)
+            .map_err(|e| to_retrieve_error(e.into()))?;
+        let passphrase = self.entry_passphrase(&head)?;
+        let to_error = |source| Error::Write {
+            path: output_path.to_owned(),
+            source,
+        };
+        let mut output = fs::ReplacingFile::beside(output_path).map_err(to_error)?;
+        let decrypted = match passphrase {
+            Some(passphrase) => {
+                let mut ciphertext = head;
+                reader
+                    .read_to_end(&mut ciphertext)
+                    .map_err(|e| to_retrieve_error(e.into()))?;
+                decrypt_value(&self.backend, &ciphertext, Some(&passphrase)).and_then(|plaintext| {
+                    output
+                        .write_all(&plaintext)
+                        .map_err(|e| CryptoError::Backend(Box::new(e)))
+                })
+            }
+            None => self
+                .backend
+                .decrypt_stream(&mut head.as_slice().chain(reader), &mut output),
+        };
+        if let Err(source) = decrypted {
+            return Err(Error::Decrypt {
+                name: name.to_owned(),
+                source,
+            });
+        }
+        output.persist().map_err(to_error)?;
+        Ok(HandlerResult::RetrieveFile(output_path.to_owned()))
+    }
+
+    /// Read and decrypt the entry with the value of `name`. Entries
+    /// encrypted with a passphrase alone are decrypted with one read from
+    /// the passphrase reader.
+    pub fn decrypt_entry(&self, name: &str) -> Result<ZeroizedString, Error> {
+        let value = self.store.get(name).map_err(|source| Error::Retrieve {
+            name: name.to_owned(),
+            source,
         })?;
-        let plaintext = self
-            .gpg
-            .decrypt(&value)
-            .with_context(|| format!(r#"The entry "{name}" could not be decrypted!"#))?;
-        Ok(HandlerResult::Retrieve(plaintext))
+        let passphrase = self.entry_passphrase(&value)?;
+        decrypt_value(&self.backend, &value, passphrase.as_ref())
+            .map_err(|source| Error::Decrypt {
+                name: name.to_owned(),
+                source,
+            })?
+            .into_zeroized_string()
+            .map_err(|_| Error::BinaryEntry {
+                name: name.to_owned(),
+            })
     }
 
-    pub fn initialize(&self, key_id: &str) -> anyhow::Result<HandlerResult> {
-        let _ = self.gpg.does_key_exist(key_id)?;
-        let home_dir = self
-            .fs_ops
-            .home_dir()
-            .with_context(|| "no home directory could be found...")?
-            .into_os_string()
-            .into_string()
-            .map_err(|_| anyhow!("failed to convert path to utf-8 string"))?;
-        let store_path = format!("{home_dir}/{}", PASSWORD_STORE_DIRECTORY);
-        self.fs_ops.mkdir(&store_path)?;
-        let mut key_list = self
-            .fs_ops
-            .touch(&format!("{store_path}/{GPG_ID_LIST_FILE}"))?;
-        key_list.write_all(key_id.as_bytes())?;
-        Ok(HandlerResult::Initialize())
+    /// Resolve a reference to an entry, or one of its fields, to its
+    /// decrypted value. Without a field the first line of the entry is used.
+    pub fn resolve(&self, entry: &EntryRef) -> Result<ZeroizedString, Error> {
+        let plaintext = self.decrypt_entry(&entry.name)?;
+        let field = entry.field.as_deref().unwrap_or(PASSWORD_FIELD);
+        entry::field(&plaintext, field)
+            .map(|value| ZeroizedString::new(value.to_owned()))
+            .ok_or_else(|| Error::FieldDoesNotExist {
+                name: entry.name.clone(),
+                field: field.to_owned(),
+            })
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::{HandlerResult, GPG_ID_LIST_FILE, PASSWORD_STORE_DIRECTORY};
-    use crate::{
-        cli::Action,
-        fs::FileSystemOperator,
-        gpg::{
-            test::{import_keys, GPG_KEY_ID},
-            Gpg,
-        },
-        input::handle,
-        secrets::{SecretReader, ZeroizedByteVec},
-        store::{Store, StoreError},
-        Args, Handler,
-    };
-    use std::{
-        cell::RefCell,
-        collections::HashMap,
-        fs::File,
-        path::{Path, PathBuf},
-        rc::Rc,
-        str::FromStr,
-    };
-    use tempfile::tempdir;
+    /// Run `command` with each binding in `env` set as an environment
+    /// variable.
+    ///
+    /// [`Command`] copies each value into an environment block of its own,
+    /// which cannot be zeroized. So the command is dropped as soon as the
+    /// child is spawned, along with the decrypted values, rather than held
+    /// until the child exits. The child's own copy lives as long as it does.
+    pub fn run(&self, env: &[EnvBinding], command: &[String]) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| anyhow!("No command was given to run."))?;
+        let to_error = |source| Error::Run {
+            command: program.clone(),
+            source,
+        };
+        let mut child = {
+            let values = env
+                .iter()
+                .map(|binding| Ok((binding.var.as_str(), self.resolve(&binding.entry)?)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            Command::new(program)
+                .args(args)
+                .envs(values.iter().map(|(var, value)| (var, &**value)))
+                .spawn()
+                .map_err(to_error)?
+        };
+        let status = child.wait().map_err(to_error)?;
+        Ok(HandlerResult::Run(exit_code(status)))
+    }
 
-    const EXISTING_GPG_KEY: &str = "passrs-tests@nocht.io";
+    /// Render the template at `template_path` to `output_path`, replacing
+    /// each placeholder with the value it references. The output is only
+    /// written once every reference has resolved, and is readable only by
+    /// its owner.
+    pub fn render(&self, template_path: &str, output_path: &str) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let source = read_template(template_path)?;
+        let segments = parse_template(template_path, &source)?;
+        let values = template::references(&segments)
+            .map(|entry| self.resolve(entry))
+            .collect::<Result<Vec<_>, Error>>()?;
+        // Sized up front so the buffer never reallocates, leaving no stray
+        // copies of the decrypted values behind.
+        let length = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.len(),
+                Segment::Secret(_) => 0,
+            })
+            .sum::<usize>()
+            + values.iter().map(|value| value.len()).sum::<usize>();
+        let mut rendered = Zeroizing::new(String::with_capacity(length));
+        let mut values = values.iter();
+        for segment in &segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Secret(_) => rendered.push_str(values.next().expect("resolved above")),
+            }
+        }
+        fs::write_private(output_path, rendered.as_bytes()).map_err(|source| Error::Write {
+            path: output_path.to_owned(),
+            source,
+        })?;
+        Ok(HandlerResult::Render(output_path.to_owned()))
+    }
 
-    #[derive(Clone)]
-    struct InMemoryStore {
-        store: Rc<RefCell<HashMap<String, Vec<u8>>>>,
+    /// Check every entry referenced by the template at `template_path`
+    /// exists, nothing is decrypted so missing fields are not detected.
+    pub fn check_template(&self, template_path: &str) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let source = read_template(template_path)?;
+        let segments = parse_template(template_path, &source)?;
+        let names = self.store.list()?;
+        let mut unresolved = Vec::new();
+        for entry in template::references(&segments) {
+            if !names.contains(&entry.name) && !unresolved.contains(&entry.name) {
+                unresolved.push(entry.name.clone());
+            }
+        }
+        if !unresolved.is_empty() {
+            return Err(Error::UnresolvedReferences(unresolved));
+        }
+        Ok(HandlerResult::CheckTemplate(
+            template::references(&segments).count(),
+        ))
     }
 
-    impl InMemoryStore {
-        fn new() -> Self {
-            Self {
-                store: Rc::new(RefCell::new(HashMap::new())),
+    /// Decrypt every entry under `folder` as a `KEY=value` line, with the
+    /// entry name relative to the folder converted to SCREAMING_SNAKE_CASE.
+    /// The whole of each entry is exported, quoted so that multi-line values
+    /// import back unchanged.
+    pub fn export_env(&self, folder: &str) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let prefix = format!("{}/", folder.trim_matches('/'));
+        let names = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Err(anyhow!("The folder `{folder}` contains no entries.").into());
+        }
+        let mut keys: Vec<(String, &str)> = Vec::with_capacity(names.len());
+        let mut lines = Vec::with_capacity(names.len());
+        for name in &names {
+            let key = dotenv::variable_name(&name[prefix.len()..]);
+            if let Some((_, other)) = keys.iter().find(|(existing, _)| *existing == key) {
+                return Err(
+                    anyhow!("The entries `{other}` and `{name}` both export as `{key}`.").into(),
+                );
             }
+            let value = self.decrypt_entry(name)?;
+            lines.push(dotenv::format_line(&key, &value));
+            keys.push((key, name));
         }
+        Ok(HandlerResult::EnvExport(lines))
     }
 
-    impl Store for InMemoryStore {
-        fn insert<S: AsRef<str>>(&self, name: S, value: &[u8]) -> Result<(), StoreError> {
-            self.store
-                .clone()
-                .borrow_mut()
-                .insert(name.as_ref().to_owned(), value.to_vec());
-            Ok(())
+    /// Save each variable in the `.env` file at `path` as its own entry,
+    /// named after the variable and placed under `prefix` if given. Nothing
+    /// is saved if any of the entries already exist, unless `force` is set.
+    pub fn import_env(
+        &self,
+        path: &str,
+        prefix: Option<&str>,
+        force: bool,
+    ) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let to_error = |source: DotenvError| Error::Dotenv {
+            path: path.to_owned(),
+            source,
+        };
+        let contents = Zeroizing::new(
+            std::fs::read_to_string(path).map_err(|source| to_error(source.into()))?,
+        );
+        let variables = dotenv::parse(&contents).map_err(to_error)?;
+        let prefix = prefix.map(|prefix| prefix.trim_matches('/'));
+        let names = variables
+            .iter()
+            .map(|variable| match prefix {
+                Some(prefix) if !prefix.is_empty() => format!("{prefix}/{}", variable.key),
+                _ => variable.key.clone(),
+            })
+            .collect::<Vec<_>>();
+        if !force {
+            let existing = self.store.list()?;
+            let conflicts = names
+                .iter()
+                .filter(|name| existing.contains(name))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if !conflicts.is_empty() {
+                return Err(anyhow!(
+                    "The store already has entries in the env file, import with `--force` to replace them: {}",
+                    conflicts.join(", ")
+                )
+                .into());
+            }
         }
+        for (name, variable) in names.iter().zip(&variables) {
+            self.save(name, variable.value.as_bytes())?;
+        }
+        Ok(HandlerResult::EnvImport(names))
+    }
 
-        fn get<S: AsRef<str>>(&self, name: S) -> Result<Vec<u8>, StoreError> {
-            if let Some(value) = self.store.clone().borrow().get(name.as_ref()) {
-                Ok(value.clone())
+    /// Import every entry, and nested `.gpg-id` file, from the `pass` store
+    /// at `dir`. Each entry is decrypted to check it is readable, then copied
+    /// as is if it is already encrypted to the keys it would be here, or
+    /// re-encrypted otherwise.
+    ///
+    /// A `.gpg-id` file is not imported if it lists a key which cannot be
+    /// used, if the store already has one there listing other keys and
+    /// `replace_gpg_ids` is not set, or, with signing keys configured, if it
+    /// is not signed by one of them and `trust_key_ids` is not set. The
+    /// entries under it then fail rather than being encrypted to the keys of
+    /// the folder above.
+    #[cfg(feature = "gpg")]
+    pub fn import_pass(
+        &self,
+        dir: &str,
+        replace_gpg_ids: bool,
+        trust_key_ids: bool,
+        options: &ImportOptions,
+    ) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let gpg = self.openpgp("Importing a pass store")?;
+        if !Path::new(dir).is_dir() {
+            return Err(anyhow!("`{dir}` is not a directory.").into());
+        }
+        let source = OnDiskStore::new(dir.trim_end_matches('/'));
+        let mut report = ImportReport {
+            dry_run: options.dry_run,
+            ..ImportReport::default()
+        };
+        let mut rejected = Vec::new();
+        for folder in source.key_id_folders()? {
+            let key_ids = source.folder_key_ids(&folder)?.unwrap_or_default();
+            let target = options.prefixed(&folder);
+            let checked = if trust_key_ids {
+                Ok(())
             } else {
-                Err(StoreError::EntryDoesNotExist(name.as_ref().to_owned()))
+                self.verify_key_id_file(&source, &folder)
+                    .map_err(|e| e.to_string())
+            };
+            let checked = checked.and_then(|()| {
+                self.check_imported_key_ids(gpg, &target, &key_ids, replace_gpg_ids)
+            });
+            match checked {
+                Ok(true) if !options.dry_run => {
+                    let contents = self.key_id_file_contents(&key_ids)?;
+                    self.set_key_id_file(&target, &contents)?;
+                }
+                Ok(_) => {}
+                Err(reason) => {
+                    report.fail(format!("{target}/{GPG_ID_LIST_FILE}"), reason);
+                    rejected.push(folder);
+                }
+            }
+        }
+        let mut taken = self.store.list()?;
+        for name in source.list()? {
+            if rejected.contains(&source.key_id_folder(&name)?) {
+                report.fail(
+                    options.prefixed(&name),
+                    format!("its {GPG_ID_LIST_FILE} file was not imported"),
+                );
+                continue;
+            }
+            let target =
+                match claim_name(&options.prefixed(&name), options, &mut taken, &mut report) {
+                    Some(target) => target,
+                    None => continue,
+                };
+            match self.import_pass_entry(gpg, &source, &name, &target, options.dry_run) {
+                Ok(()) => report.imported.push(target),
+                Err(e) => report.fail(target, e),
             }
         }
+        Ok(HandlerResult::Import(report))
     }
 
-    struct IoErrorStore;
+    /// Whether the `.gpg-id` file listing `key_ids` should be written to
+    /// `folder`, as the store does not already have the same one there, or
+    /// why it must not be.
+    #[cfg(feature = "gpg")]
+    fn check_imported_key_ids(
+        &self,
+        gpg: &Gpg,
+        folder: &str,
+        key_ids: &[String],
+        replace: bool,
+    ) -> Result<bool, String> {
+        if let Some(e) = key_ids
+            .iter()
+            .find_map(|key_id| gpg.does_key_exist(key_id).err())
+        {
+            return Err(e.to_string());
+        }
+        let sorted = |key_ids: &[String]| {
+            let mut key_ids = key_ids.to_vec();
+            key_ids.sort();
+            key_ids
+        };
+        match self.store.folder_key_ids(folder) {
+            Ok(None) => Ok(true),
+            Ok(Some(existing)) if sorted(&existing) == sorted(key_ids) => Ok(false),
+            Ok(Some(_)) if replace => self
+                .verify_key_id_file(&self.store, folder)
+                .map(|()| true)
+                .map_err(|e| e.to_string()),
+            Ok(Some(_)) => Err(format!(
+                "the store already has a {GPG_ID_LIST_FILE} file here listing other keys, \
+                 import with `--replace-gpg-ids` to replace it"
+            )),
+            Err(e) => Err(e.to_string()),
+        }
+    }
 
-    impl Store for IoErrorStore {
-        fn insert<S: AsRef<str>>(&self, _: S, _: &[u8]) -> Result<(), StoreError> {
-            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
-            Ok(())
+    #[cfg(feature = "gpg")]
+    fn import_pass_entry(
+        &self,
+        gpg: &Gpg,
+        source: &OnDiskStore,
+        name: &str,
+        target: &str,
+        dry_run: bool,
+    ) -> Result<(), Error> {
+        let ciphertext = source.get(name).map_err(|source| Error::Retrieve {
+            name: name.to_owned(),
+            source,
+        })?;
+        let plaintext = gpg
+            .decrypt_bytes(&ciphertext)
+            .map_err(|source| Error::Decrypt {
+                name: name.to_owned(),
+                source: source.into(),
+            })?;
+        if dry_run {
+            return Ok(());
         }
+        let sorted = |mut key_ids: Vec<String>| {
+            key_ids.sort();
+            key_ids
+        };
+        let from = source.recipients(name).ok().map(sorted);
+        let to = sorted(self.store.recipients(target)?);
+        if from.as_ref() == Some(&to) {
+            self.write_out_value(target, &ciphertext)
+        } else {
+            self.save(target, &plaintext)
+        }
+    }
 
-        fn get<S: AsRef<str>>(&self, _: S) -> Result<Vec<u8>, StoreError> {
-            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
-            Ok(Vec::new())
+    /// Import the entries of another password manager's export, each is
+    /// encrypted to the keys in the `.gpg-id` file nearest to it.
+    pub fn import(&self, format: Format, args: &ExportArgs) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let to_error = |source: ImportError| Error::Import {
+            path: args.file.clone(),
+            source,
+        };
+        let export = Zeroizing::new(
+            std::fs::read_to_string(&args.file).map_err(|source| to_error(source.into()))?,
+        );
+        let entries = format.parse(&export).map_err(to_error)?;
+        let options = &args.options;
+        let mut report = ImportReport {
+            dry_run: options.dry_run,
+            ..ImportReport::default()
+        };
+        let mut taken = self.store.list()?;
+        for entry in entries {
+            let target = match claim_name(
+                &options.prefixed(&entry.name),
+                options,
+                &mut taken,
+                &mut report,
+            ) {
+                Some(target) => target,
+                None => continue,
+            };
+            if !options.dry_run {
+                if let Err(e) = self.save(&target, entry.to_plaintext().as_bytes()) {
+                    report.fail(target, e);
+                    continue;
+                }
+            }
+            report.imported.push(target);
         }
+        Ok(HandlerResult::Import(report))
     }
 
-    struct FakeSecretReader<'a> {
-        secret: RefCell<&'a [u8]>,
+    /// Write every entry and `.gpg-id` file to a single archive, with a
+    /// manifest of their checksums, encrypted to `key_id`. Entries stay
+    /// encrypted to their own keys inside the archive.
+    #[cfg(feature = "gpg")]
+    pub fn backup_create(&self, output_path: &str, key_id: &str) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let gpg = self.openpgp("Backups")?;
+        gpg.does_key_exist(key_id)?;
+        let mut files = Vec::new();
+        for folder in std::iter::once(String::new()).chain(self.store.key_id_folders()?) {
+            let path = |file_name: &str| match folder.as_str() {
+                "" => file_name.to_owned(),
+                folder => format!("{folder}/{file_name}"),
+            };
+            if let Some(contents) = self.store.key_id_file(&folder)? {
+                files.push(BackupFile {
+                    path: path(GPG_ID_LIST_FILE),
+                    contents,
+                });
+            }
+            if let Some(contents) = self.store.key_id_signature(&folder)? {
+                files.push(BackupFile {
+                    path: path(GPG_ID_SIGNATURE_FILE),
+                    contents,
+                });
+            }
+        }
+        let names = self.store.list()?;
+        for name in &names {
+            let contents = self.store.get(name).map_err(|source| Error::Retrieve {
+                name: name.clone(),
+                source,
+            })?;
+            let extension = if armor::is_armored(&contents) {
+                ARMORED_ENTRY_EXTENSION
+            } else {
+                ".gpg"
+            };
+            files.push(BackupFile {
+                path: format!("{name}{extension}"),
+                contents,
+            });
+        }
+        let archive = backup::pack(&files).map_err(|source| Error::Backup {
+            path: output_path.to_owned(),
+            source,
+        })?;
+        let ciphertext = gpg.encrypt(&[key_id], &archive)?;
+        fs::write_private(output_path, &ciphertext).map_err(|source| Error::Write {
+            path: output_path.to_owned(),
+            source,
+        })?;
+        Ok(HandlerResult::BackupCreate {
+            path: output_path.to_owned(),
+            entries: names.len(),
+        })
     }
 
-    impl<'a> SecretReader for FakeSecretReader<'a> {
-        fn read_secret(&self) -> anyhow::Result<ZeroizedByteVec> {
-            Ok(ZeroizedByteVec::new(
-                rpassword::read_password_from_bufread(&mut self.secret.take())
-                    .unwrap()
-                    .into_bytes(),
-            ))
+    /// Restore the entries and `.gpg-id` files in the backup at `path`. The
+    /// manifest is verified, and existing entries checked for unless they
+    /// are to be overwritten, before anything is written to the store. A
+    /// `.gpg-id` file listing anything but fingerprints is rewritten with
+    /// the fingerprints of its keys, as any other `.gpg-id` file written is.
+    ///
+    /// With signing keys configured, each `.gpg-id` file in the backup must
+    /// be signed by one of them, unless `trust_key_ids` is set, and so must
+    /// any `.gpg-id` file it replaces.
+    #[cfg(feature = "gpg")]
+    pub fn backup_restore(
+        &self,
+        path: &str,
+        overwrite: bool,
+        trust_key_ids: bool,
+    ) -> Result<HandlerResult, Error> {
+        let to_error = |source| Error::Backup {
+            path: path.to_owned(),
+            source,
+        };
+        let gpg = self.openpgp("Backups")?;
+        let ciphertext = std::fs::read(path).map_err(|source| to_error(source.into()))?;
+        let archive = gpg.decrypt_bytes(&ciphertext)?;
+        let files = backup::unpack(&archive).map_err(to_error)?;
+        let entries = files
+            .iter()
+            .filter_map(|file| {
+                let name = file
+                    .path
+                    .strip_suffix(".gpg")
+                    .or_else(|| file.path.strip_suffix(ARMORED_ENTRY_EXTENSION))?;
+                Some((name, &file.contents))
+            })
+            .collect::<Vec<_>>();
+        if !overwrite {
+            let existing = match self.store.list() {
+                Ok(names) => names,
+                Err(StoreError::IoError(e)) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            };
+            let conflicts = entries
+                .iter()
+                .map(|(name, _)| *name)
+                .filter(|name| existing.iter().any(|existing| existing == name))
+                .collect::<Vec<_>>();
+            if !conflicts.is_empty() {
+                return Err(anyhow!(
+                    "The store already has entries in the backup, restore with `--overwrite` to replace them: {}",
+                    conflicts.join(", ")
+                )
+                .into());
+            }
+        }
+        let key_id_files = files
+            .iter()
+            .filter_map(|file| {
+                let folder = file.path.strip_suffix(GPG_ID_LIST_FILE)?;
+                let signature = files
+                    .iter()
+                    .find(|signature| signature.path == format!("{}.sig", file.path))
+                    .map(|signature| signature.contents.as_slice());
+                Some((folder.trim_end_matches('/'), &file.contents, signature))
+            })
+            .collect::<Vec<_>>();
+        let mut resolved = Vec::with_capacity(key_id_files.len());
+        for (folder, contents, signature) in &key_id_files {
+            self.verify_key_id_file(&self.store, folder)?;
+            if !trust_key_ids {
+                self.verify_key_id_signature(folder, contents, *signature)?;
+            }
+            let key_ids = store::parse_key_ids(&String::from_utf8_lossy(contents));
+            if key_ids.iter().all(|key_id| is_fingerprint(key_id)) {
+                resolved.push(None);
+            } else {
+                resolved.push(Some(self.key_id_file_contents(&key_ids)?));
+            }
+        }
+        for ((folder, contents, signature), resolved) in key_id_files.iter().zip(resolved) {
+            match resolved {
+                Some(contents) => {
+                    self.store.set_key_id_file(folder, contents.as_bytes())?;
+                    self.sign_key_id_file(folder)?;
+                }
+                None => self.restore_key_id_file(folder, contents, *signature)?,
+            }
+        }
+        for (name, ciphertext) in &entries {
+            self.write_out_value(name, ciphertext)?;
+        }
+        Ok(HandlerResult::BackupRestore(entries.len()))
+    }
+
+    /// Encrypt `plaintext` to every key in the `.gpg-id` file nearest to the
+    /// entry, as they are written there, and save it as the entry with the
+    /// value of `name`.
+    pub fn save(&self, name: &str, plaintext: &[u8]) -> Result<(), Error> {
+        store::validate_name(name)?;
+        let key_ids = self.recipients(&self.store, name)?;
+        let ciphertext = self.backend.encrypt(&key_ids, plaintext)?;
+        self.write_out_value(name, &ciphertext)
+    }
+
+    /// Generate a random password of the given length and save it as the
+    /// entry with the value of `name`.
+    pub fn generate(&self, name: &str, length: usize) -> Result<ZeroizedString, Error> {
+        let password = generate::password(length);
+        self.save(name, password.as_bytes())?;
+        Ok(password)
+    }
+
+    /// Remove the entry with the value of `name` from the store.
+    pub fn delete(&self, name: &str) -> Result<(), Error> {
+        Ok(self.store.remove(name)?)
+    }
+
+    /// The names of all entries in the store.
+    pub fn entry_names(&self) -> Result<Vec<String>, Error> {
+        Ok(self.store.list()?)
+    }
+
+    /// Browse the store in a full-screen terminal interface.
+    pub fn browse(&self) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        browser::browse(self)?;
+        Ok(HandlerResult::Browse())
+    }
+
+    /// Pick the entry to retrieve with an interactive fuzzy finder over all
+    /// entry names, only available when attached to a terminal.
+    pub fn retrieve_interactively(&self) -> Result<HandlerResult, Error> {
+        if !io::stdin().is_tty() || !io::stderr().is_tty() {
+            return Err(anyhow!(
+                "No entry name was given, and no terminal is attached to pick one."
+            )
+            .into());
+        }
+        self.ensure_initialized()?;
+        match picker::pick(self.store.list()?)? {
+            Some(name) => self.retrieve(&name),
+            None => Err(anyhow!("No entry was selected.").into()),
         }
     }
 
-    #[derive(Default)]
-    struct FakeFsOps {
-        home: String,
+    /// List the names of all entries in the store, along with those
+    /// encrypted with a passphrase alone.
+    pub fn list(&self) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let names = self.entry_names()?;
+        let mut symmetric = Vec::new();
+        for name in &names {
+            if self.is_symmetric_only_entry(name)? {
+                symmetric.push(name.clone());
+            }
+        }
+        Ok(HandlerResult::List { names, symmetric })
+    }
+
+    /// Find the entries whose names match any of the `patterns`, nothing is
+    /// decrypted.
+    pub fn find(&self, patterns: &[String]) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let patterns = patterns
+            .iter()
+            .map(|pattern| NamePattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let names = self.store.list()?;
+        let found = find(&patterns, &names).into_iter().cloned().collect();
+        Ok(HandlerResult::Find(found))
+    }
+
+    /// Search the decrypted contents of every entry in the store for `pattern`.
+    ///
+    /// Entries are read from the store up front and decrypted in parallel,
+    /// plaintext only ever lives in memory. An entry which cannot be read or
+    /// decrypted is reported as failed and the rest are still searched.
+    /// Entries encrypted with a passphrase alone are all decrypted with the
+    /// one passphrase, read once. Entries which are not text are skipped.
+    pub fn grep(
+        &self,
+        pattern: &str,
+        is_regex: bool,
+        ignore_case: bool,
+    ) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let matcher = Matcher::new(pattern, is_regex, ignore_case)?;
+        let mut failed = Vec::new();
+        let entries = self
+            .store
+            .list()?
+            .into_iter()
+            .filter_map(|name| match self.store.get(&name) {
+                Ok(ciphertext) => {
+                    let symmetric = self.is_symmetric_only(&ciphertext);
+                    Some((name, ciphertext, symmetric))
+                }
+                Err(err) => {
+                    failed.push(GrepFailure {
+                        name,
+                        reason: err.to_string(),
+                    });
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let passphrase = match entries.iter().find(|(_, _, symmetric)| *symmetric) {
+            Some((_, ciphertext, _)) => self.entry_passphrase(ciphertext)?,
+            None => None,
+        };
+        let backend = &self.backend;
+        let results = entries
+            .par_iter()
+            .map(|(name, ciphertext, symmetric)| {
+                let passphrase = passphrase.as_ref().filter(|_| *symmetric);
+                decrypt_value(backend, ciphertext, passphrase)
+                    .map(|plaintext| {
+                        // Binary entries, such as those inserted from files,
+                        // have no lines to search.
+                        let plaintext = plaintext.into_zeroized_string().ok()?;
+                        matcher.find(name, &plaintext)
+                    })
+                    .map_err(|err| GrepFailure {
+                        name: name.clone(),
+                        reason: err.to_string(),
+                    })
+            })
+            .collect::<Vec<_>>();
+        let mut matches = Vec::new();
+        for result in results {
+            match result {
+                Ok(found) => matches.extend(found),
+                Err(failure) => failed.push(failure),
+            }
+        }
+        failed.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(HandlerResult::Grep { matches, failed })
+    }
+
+    /// Report who each entry, optionally only those under `folder`, is
+    /// encrypted to, compared with its `.gpg-id` file, along with the status
+    /// of each key. Recipients are read from each message without
+    /// decrypting it. Entries encrypted with a passphrase alone are skipped.
+    #[cfg(feature = "gpg")]
+    pub fn audit_recipients(
+        &self,
+        folder: Option<&str>,
+        json: bool,
+    ) -> Result<HandlerResult, Error> {
+        let gpg = self.openpgp("Auditing recipients")?;
+        self.store.key_ids()?;
+        let prefix = folder.map(|folder| format!("{}/", folder.trim_matches('/')));
+        let names = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|name| match &prefix {
+                Some(prefix) => name.starts_with(prefix),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if let (Some(folder), true) = (folder, names.is_empty()) {
+            return Err(anyhow!("The folder `{folder}` contains no entries.").into());
+        }
+        let mut entries = Vec::with_capacity(names.len());
+        for name in names {
+            entries.extend(self.audit_entry(gpg, name)?);
+        }
+        Ok(HandlerResult::AuditRecipients { entries, json })
+    }
+
+    /// The keys in the keyring entries can be encrypted to.
+    #[cfg(feature = "gpg")]
+    pub fn keys_list(&self) -> Result<HandlerResult, Error> {
+        let gpg = self.openpgp("Managing keys")?;
+        Ok(HandlerResult::KeysList(gpg.encryption_keys()?))
+    }
+
+    /// Import the keys in the file at `path` into the keyring.
+    #[cfg(feature = "gpg")]
+    pub fn keys_import(&self, path: &str) -> Result<HandlerResult, Error> {
+        let gpg = self.openpgp("Managing keys")?;
+        let data = std::fs::read(path).with_context(|| format!("Failed to read `{path}`"))?;
+        let fingerprints = gpg.import_keys(&data)?;
+        if fingerprints.is_empty() {
+            return Err(anyhow!("No keys were found in `{path}`.").into());
+        }
+        Ok(HandlerResult::KeysImport(fingerprints))
+    }
+
+    /// The public key identified by `key_id`, ASCII armored.
+    #[cfg(feature = "gpg")]
+    pub fn keys_export(&self, key_id: &str) -> Result<HandlerResult, Error> {
+        let gpg = self.openpgp("Managing keys")?;
+        Ok(HandlerResult::KeysExport(gpg.export_key(key_id)?))
+    }
+
+    /// Replace the key `old` with `new` in every `.gpg-id` file listing it,
+    /// then re-encrypt the entries those files apply to. Every affected
+    /// `.gpg-id` file is verified, and every affected entry decrypted, before
+    /// anything is changed.
+    pub fn keys_rotate(&self, old: &str, new: &str) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        let new = new.to_owned();
+        let old_fingerprint = self.backend.fingerprint(old);
+        let is_old = |key_id: &str| {
+            key_id == old
+                || old_fingerprint.as_ref().is_some_and(|fingerprint| {
+                    self.backend.fingerprint(key_id).as_ref() == Some(fingerprint)
+                })
+        };
+        let mut folders = Vec::new();
+        for folder in Some(String::new())
+            .into_iter()
+            .chain(self.store.key_id_folders()?)
+        {
+            let key_ids = match folder.as_str() {
+                "" => self.store.key_ids()?,
+                folder => self.store.folder_key_ids(folder)?.unwrap_or_default(),
+            };
+            if key_ids.iter().any(|key_id| is_old(key_id)) {
+                self.verify_key_id_file(&self.store, &folder)?;
+                let rotated = key_ids
+                    .iter()
+                    .map(|key_id| if is_old(key_id) { &new } else { key_id })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let contents = self.key_id_file_contents(&rotated)?;
+                folders.push((folder, contents));
+            }
+        }
+        if folders.is_empty() {
+            return Err(anyhow!("No .gpg-id file lists the key `{old}`.").into());
+        }
+        let mut entries = Vec::new();
+        for name in self.store.list()? {
+            let folder = self.store.key_id_folder(&name)?;
+            if folders.iter().any(|(rotated, _)| *rotated == folder) {
+                let ciphertext = self.store.get(&name)?;
+                // Entries encrypted with a passphrase alone are left as they
+                // are, as no key can read them.
+                if !self.is_symmetric_only(&ciphertext) {
+                    entries.push((name, self.backend.decrypt(&ciphertext)?));
+                }
+            }
+        }
+        for (folder, contents) in &folders {
+            self.set_key_id_file(folder, contents)?;
+        }
+        for (name, plaintext) in &entries {
+            self.save(name, plaintext)?;
+        }
+        Ok(HandlerResult::KeysRotate {
+            folders: folders.len(),
+            entries: entries.len(),
+        })
+    }
+
+    /// Rewrite every entry's message as `encoding`, without decrypting
+    /// anything, and set the store's `armor` setting to match so entries are
+    /// encrypted that way from then on.
+    #[cfg(feature = "gpg")]
+    pub fn convert(&self, encoding: Encoding) -> Result<HandlerResult, Error> {
+        self.ensure_initialized()?;
+        self.openpgp("Converting entries")?;
+        let store_path = self
+            .store
+            .root()
+            .ok_or_else(|| anyhow!("Converting entries is only supported for stores on disk."))?;
+        let mut entries = 0;
+        for name in self.store.list()? {
+            let message = self.store.get(&name)?;
+            let encoded = encoding.encode(&message).map_err(|source| Error::Convert {
+                name: name.clone(),
+                source,
+            })?;
+            if let Some(encoded) = encoded {
+                self.store.insert(&name, &encoded)?;
+                entries += 1;
+            }
+        }
+        let armor = (encoding == Encoding::Armored).to_string();
+        config::save_setting(store_path, "armor", &armor).map_err(|source| Error::Config {
+            path: format!("{store_path}/{CONFIG_FILE}"),
+            source,
+        })?;
+        Ok(HandlerResult::Convert { encoding, entries })
+    }
+
+    /// The audit of the entry `name`, or `None` if it is encrypted with a
+    /// passphrase alone, so has no recipients to compare.
+    #[cfg(feature = "gpg")]
+    fn audit_entry(&self, gpg: &Gpg, name: String) -> Result<Option<EntryAudit>, Error> {
+        let mut audit = EntryAudit {
+            expected: self.store.recipients(&name)?,
+            ..EntryAudit::default()
+        };
+        let ciphertext = self.store.get(&name)?;
+        audit.name = name;
+        let message = match message::inspect_entry(&ciphertext) {
+            Ok(message) if message.is_symmetric_only() => return Ok(None),
+            Ok(message) => message,
+            Err(e) => {
+                audit.error = Some(e.to_string());
+                return Ok(Some(audit));
+            }
+        };
+        audit.recipients = message
+            .recipients
+            .iter()
+            .map(|key_id| {
+                let key = gpg.key_info(key_id).ok();
+                Recipient {
+                    key_id: key_id.clone(),
+                    user_id: key.as_ref().and_then(|key| key.user_id.clone()),
+                    status: KeyStatus::of(key.as_ref()),
+                }
+            })
+            .collect();
+        (audit.missing, audit.unexpected) =
+            compare_recipients(gpg, &audit.expected, &message.recipients);
+        Ok(Some(audit))
+    }
+
+    /// Check the store on disk for corrupt entries, entries not encrypted
+    /// to the keys in their `.gpg-id` file, stray files, loose permissions
+    /// and empty folders, repairing what can be if asked to. Recipients are
+    /// read from each message without decrypting it, only entries being
+    /// re-encrypted are decrypted. Entries encrypted with a passphrase alone
+    /// are not meant to be encrypted to any key, so are not checked.
+    #[cfg(feature = "gpg")]
+    pub fn fsck(&self, repair: bool) -> Result<HandlerResult, Error> {
+        let gpg = self.openpgp("Checking the store")?;
+        let store_path = self
+            .store
+            .root()
+            .ok_or_else(|| anyhow!("Checking the store is only supported for stores on disk."))?;
+        self.store.key_ids()?;
+        let fsck::Scan {
+            mut issues,
+            entries,
+        } = fsck::scan(Path::new(store_path)).map_err(StoreError::from)?;
+        for fsck::Entry {
+            name,
+            path,
+            message,
+        } in entries
+        {
+            if message.is_symmetric_only() {
+                continue;
+            }
+            let expected = self.store.recipients(&name)?;
+            let (missing, unexpected) = compare_recipients(gpg, &expected, &message.recipients);
+            if !missing.is_empty() || !unexpected.is_empty() {
+                let problem = Problem::Recipients {
+                    missing,
+                    unexpected,
+                };
+                issues.push(Issue::new(path, problem));
+            }
+        }
+        if repair {
+            for issue in issues
+                .iter_mut()
+                .filter(|issue| issue.problem.is_repairable())
+            {
+                let repaired = match issue.problem {
+                    Problem::Recipients { .. } => {
+                        let name = fsck::entry_name(&issue.path).unwrap_or_default();
+                        self.reencrypt(name).map_err(|e| e.to_string())
+                    }
+                    _ => fsck::repair(Path::new(store_path), issue).map_err(|e| e.to_string()),
+                };
+                issue.status = match repaired {
+                    Ok(()) => Status::Repaired,
+                    Err(reason) => Status::RepairFailed(reason),
+                };
+            }
+        }
+        Ok(HandlerResult::Fsck(issues))
+    }
+
+    /// Decrypt the entry `name` and encrypt it again to the keys it should
+    /// be encrypted to.
+    #[cfg(feature = "gpg")]
+    fn reencrypt(&self, name: &str) -> Result<(), Error> {
+        let plaintext = self.backend.decrypt(&self.store.get(name)?)?;
+        let key_ids = self.recipients(&self.store, name)?;
+        let ciphertext = self.backend.encrypt(&key_ids, &plaintext)?;
+        Ok(self.store.insert(name, &ciphertext)?)
+    }
+
+    /// Check the `.gpg-id` file of `folder` itself, if it has one, is signed
+    /// by a trusted signing key, before it is replaced.
+    fn verify_key_id_file<T: Store>(&self, store: &T, folder: &str) -> Result<(), Error> {
+        if self.signing_keys.is_empty() {
+            return Ok(());
+        }
+        match store.key_id_file(folder)? {
+            Some(contents) => {
+                let signature = store.key_id_signature(folder)?;
+                self.verify_key_id_signature(folder, &contents, signature.as_deref())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check `signature` is a valid signature of `contents`, the `.gpg-id`
+    /// file of `folder`, by a trusted signing key. Nothing is checked unless
+    /// signing keys are configured.
+    #[cfg(feature = "gpg")]
+    fn verify_key_id_signature(
+        &self,
+        folder: &str,
+        contents: &[u8],
+        signature: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        if self.signing_keys.is_empty() {
+            return Ok(());
+        }
+        let to_error = |source| Error::KeyIdSignature {
+            path: match folder {
+                "" => GPG_ID_LIST_FILE.to_owned(),
+                folder => format!("{folder}/{GPG_ID_LIST_FILE}"),
+            },
+            source,
+        };
+        let signature = signature.ok_or_else(|| to_error(SignatureError::Missing))?;
+        let signers = self
+            .openpgp("Signing .gpg-id files")?
+            .verify_detached(signature, contents)
+            .map_err(|e| to_error(SignatureError::Invalid(Some(e.into()))))?;
+        if signers.is_empty() {
+            return Err(to_error(SignatureError::Invalid(None)));
+        }
+        let is_trusted = signers.iter().any(|signer| {
+            self.signing_keys
+                .iter()
+                .any(|trusted| trusted.eq_ignore_ascii_case(signer))
+        });
+        if !is_trusted {
+            return Err(to_error(SignatureError::UntrustedSigner(signers)));
+        }
+        Ok(())
+    }
+
+    /// Write the `.gpg-id` file of `folder` with `contents`, from
+    /// [`Handler::key_id_file_contents`], signing it when signing keys are
+    /// configured. A `.gpg-id` file already there must be signed by a
+    /// trusted signing key, so a tampered one is never signed over.
+    fn set_key_id_file(&self, folder: &str, contents: &str) -> Result<(), Error> {
+        self.verify_key_id_file(&self.store, folder)?;
+        self.store.set_key_id_file(folder, contents.as_bytes())?;
+        self.sign_key_id_file(folder)
+    }
+
+    #[cfg(not(feature = "gpg"))]
+    fn verify_key_id_signature(
+        &self,
+        _folder: &str,
+        _contents: &[u8],
+        _signature: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        self.require_no_signing_keys()
+    }
+
+    /// Sign the `.gpg-id` file of `folder` with the signing keys, if any are
+    /// set.
+    #[cfg(feature = "gpg")]
+    fn sign_key_id_file(&self, folder: &str) -> Result<(), Error> {
+        if !self.signing_keys.is_empty() {
+            let contents = self.store.key_id_file(folder)?.unwrap_or_default();
+            let signature = self
+                .openpgp("Signing .gpg-id files")?
+                .sign_detached(&self.signing_keys, &contents)?;
+            self.store.set_key_id_signature(folder, &signature)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "gpg"))]
+    fn sign_key_id_file(&self, _folder: &str) -> Result<(), Error> {
+        self.require_no_signing_keys()
+    }
+
+    /// Without the `gpg` feature `.gpg-id` files can be neither signed nor
+    /// verified, so signing keys must not be configured.
+    #[cfg(not(feature = "gpg"))]
+    fn require_no_signing_keys(&self) -> Result<(), Error> {
+        if self.signing_keys.is_empty() {
+            Ok(())
+        } else {
+            Err(unsupported("Signing .gpg-id files"))
+        }
+    }
+
+    /// Write the `.gpg-id` file of `folder` as it was backed up. Its
+    /// signature is kept if it is still valid, otherwise it is signed as any
+    /// other `.gpg-id` file written is.
+    #[cfg(feature = "gpg")]
+    fn restore_key_id_file(
+        &self,
+        folder: &str,
+        contents: &[u8],
+        signature: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        let signature = signature.filter(|signature| {
+            self.verify_key_id_signature(folder, contents, Some(signature))
+                .is_ok()
+        });
+        self.store.set_key_id_file(folder, contents)?;
+        match signature {
+            Some(signature) => self.store.set_key_id_signature(folder, signature)?,
+            None => self.sign_key_id_file(folder)?,
+        }
+        Ok(())
+    }
+
+    /// Resolve `recipient` to what is encrypted to, such as the fingerprint
+    /// of a key, warning when it is ambiguous.
+    fn resolve_recipient(&self, recipient: &str) -> Result<String, Error> {
+        let resolved = self.backend.resolve_recipient(recipient)?;
+        self.warn_if_expiring(&resolved.recipient);
+        if !resolved.alternatives.is_empty() {
+            self.warn(format!(
+                "`{recipient}` matches more than one key, {} was used rather than {}. \
+                 Give a fingerprint to choose another.",
+                resolved.recipient,
+                resolved.alternatives.join(", ")
+            ));
+        }
+        Ok(resolved.recipient)
+    }
+
+    /// Warn when `recipient` expires within the expiry warning window, as
+    /// nothing can be encrypted to it once it does.
+    fn warn_if_expiring(&self, recipient: &str) {
+        let remaining = match self.backend.expires(recipient) {
+            Some(expires) => match expires.duration_since(SystemTime::now()) {
+                Ok(remaining) => remaining,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        if remaining > self.expiry_warning {
+            return;
+        }
+        let when = match remaining.as_secs() / (24 * 60 * 60) {
+            0 => "within a day".to_owned(),
+            1 => "in 1 day".to_owned(),
+            days => format!("in {days} days"),
+        };
+        self.warn(format!(
+            "The key {recipient} expires {when}. Extend it, or replace it with \
+             `sec keys rotate {recipient} <new key>`."
+        ));
+    }
+
+    /// The keys the entry `name` in `store` is encrypted to, as listed in its
+    /// `.gpg-id` file, warning of any about to expire. They are not looked
+    /// up again, as `.gpg-id` files are written with fingerprints.
+    ///
+    /// The file is read once, and its signature checked against the very
+    /// contents the keys are taken from, so keys added to it by anyone but
+    /// a trusted signing key are never encrypted to.
+    fn recipients<T: Store>(&self, store: &T, name: &str) -> Result<Vec<String>, Error> {
+        let folder = store.key_id_folder(name)?;
+        let contents = store.key_id_file(&folder)?;
+        let signature = store.key_id_signature(&folder)?;
+        self.verify_key_id_signature(
+            &folder,
+            contents.as_deref().unwrap_or_default(),
+            signature.as_deref(),
+        )?;
+        let key_ids = contents
+            .map(|contents| store::parse_key_ids(&String::from_utf8_lossy(&contents)))
+            .unwrap_or_default();
+        if folder.is_empty() && key_ids.is_empty() {
+            // Say why the store's own `.gpg-id` file is unusable.
+            store.key_ids()?;
+            return Err(StoreError::NotInitialized(format!(
+                "its {} file lists no keys",
+                C::RECIPIENTS_FILE
+            ))
+            .into());
+        }
+        for key_id in &key_ids {
+            self.warn_if_expiring(key_id);
+        }
+        Ok(key_ids)
+    }
+
+    /// The keys the entry `name` is encrypted to, as [`Handler::recipients`],
+    /// which must include `key_id`, the key it was asked to be encrypted
+    /// to, as given or by fingerprint. An entry is never encrypted to keys
+    /// other than those its `.gpg-id` file lists.
+    fn recipients_including(&self, name: &str, key_id: &str) -> Result<Vec<String>, Error> {
+        let recipients = self.recipients(&self.store, name)?;
+        let fingerprint = self.backend.fingerprint(key_id);
+        let is_listed = recipients.iter().any(|recipient| {
+            recipient == key_id
+                || fingerprint.is_some() && self.backend.fingerprint(recipient) == fingerprint
+        });
+        if !is_listed {
+            return Err(anyhow!(
+                "`{key_id}` is not one of the keys in the {} file which applies to `{name}`: {}",
+                C::RECIPIENTS_FILE,
+                recipients.join(", ")
+            )
+            .into());
+        }
+        Ok(recipients)
+    }
+
+    /// The contents of a `.gpg-id` file listing `key_ids`, each resolved to
+    /// the fingerprint of its key as [`Handler::initialize`] writes them. A
+    /// fingerprint is commented with what it was given as, or otherwise the
+    /// user id of its key, and listed only once.
+    fn key_id_file_contents(&self, key_ids: &[String]) -> Result<String, Error> {
+        let mut fingerprints = Vec::with_capacity(key_ids.len());
+        let mut contents = String::new();
+        for key_id in key_ids {
+            let fingerprint = self.resolve_recipient(key_id)?;
+            if fingerprints.contains(&fingerprint) {
+                continue;
+            }
+            let comment = if fingerprint == *key_id {
+                self.backend.user_id(&fingerprint)
+            } else {
+                Some(key_id.clone())
+            };
+            if let Some(comment) = comment {
+                contents.push_str(&format!("# {comment}\n"));
+            }
+            contents.push_str(&fingerprint);
+            contents.push('\n');
+            fingerprints.push(fingerprint);
+        }
+        Ok(contents)
+    }
+
+    /// Whether entries are OpenPGP messages which gpg can read.
+    fn is_openpgp(&self) -> bool {
+        #[cfg(feature = "gpg")]
+        let is_openpgp = self.backend.openpgp().is_some();
+        #[cfg(not(feature = "gpg"))]
+        let is_openpgp = false;
+        is_openpgp
+    }
+
+    /// The OpenPGP backend, for `feature` which only it supports.
+    #[cfg(feature = "gpg")]
+    fn openpgp(&self, feature: &str) -> Result<&Gpg, Error> {
+        self.backend
+            .openpgp()
+            .ok_or_else(|| anyhow!("{feature} is only supported in OpenPGP stores.").into())
+    }
+
+    fn store_path(&self) -> Result<String, Error> {
+        let home_dir = self
+            .fs_ops
+            .home_dir()
+            .ok_or(InitError::HomeDirectoryNotFound)?
+            .into_os_string()
+            .into_string()
+            .map_err(|_| InitError::NonUtf8Path)?;
+        Ok(format!("{home_dir}/{}", PASSWORD_STORE_DIRECTORY))
+    }
+
+    /// Check the store has been initialized with recipients which exist.
+    /// Recipients which can no longer be encrypted to, such as expired keys,
+    /// are only reported once something is encrypted to them, so existing
+    /// entries can still be read.
+    fn ensure_initialized(&self) -> Result<(), Error> {
+        for key_id in self.store.key_ids()? {
+            match self.backend.validate_recipient(&key_id) {
+                Ok(()) | Err(CryptoError::UnusableRecipient { .. }) => {}
+                Err(e) => return Err(StoreError::NotInitialized(e.to_string()).into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the store, encrypted to `key_id`. It is resolved to the
+    /// fingerprint of the key when it is a user id or short key id, which
+    /// is written to the `.gpg-id` file below a comment of what was given.
+    pub fn initialize(&self, key_id: &str) -> Result<HandlerResult, Error> {
+        let contents = self.key_id_file_contents(&[key_id.to_owned()])?;
+        #[cfg(feature = "gpg")]
+        let signer = if self.signing_keys.is_empty() {
+            None
+        } else {
+            Some(self.openpgp("Signing .gpg-id files")?)
+        };
+        #[cfg(not(feature = "gpg"))]
+        self.require_no_signing_keys()?;
+        let store_path = self.store_path()?;
+        let create_store = || -> anyhow::Result<()> {
+            self.fs_ops.mkdir(&store_path)?;
+            let mut key_list = self
+                .fs_ops
+                .touch(format!("{store_path}/{}", C::RECIPIENTS_FILE))?;
+            key_list.write_all(contents.as_bytes())?;
+            #[cfg(feature = "gpg")]
+            if let Some(gpg) = signer {
+                let signature = gpg.sign_detached(&self.signing_keys, contents.as_bytes())?;
+                self.fs_ops
+                    .touch(format!("{store_path}/{GPG_ID_SIGNATURE_FILE}"))?
+                    .write_all(&signature)?;
+            }
+            Ok(())
+        };
+        create_store().map_err(|source| InitError::CreateStore {
+            path: store_path.clone(),
+            source,
+        })?;
+        Ok(HandlerResult::Initialize())
+    }
+}
+
+/// Compare the recipients of a message to the keys it should be encrypted
+/// to, see [`audit::compare`]. Keys missing from the keyring have no
+/// subkeys, so are always missing.
+#[cfg(feature = "gpg")]
+fn compare_recipients(
+    gpg: &Gpg,
+    expected: &[String],
+    recipients: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let expected = expected
+        .iter()
+        .map(|key_id| (key_id, gpg.subkey_ids(key_id).unwrap_or_default()))
+        .collect::<Vec<_>>();
+    audit::compare(&expected, recipients)
+}
+
+/// Decrypt `ciphertext`, the value of an entry, with `passphrase` if it is
+/// encrypted with one alone and otherwise with `backend`.
+#[cfg(feature = "gpg")]
+fn decrypt_value<C: CryptoBackend>(
+    backend: &C,
+    ciphertext: &[u8],
+    passphrase: Option<&ZeroizedString>,
+) -> Result<ZeroizedByteVec, CryptoError> {
+    match (backend.openpgp(), passphrase) {
+        (Some(gpg), Some(passphrase)) => Ok(gpg.decrypt_symmetric(ciphertext, passphrase)?),
+        _ => backend.decrypt(ciphertext),
+    }
+}
+
+#[cfg(not(feature = "gpg"))]
+fn decrypt_value<C: CryptoBackend>(
+    backend: &C,
+    ciphertext: &[u8],
+    _passphrase: Option<&ZeroizedString>,
+) -> Result<ZeroizedByteVec, CryptoError> {
+    backend.decrypt(ciphertext)
+}
+
+/// Whether `key_id` is the fingerprint of an OpenPGP key, which is written to
+/// `.gpg-id` files as is.
+#[cfg(feature = "gpg")]
+fn is_fingerprint(key_id: &str) -> bool {
+    matches!(key_id.len(), 40 | 64) && key_id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The error for `feature`, which needs gpg, without the `gpg` feature.
+#[cfg(not(feature = "gpg"))]
+fn unsupported(feature: &str) -> Error {
+    anyhow!("{feature} needs sec to be built with the `gpg` feature.").into()
+}
+
+/// Pick the name to import `name` as following the conflict strategy in
+/// `options`, recording skipped and renamed entries in `report`. The name is
+/// then taken, so later entries of the same name conflict with it.
+fn claim_name(
+    name: &str,
+    options: &ImportOptions,
+    taken: &mut Vec<String>,
+    report: &mut ImportReport,
+) -> Option<String> {
+    let target = match options.on_conflict.resolve(name, taken) {
+        Some(target) => target,
+        None => {
+            report.skipped.push(name.to_owned());
+            return None;
+        }
+    };
+    if target != name {
+        report.renamed.push((name.to_owned(), target.clone()));
+    }
+    if !taken.contains(&target) {
+        taken.push(target.clone());
+    }
+    Some(target)
+}
+
+fn read_template(path: &str) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|source| Error::Template {
+        path: path.to_owned(),
+        source: source.into(),
+    })
+}
+
+fn parse_template<'a>(path: &str, source: &'a str) -> Result<Vec<Segment<'a>>, Error> {
+    template::parse(source).map_err(|source| Error::Template {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// The exit code of a finished child, following the shell convention of
+/// `128 + signal` for a child killed by a signal.
+fn exit_code(status: ExitStatus) -> u8 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return (128 + signal) as u8;
+        }
+    }
+    status.code().unwrap_or(1) as u8
+}
+
+#[cfg(all(test, feature = "gpg"))]
+mod test {
+    use super::{HandlerResult, GPG_ID_LIST_FILE, PASSWORD_STORE_DIRECTORY};
+    use crate::{
+        armor::{self, Encoding},
+        backup,
+        cli::{Action, EnvAction, ExportArgs, GlobalOptions, ImportOptions, ImportSource},
+        config::StoreConfig,
+        crypto::{CryptoBackend, CryptoError, ResolvedRecipient},
+        error::{Error, SignatureError},
+        fs::FileSystemOperator,
+        fsck::Status,
+        gpg::{
+            test::{import_keys, GPG_KEY_ID},
+            Gpg,
+        },
+        import::{ConflictStrategy, Format, ImportReport},
+        input::handle,
+        secrets::{SecretReader, ZeroizedByteVec},
+        store::{self, OnDiskStore, Store, StoreError},
+        Args, Handler,
+    };
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        fs::File,
+        io::{Cursor, Write},
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        rc::Rc,
+        str::FromStr,
+        time::{Duration, SystemTime},
+    };
+    use tempfile::tempdir;
+
+    const EXISTING_GPG_KEY: &str = "passrs-tests@nocht.io";
+
+    #[derive(Clone)]
+    struct InMemoryStore {
+        store: Rc<RefCell<HashMap<String, Vec<u8>>>>,
+        /// The contents of each `.gpg-id` file, by folder.
+        key_id_files: Rc<RefCell<HashMap<String, Vec<u8>>>>,
+        signatures: Rc<RefCell<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl InMemoryStore {
+        fn new() -> Self {
+            Self {
+                store: Rc::new(RefCell::new(HashMap::new())),
+                key_id_files: Rc::new(RefCell::new(HashMap::new())),
+                signatures: Rc::new(RefCell::new(HashMap::new())),
+            }
+        }
+    }
+
+    /// A new value of an entry in an [`InMemoryStore`], saved once
+    /// committed.
+    struct BufferedEntry {
+        name: String,
+        value: Vec<u8>,
+    }
+
+    impl Write for BufferedEntry {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.value.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Store for InMemoryStore {
+        type Reader = Cursor<Vec<u8>>;
+        type Writer = BufferedEntry;
+
+        fn insert<S: AsRef<str>>(&self, name: S, value: &[u8]) -> Result<(), StoreError> {
+            self.store
+                .clone()
+                .borrow_mut()
+                .insert(name.as_ref().to_owned(), value.to_vec());
+            Ok(())
+        }
+
+        fn get<S: AsRef<str>>(&self, name: S) -> Result<Vec<u8>, StoreError> {
+            if let Some(value) = self.store.clone().borrow().get(name.as_ref()) {
+                Ok(value.clone())
+            } else {
+                Err(StoreError::EntryDoesNotExist(name.as_ref().to_owned()))
+            }
+        }
+
+        fn reader<S: AsRef<str>>(&self, name: S) -> Result<Self::Reader, StoreError> {
+            Ok(Cursor::new(self.get(name)?))
+        }
+
+        fn writer<S: AsRef<str>>(&self, name: S) -> Result<Self::Writer, StoreError> {
+            Ok(BufferedEntry {
+                name: name.as_ref().to_owned(),
+                value: Vec::new(),
+            })
+        }
+
+        fn commit(&self, writer: Self::Writer) -> Result<(), StoreError> {
+            self.insert(writer.name, &writer.value)
+        }
+
+        fn key_ids(&self) -> Result<Vec<String>, StoreError> {
+            Ok(self
+                .folder_key_ids("")?
+                .unwrap_or_else(|| vec![GPG_KEY_ID.to_owned()]))
+        }
+
+        fn folder_key_ids<S: AsRef<str>>(
+            &self,
+            folder: S,
+        ) -> Result<Option<Vec<String>>, StoreError> {
+            Ok(self
+                .key_id_files
+                .borrow()
+                .get(folder.as_ref())
+                .map(|contents| store::parse_key_ids(&String::from_utf8_lossy(contents))))
+        }
+
+        fn key_id_folders(&self) -> Result<Vec<String>, StoreError> {
+            let mut folders = self
+                .key_id_files
+                .borrow()
+                .keys()
+                .filter(|folder| !folder.is_empty())
+                .cloned()
+                .collect::<Vec<_>>();
+            folders.sort();
+            Ok(folders)
+        }
+
+        fn set_key_id_file<S: AsRef<str>>(
+            &self,
+            folder: S,
+            contents: &[u8],
+        ) -> Result<(), StoreError> {
+            self.key_id_files
+                .borrow_mut()
+                .insert(folder.as_ref().to_owned(), contents.to_vec());
+            Ok(())
+        }
+
+        fn key_id_file<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<u8>>, StoreError> {
+            let contents = self.key_id_files.borrow().get(folder.as_ref()).cloned();
+            Ok(match folder.as_ref() {
+                "" => contents.or_else(|| Some(format!("{GPG_KEY_ID}\n").into_bytes())),
+                _ => contents,
+            })
+        }
+
+        fn key_id_signature<S: AsRef<str>>(
+            &self,
+            folder: S,
+        ) -> Result<Option<Vec<u8>>, StoreError> {
+            Ok(self.signatures.borrow().get(folder.as_ref()).cloned())
+        }
+
+        fn set_key_id_signature<S: AsRef<str>>(
+            &self,
+            folder: S,
+            signature: &[u8],
+        ) -> Result<(), StoreError> {
+            self.signatures
+                .borrow_mut()
+                .insert(folder.as_ref().to_owned(), signature.to_vec());
+            Ok(())
+        }
+
+        fn list(&self) -> Result<Vec<String>, StoreError> {
+            let mut names = self.store.borrow().keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            Ok(names)
+        }
+
+        fn remove<S: AsRef<str>>(&self, name: S) -> Result<(), StoreError> {
+            self.store
+                .borrow_mut()
+                .remove(name.as_ref())
+                .map(|_| ())
+                .ok_or_else(|| StoreError::EntryDoesNotExist(name.as_ref().to_owned()))
+        }
+    }
+
+    struct IoErrorStore;
+
+    impl Store for IoErrorStore {
+        type Reader = std::io::Empty;
+        type Writer = std::io::Sink;
+
+        fn insert<S: AsRef<str>>(&self, _: S, _: &[u8]) -> Result<(), StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(())
+        }
+
+        fn get<S: AsRef<str>>(&self, _: S) -> Result<Vec<u8>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(Vec::new())
+        }
+
+        fn reader<S: AsRef<str>>(&self, _: S) -> Result<Self::Reader, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(std::io::empty())
+        }
+
+        fn writer<S: AsRef<str>>(&self, _: S) -> Result<Self::Writer, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(std::io::sink())
+        }
+
+        fn commit(&self, _: Self::Writer) -> Result<(), StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(())
+        }
+
+        fn key_ids(&self) -> Result<Vec<String>, StoreError> {
+            Ok(vec![GPG_KEY_ID.to_owned()])
+        }
+
+        fn folder_key_ids<S: AsRef<str>>(&self, _: S) -> Result<Option<Vec<String>>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(None)
+        }
+
+        fn key_id_folders(&self) -> Result<Vec<String>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(Vec::new())
+        }
+
+        fn set_key_id_file<S: AsRef<str>>(&self, _: S, _: &[u8]) -> Result<(), StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(())
+        }
+
+        fn key_id_file<S: AsRef<str>>(&self, _: S) -> Result<Option<Vec<u8>>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(None)
+        }
+
+        fn key_id_signature<S: AsRef<str>>(&self, _: S) -> Result<Option<Vec<u8>>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(None)
+        }
+
+        fn set_key_id_signature<S: AsRef<str>>(&self, _: S, _: &[u8]) -> Result<(), StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(())
+        }
+
+        fn list(&self) -> Result<Vec<String>, StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(Vec::new())
+        }
+
+        fn remove<S: AsRef<str>>(&self, _: S) -> Result<(), StoreError> {
+            let _ = File::open("b68eea40-38e3-43e8-bb61-60ec38067feb")?;
+            Ok(())
+        }
+    }
+
+    struct FakeSecretReader<'a> {
+        secret: RefCell<&'a [u8]>,
+    }
+
+    impl<'a> SecretReader for FakeSecretReader<'a> {
+        fn read_secret(&self) -> anyhow::Result<ZeroizedByteVec> {
+            Ok(ZeroizedByteVec::new(
+                rpassword::read_password_from_bufread(&mut self.secret.take())
+                    .unwrap()
+                    .into_bytes(),
+            ))
+        }
+    }
+
+    /// A backend which "encrypts" by prefixing the plaintext with a line
+    /// listing its recipients, so the handler can be tested without GPG.
+    struct FakeBackend;
+
+    impl CryptoBackend for FakeBackend {
+        const ENTRY_EXTENSION: &'static str = ".fake";
+        const RECIPIENTS_FILE: &'static str = ".fake-recipients";
+
+        fn encrypt<S: AsRef<str>>(
+            &self,
+            recipients: &[S],
+            plaintext: &[u8],
+        ) -> Result<Vec<u8>, CryptoError> {
+            let recipients = recipients.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+            let mut ciphertext = format!("{}\n", recipients.join(",")).into_bytes();
+            ciphertext.extend_from_slice(plaintext);
+            Ok(ciphertext)
+        }
+
+        fn decrypt(&self, ciphertext: &[u8]) -> Result<ZeroizedByteVec, CryptoError> {
+            let start = ciphertext
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .ok_or_else(|| CryptoError::DecryptionFailed("no recipients".into()))?;
+            Ok(ZeroizedByteVec::new(ciphertext[start + 1..].to_vec()))
+        }
+
+        fn validate_recipient(&self, recipient: &str) -> Result<(), CryptoError> {
+            if recipient.starts_with("unknown") {
+                return Err(CryptoError::KeyNotFound(recipient.to_owned()));
+            }
+            Ok(())
+        }
+
+        /// Recipients named `expiring*` expire in ten days.
+        fn expires(&self, recipient: &str) -> Option<SystemTime> {
+            recipient
+                .starts_with("expiring")
+                .then(|| SystemTime::now() + Duration::from_secs(10 * 24 * 60 * 60))
+        }
+
+        /// `team` stands for two recipients, like a user id shared by two
+        /// keys.
+        fn resolve_recipient(&self, recipient: &str) -> Result<ResolvedRecipient, CryptoError> {
+            self.validate_recipient(recipient)?;
+            Ok(match recipient {
+                "team" => ResolvedRecipient {
+                    recipient: "fake-alice".to_owned(),
+                    alternatives: vec!["fake-bob".to_owned()],
+                },
+                _ => ResolvedRecipient {
+                    recipient: recipient.to_owned(),
+                    alternatives: Vec::new(),
+                },
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeFsOps {
+        home: String,
+    }
+
+    impl FileSystemOperator for FakeFsOps {
+        fn home_dir(&self) -> Option<std::path::PathBuf> {
+            Some(PathBuf::from_str(&self.home).unwrap())
+        }
+
+        fn mkdir<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+            std::fs::create_dir(path.as_ref().to_str().unwrap()).unwrap();
+            Ok(())
+        }
+
+        fn touch<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<File> {
+            Ok(std::fs::File::create(path).unwrap())
+        }
+    }
+
+    #[test]
+    fn should_encrypt_and_store_given_value() {
+        import_keys();
+        let gpg = Gpg::new();
+        let name = "name".to_owned();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Insert {
+                name,
+                key_id: Some(GPG_KEY_ID.to_owned()),
+                symmetric: false,
+                file: None,
+            },
+        };
+        let input = "password\n";
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new(input.as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        if let HandlerResult::Insert(name) = handle(&handler, &args).expect("expected a result") {
+            let ciphertext = store.get(name).unwrap();
+            let plaintext = gpg.decrypt(&ciphertext).unwrap();
+            assert_eq!(&*plaintext, input.trim());
+        } else {
+            panic!("got unexpected handle result")
+        }
+    }
+
+    #[test]
+    fn should_retrieve_entry_decrypted() {
+        import_keys();
+        let name = "name".to_string();
+        let retrieve_args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Retrieve {
+                name: Some(name.clone()),
+                output: None,
+            },
+        };
+        let input = "password\n";
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new(input.as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        handler.insert(&name, GPG_KEY_ID).unwrap();
+        if let HandlerResult::Retrieve(value) =
+            handle(&handler, &retrieve_args).expect("expected a result")
+        {
+            assert_eq!(&*value, input.trim());
+            return;
+        }
+        panic!("got unexpected handle result");
+    }
+
+    #[test]
+    fn should_give_meaningful_error_if_entry_could_not_be_decrypted() {
+        import_keys();
+        let name = "name".to_string();
+        let retrieve_args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Retrieve {
+                name: Some(name.clone()),
+                output: None,
+            },
+        };
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        store.insert(name.clone(), b"").unwrap();
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &retrieve_args);
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            format!(r#"The entry "{name}" could not be decrypted!"#)
+        );
+    }
+
+    #[test]
+    fn insert_should_give_meaningful_error_if_store_has_an_fs_error() {
+        import_keys();
+        let name = "name".to_string();
+        let retrieve_args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Insert {
+                name: name.clone(),
+                key_id: Some(GPG_KEY_ID.to_string()),
+                symmetric: false,
+                file: None,
+            },
+        };
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("secret\n".as_bytes()),
+        };
+        let store = IoErrorStore;
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &retrieve_args);
+        assert!(result.is_err());
+        let partial_error =
+            &format!("An error occurred when attempting to insert the entry `{name}`.");
+        let err = result.err().unwrap();
+        assert!(
+            err.to_string().contains(partial_error),
+            "error incorrect, got `{err}`",
+        );
+    }
+
+    #[test]
+    fn retrieve_should_give_meaningful_error_if_store_has_an_fs_error() {
+        import_keys();
+        let name = "name".to_string();
+        let retrieve_args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Retrieve {
+                name: Some(name.clone()),
+                output: None,
+            },
+        };
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = IoErrorStore;
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &retrieve_args);
+        assert!(result.is_err());
+        let partial_error =
+            &format!("An error occurred when attempting to retrieve the entry `{name}`.");
+        let actual_error = result.err().unwrap();
+        assert!(
+            actual_error.to_string().contains(partial_error),
+            "error incorrect, got `{actual_error}`"
+        );
+    }
+
+    #[test]
+    fn insert_should_give_meaningful_error_if_store_is_not_initialized() {
+        let tmpdir = tempdir().unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Insert {
+                name: "name".to_owned(),
+                key_id: Some(GPG_KEY_ID.to_owned()),
+                symmetric: false,
+                file: None,
+            },
+        };
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("secret\n".as_bytes()),
+        };
+        let store = OnDiskStore::new(tmpdir.path().to_str().unwrap());
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &args);
+        assert!(matches!(
+            result,
+            Err(Error::Store(StoreError::NotInitialized(_)))
+        ));
+        let err = result.err().unwrap();
+        assert!(
+            err.to_string().contains("sec initialize"),
+            "error incorrect, got `{err}`"
+        );
+    }
+
+    #[test]
+    fn retrieve_should_give_meaningful_error_if_store_is_not_initialized() {
+        let tmpdir = tempdir().unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Retrieve {
+                name: Some("name".to_owned()),
+                output: None,
+            },
+        };
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = OnDiskStore::new(format!("{}/missing", tmpdir.path().to_str().unwrap()));
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &args);
+        assert!(matches!(
+            result,
+            Err(Error::Store(StoreError::NotInitialized(_)))
+        ));
+    }
+
+    #[test]
+    fn generate_should_save_encrypted_password() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        let password = handler.generate("prod/db", 16).unwrap();
+        assert_eq!(password.len(), 16);
+        let plaintext = Gpg::new().decrypt(&store.get("prod/db").unwrap()).unwrap();
+        assert!(plaintext == password);
+    }
+
+    #[test]
+    fn symmetric_entries_should_be_listed_and_retrieved_with_their_passphrase() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("hunter2".as_bytes()),
+        };
+        let passphrase = || {
+            Box::new(FakeSecretReader {
+                secret: RefCell::new("abc".as_bytes()),
+            })
+        };
+        let store = InMemoryStore::new();
+        let db = Gpg::new().encrypt(&[GPG_KEY_ID], b"db").unwrap();
+        store.insert("prod/db", &db).unwrap();
+        Handler::new(store.clone(), secret_reader, FakeFsOps::default())
+            .with_passphrase_reader(passphrase())
+            .insert_symmetric("shared/vpn")
+            .unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default())
+            .with_passphrase_reader(passphrase());
+        let result = handler.list().unwrap();
+        assert!(
+            result
+                == HandlerResult::List {
+                    names: vec!["prod/db".to_owned(), "shared/vpn".to_owned()],
+                    symmetric: vec!["shared/vpn".to_owned()],
+                }
+        );
+        let plaintext = handler.decrypt_entry("shared/vpn").unwrap();
+        assert_eq!(&*plaintext, "hunter2");
+    }
+
+    #[test]
+    fn symmetric_entries_should_be_searched_and_retrieved_but_not_audited() {
+        import_keys();
+        let store = InMemoryStore::new();
+        let db = Gpg::new().encrypt(&[GPG_KEY_ID], b"db").unwrap();
+        store.insert("prod/db", &db).unwrap();
+        let vpn = Gpg::new().encrypt_symmetric(b"hunter2", "abc").unwrap();
+        store.insert("shared/vpn", &vpn).unwrap();
+        let handler = || {
+            let secret_reader = FakeSecretReader {
+                secret: RefCell::new("".as_bytes()),
+            };
+            Handler::new(store.clone(), secret_reader, FakeFsOps::default()).with_passphrase_reader(
+                Box::new(FakeSecretReader {
+                    secret: RefCell::new("abc".as_bytes()),
+                }),
+            )
+        };
+        match handler().grep("hunter", false, false).unwrap() {
+            HandlerResult::Grep { matches, failed } => {
+                assert!(failed.is_empty());
+                assert_eq!(matches.len(), 1);
+                assert_eq!(matches[0].name, "shared/vpn");
+            }
+            _ => panic!("expected grep results"),
+        }
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("vpn");
+        let output = output.to_str().unwrap();
+        handler().retrieve_to_file("shared/vpn", output).unwrap();
+        assert_eq!(std::fs::read(output).unwrap(), b"hunter2");
+        match handler().audit_recipients(None, false).unwrap() {
+            HandlerResult::AuditRecipients { entries, .. } => {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].name, "prod/db");
+            }
+            _ => panic!("expected an audit"),
+        }
+    }
+
+    #[test]
+    fn delete_should_remove_entry_from_store() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        store.insert("name", b"").unwrap();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        handler.delete("name").unwrap();
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn run_should_set_env_vars_from_entries_and_fields() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        let db = gpg.encrypt(&[GPG_KEY_ID], b"hunter2\nuser: admin").unwrap();
+        store.insert("prod/db", &db).unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Run {
+                env: vec![
+                    "DB_PASS=prod/db".parse().unwrap(),
+                    "DB_USER=prod/db:user".parse().unwrap(),
+                ],
+                command: vec![
+                    "sh".to_owned(),
+                    "-c".to_owned(),
+                    r#"test "$DB_PASS" = hunter2 && test "$DB_USER" = admin"#.to_owned(),
+                ],
+            },
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &args).expect("expected a result");
+        assert!(result == HandlerResult::Run(0));
+    }
+
+    #[test]
+    fn run_should_propagate_exit_code_of_command() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(InMemoryStore::new(), secret_reader, FakeFsOps::default());
+        let command = ["sh", "-c", "exit 3"].map(ToOwned::to_owned);
+        let result = handler.run(&[], &command).expect("expected a result");
+        assert!(result == HandlerResult::Run(3));
+    }
+
+    #[test]
+    fn run_should_give_meaningful_error_if_field_does_not_exist() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let db = Gpg::new().encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        store.insert("prod/db", &db).unwrap();
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let env = ["DB_USER=prod/db:user".parse().unwrap()];
+        let result = handler.run(&env, &["true".to_owned()]);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            r#"The entry "prod/db" has no field "user"!"#
+        );
+    }
+
+    #[test]
+    fn render_should_write_template_with_resolved_values() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let db = Gpg::new()
+            .encrypt(&[GPG_KEY_ID], b"hunter2\nuser: admin")
+            .unwrap();
+        store.insert("prod/db", &db).unwrap();
+        let dir = tempdir().unwrap();
+        let template = dir.path().join("app.tmpl");
+        let output = dir.path().join("app.conf");
+        std::fs::write(
+            &template,
+            r#"user={{ sec "prod/db" field="user" }}
+password={{ sec "prod/db" field="password" }}
+"#,
+        )
+        .unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Render {
+                template: template.to_str().unwrap().to_owned(),
+                output: Some(output.to_str().unwrap().to_owned()),
+                check: false,
+            },
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        handle(&handler, &args).expect("expected a result");
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "user=admin\npassword=hunter2\n"
+        );
+    }
+
+    #[test]
+    fn render_should_fail_without_writing_output_if_entry_does_not_exist() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let dir = tempdir().unwrap();
+        let template = dir.path().join("app.tmpl");
+        let output = dir.path().join("app.conf");
+        std::fs::write(&template, r#"password={{ sec "prod/db" }}"#).unwrap();
+        let handler = Handler::new(InMemoryStore::new(), secret_reader, FakeFsOps::default());
+        let result = handler.render(template.to_str().unwrap(), output.to_str().unwrap());
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "An error occurred when attempting to retrieve the entry `prod/db`."
+        );
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn check_template_should_report_references_to_missing_entries() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        store.insert("prod/db", b"not decrypted").unwrap();
+        let dir = tempdir().unwrap();
+        let template = dir.path().join("app.tmpl");
+        std::fs::write(
+            &template,
+            r#"{{ sec "prod/db" }} {{ sec "prod/api" }} {{ sec "prod/api" field="key" }}"#,
+        )
+        .unwrap();
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handler.check_template(template.to_str().unwrap());
+        assert!(matches!(
+            result,
+            Err(Error::UnresolvedReferences(names)) if names == vec!["prod/api".to_owned()]
+        ));
+    }
+
+    #[test]
+    fn env_export_should_print_entries_in_folder_as_variables() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        let user = gpg.encrypt(&[GPG_KEY_ID], b"admin").unwrap();
+        let pass = gpg.encrypt(&[GPG_KEY_ID], b"hunter 2\nurl: x").unwrap();
+        store.insert("app/dev/db-user", &user).unwrap();
+        store.insert("app/dev/db/password", &pass).unwrap();
+        store.insert("app/prod/db-user", &user).unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Env {
+                action: EnvAction::Export {
+                    folder: "app/dev".to_owned(),
+                },
+            },
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        if let HandlerResult::EnvExport(lines) = handle(&handler, &args).expect("expected a result")
+        {
+            let lines = lines.iter().map(|line| &**line).collect::<Vec<_>>();
+            assert_eq!(
+                lines,
+                vec!["DB_USER=admin", r#"DB_PASSWORD="hunter 2\nurl: x""#]
+            );
+        } else {
+            panic!("expected an env export result");
+        }
+    }
+
+    #[test]
+    fn env_import_should_save_each_variable_as_an_entry() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let dir = tempdir().unwrap();
+        let file = dir.path().join(".env");
+        std::fs::write(&file, "# dev\nDB_USER=admin\nDB_PASS='hunter 2'\n").unwrap();
+        let store = InMemoryStore::new();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        let result = handler
+            .import_env(file.to_str().unwrap(), Some("app/dev/"), false)
+            .expect("expected a result");
+        assert!(
+            result
+                == HandlerResult::EnvImport(vec![
+                    "app/dev/DB_USER".to_owned(),
+                    "app/dev/DB_PASS".to_owned(),
+                ])
+        );
+        let value = handler.decrypt_entry("app/dev/DB_PASS").unwrap();
+        assert_eq!(&*value, "hunter 2");
+    }
+
+    #[test]
+    fn env_import_should_only_replace_existing_entries_when_forced() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let dir = tempdir().unwrap();
+        let file = dir.path().join(".env");
+        std::fs::write(&file, "DB_USER=admin\nDB_PASS=\"hunter 2\\nurl: x\"\n").unwrap();
+        let file = file.to_str().unwrap();
+        let store = InMemoryStore::new();
+        store.insert("DB_PASS", b"fake-key\nold").unwrap();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        assert!(handler.import_env(file, None, false).is_err());
+        assert!(matches!(
+            store.get("DB_USER"),
+            Err(StoreError::EntryDoesNotExist(_))
+        ));
+        handler.import_env(file, None, true).unwrap();
+        let value = handler.decrypt_entry("DB_PASS").unwrap();
+        assert_eq!(&*value, "hunter 2\nurl: x");
+    }
+
+    #[test]
+    fn import_pass_should_copy_entries_and_nested_gpg_id_files() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let gpg = Gpg::new();
+        let dir = tempdir().unwrap();
+        let source_dir = dir.path().to_str().unwrap();
+        let source = OnDiskStore::new(source_dir);
+        std::fs::write(format!("{source_dir}/{GPG_ID_LIST_FILE}"), GPG_KEY_ID).unwrap();
+        source
+            .set_folder_key_ids("team", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let api = gpg.encrypt(&[GPG_KEY_ID], b"token").unwrap();
+        source.insert("api", &api).unwrap();
+        source
+            .insert("team/db", &gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap())
+            .unwrap();
+        source.insert("broken", b"not ciphertext").unwrap();
+        source.insert("existing", &api).unwrap();
+        let store = InMemoryStore::new();
+        store.insert("existing", b"kept").unwrap();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Import {
+                source: ImportSource::Pass {
+                    dir: source_dir.to_owned(),
+                    replace_gpg_ids: false,
+                    trust_gpg_id: false,
+                    options: ImportOptions {
+                        dry_run: false,
+                        on_conflict: ConflictStrategy::Skip,
+                        prefix: None,
+                    },
+                },
+            },
+        };
+        if let HandlerResult::Import(report) = handle(&handler, &args).expect("expected a result") {
+            assert_eq!(report.imported, vec!["api", "team/db"]);
+            assert_eq!(report.skipped, vec!["existing"]);
+            let failed = report
+                .failed
+                .iter()
+                .map(|failure| failure.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(failed, vec!["broken"]);
+        } else {
+            panic!("expected an import result");
+        }
+        assert_eq!(store.get("api").unwrap(), api);
+        assert_eq!(store.get("existing").unwrap(), b"kept");
+        assert_eq!(
+            store.folder_key_ids("team").unwrap(),
+            Some(vec![GPG_KEY_ID.to_owned()])
+        );
+        assert_eq!(&*handler.decrypt_entry("team/db").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn import_pass_should_only_replace_differing_gpg_id_files_when_asked() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let gpg = Gpg::new();
+        let dir = tempdir().unwrap();
+        let source_dir = dir.path().to_str().unwrap();
+        let source = OnDiskStore::new(source_dir);
+        std::fs::write(format!("{source_dir}/{GPG_ID_LIST_FILE}"), GPG_KEY_ID).unwrap();
+        source
+            .set_folder_key_ids("team", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        source
+            .insert("team/db", &gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap())
+            .unwrap();
+        let store = InMemoryStore::new();
+        let others = vec![EXISTING_GPG_KEY.to_owned(), "other".to_owned()];
+        store.set_folder_key_ids("team", &others).unwrap();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        let options = ImportOptions {
+            dry_run: false,
+            on_conflict: ConflictStrategy::Skip,
+            prefix: None,
+        };
+        let result = handler
+            .import_pass(source_dir, false, false, &options)
+            .unwrap();
+        if let HandlerResult::Import(report) = result {
+            assert!(report.imported.is_empty());
+            let failed = report
+                .failed
+                .iter()
+                .map(|failure| failure.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(failed, vec!["team/.gpg-id", "team/db"]);
+        } else {
+            panic!("expected an import result");
+        }
+        assert_eq!(store.folder_key_ids("team").unwrap(), Some(others));
+        assert!(store.get("team/db").is_err());
+        handler
+            .import_pass(source_dir, true, false, &options)
+            .unwrap();
+        assert_eq!(
+            store.folder_key_ids("team").unwrap(),
+            Some(vec![GPG_KEY_ID.to_owned()])
+        );
+        assert_eq!(&*handler.decrypt_entry("team/db").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn backup_should_restore_entries_and_gpg_id_files_into_a_new_store() {
+        import_keys();
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("team", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let db = gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap();
+        store.insert("team/db", &db).unwrap();
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup.tar.gpg");
+        let backup_path = backup_path.to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handler.backup_create(backup_path, GPG_KEY_ID).unwrap();
+        assert!(matches!(
+            result,
+            HandlerResult::BackupCreate { entries: 1, .. }
+        ));
+
+        let restored = InMemoryStore::new();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(restored.clone(), secret_reader, FakeFsOps::default());
+        let result = handler.backup_restore(backup_path, false, false).unwrap();
+        assert!(matches!(result, HandlerResult::BackupRestore(1)));
+        assert_eq!(restored.get("team/db").unwrap(), db);
+        assert_eq!(
+            restored.folder_key_ids("team").unwrap(),
+            Some(vec![GPG_KEY_ID.to_owned()])
+        );
+    }
+
+    #[test]
+    fn backup_should_keep_gpg_id_files_as_written_and_armored_entries() {
+        import_keys();
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        let key_id_file = format!("# team\n{GPG_KEY_ID}\n");
+        store
+            .set_key_id_file("team", key_id_file.as_bytes())
+            .unwrap();
+        store.set_key_id_signature("team", b"signature").unwrap();
+        let db = armor::armor(&gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap());
+        store.insert("team/db", &db).unwrap();
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup.tar.gpg");
+        let backup_path = backup_path.to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        handler.backup_create(backup_path, GPG_KEY_ID).unwrap();
+        let archive = gpg
+            .decrypt_bytes(&std::fs::read(backup_path).unwrap())
+            .unwrap();
+        let paths = backup::unpack(&archive)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+        assert!(paths.contains(&"team/db.asc".to_owned()));
+        assert!(paths.contains(&"team/.gpg-id.sig".to_owned()));
+
+        let restored = InMemoryStore::new();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(restored.clone(), secret_reader, FakeFsOps::default());
+        handler.backup_restore(backup_path, false, false).unwrap();
+        assert_eq!(restored.get("team/db").unwrap(), db);
+        assert_eq!(
+            restored.key_id_file("team").unwrap(),
+            Some(key_id_file.into_bytes())
+        );
+        assert_eq!(
+            restored.key_id_signature("team").unwrap(),
+            Some(b"signature".to_vec())
+        );
+    }
+
+    #[test]
+    fn backup_restore_should_not_write_anything_if_entries_exist() {
+        import_keys();
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        store
+            .insert("api", &gpg.encrypt(&[GPG_KEY_ID], b"token").unwrap())
+            .unwrap();
+        store
+            .insert("db", &gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap())
+            .unwrap();
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup.tar.gpg");
+        let backup_path = backup_path.to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        handler.backup_create(backup_path, GPG_KEY_ID).unwrap();
+
+        let existing = InMemoryStore::new();
+        existing.insert("db", b"kept").unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(existing.clone(), secret_reader, FakeFsOps::default());
+        let result = handler.backup_restore(backup_path, false, false);
+        assert!(result.is_err());
+        assert_eq!(existing.list().unwrap(), vec!["db"]);
+        assert_eq!(existing.get("db").unwrap(), b"kept");
+    }
+
+    #[test]
+    fn fsck_should_repair_trailing_data_and_report_stray_files() {
+        import_keys();
+        let tmpdir = tempdir().unwrap();
+        let home = tmpdir.path().to_str().unwrap();
+        let store_path = format!("{home}/{PASSWORD_STORE_DIRECTORY}");
+        let store = OnDiskStore::new(&store_path);
+        store
+            .set_folder_key_ids("", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let api = Gpg::new().encrypt(&[GPG_KEY_ID], b"token").unwrap();
+        store.insert("api", &api).unwrap();
+        let mut db = api.clone();
+        db.extend_from_slice(b"left over from a longer value");
+        store.insert("db", &db).unwrap();
+        std::fs::write(format!("{store_path}/notes.txt"), "notes").unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(
+            OnDiskStore::new(&store_path),
+            secret_reader,
+            FakeFsOps::default(),
+        );
+        if let HandlerResult::Fsck(issues) = handler.fsck(true).expect("expected a result") {
+            assert_eq!(
+                issues
+                    .iter()
+                    .map(|issue| (issue.path.as_str(), &issue.status))
+                    .collect::<Vec<_>>(),
+                vec![("db.gpg", &Status::Repaired), ("notes.txt", &Status::Found)]
+            );
+        } else {
+            panic!("expected an fsck result");
+        }
+        assert_eq!(store.get("db").unwrap(), api);
+    }
+
+    #[test]
+    fn convert_should_reencode_entries_and_save_the_setting() {
+        import_keys();
+        let tmpdir = tempdir().unwrap();
+        let home = tmpdir.path().to_str().unwrap();
+        let store_path = format!("{home}/{PASSWORD_STORE_DIRECTORY}");
+        let store = OnDiskStore::new(&store_path);
+        store
+            .set_folder_key_ids("", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        store.insert("api", b"message").unwrap();
+        store.insert("db", &armor::armor(b"db")).unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(
+            OnDiskStore::new(&store_path),
+            secret_reader,
+            FakeFsOps::default(),
+        );
+        let result = handler.convert(Encoding::Armored).unwrap();
+        assert!(
+            result
+                == HandlerResult::Convert {
+                    encoding: Encoding::Armored,
+                    entries: 1,
+                }
+        );
+        assert!(Path::new(&format!("{store_path}/api.asc")).is_file());
+        assert!(!Path::new(&format!("{store_path}/api.gpg")).exists());
+        assert!(StoreConfig::load(&store_path).unwrap().armor);
+        handler.convert(Encoding::Binary).unwrap();
+        assert_eq!(store.get("api").unwrap(), b"message");
+        assert_eq!(store.get("db").unwrap(), b"db");
+        assert!(!StoreConfig::load(&store_path).unwrap().armor);
+    }
+
+    #[test]
+    fn audit_recipients_should_report_each_entry_in_folder() {
+        import_keys();
+        let store = InMemoryStore::new();
+        let api = Gpg::new().encrypt(&[GPG_KEY_ID], b"token").unwrap();
+        store.insert("prod/api", &api).unwrap();
+        store.insert("prod/broken", b"not ciphertext").unwrap();
+        store.insert("dev/api", &api).unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        let result = handler
+            .audit_recipients(Some("prod"), false)
+            .expect("expected a result");
+        if let HandlerResult::AuditRecipients { entries, .. } = result {
+            let names = entries
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["prod/api", "prod/broken"]);
+            assert!(entries[0].is_compliant(), "{:?}", entries[0]);
+            assert_eq!(entries[0].recipients.len(), 1);
+            assert!(entries[1].error.is_some());
+        } else {
+            panic!("expected an audit result");
+        }
+    }
+
+    fn signing_handler(
+        store: InMemoryStore,
+        signing_key: String,
+    ) -> Handler<FakeFsOps, FakeSecretReader<'static>, InMemoryStore, Gpg> {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        Handler::new(store, secret_reader, FakeFsOps::default())
+            .with_signing_keys(vec![signing_key])
+    }
+
+    #[test]
+    fn keys_rotate_should_refuse_unsigned_gpg_id_files_before_changing_anything() {
+        import_keys();
+        let fingerprint = Gpg::new().key_info(GPG_KEY_ID).unwrap().fingerprint;
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let handler = signing_handler(store.clone(), fingerprint.clone());
+        let result = handler.keys_rotate(GPG_KEY_ID, &fingerprint);
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Missing,
+                ..
+            })
+        ));
+        assert_eq!(store.key_ids().unwrap(), vec![GPG_KEY_ID]);
+    }
+
+    #[test]
+    fn save_should_refuse_if_gpg_id_is_not_signed() {
+        import_keys();
+        let fingerprint = Gpg::new().key_info(GPG_KEY_ID).unwrap().fingerprint;
+        let store = InMemoryStore::new();
+        let handler = signing_handler(store.clone(), fingerprint);
+        let result = handler.save("api", b"token");
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Missing,
+                ..
+            })
+        ));
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn save_should_only_encrypt_to_gpg_id_signed_by_trusted_key() {
+        import_keys();
+        let gpg = Gpg::new();
+        let fingerprint = gpg.key_info(GPG_KEY_ID).unwrap().fingerprint;
+        let store = InMemoryStore::new();
+        let contents = store.key_id_file("").unwrap().unwrap();
+        let signature = gpg.sign_detached(&[GPG_KEY_ID], &contents).unwrap();
+        store.set_key_id_signature("", &signature).unwrap();
+        let handler = signing_handler(store.clone(), fingerprint);
+        handler.save("api", b"token").unwrap();
+        assert_eq!(&*handler.decrypt_entry("api").unwrap(), "token");
+
+        // A folder whose `.gpg-id` was added without signing it.
+        store
+            .set_folder_key_ids("team", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let result = handler.save("team/db", b"hunter2");
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature { path, source: SignatureError::Missing }) if path == "team/.gpg-id"
+        ));
+        // Signed, but the file changed since.
+        store.set_key_id_signature("team", &signature).unwrap();
+        store
+            .set_folder_key_ids("team", &["intruder@example.com".to_owned()])
+            .unwrap();
+        let result = handler.save("team/db", b"hunter2");
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Invalid(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn insert_should_refuse_a_gpg_id_changed_since_it_was_signed() {
+        import_keys();
+        let gpg = Gpg::new();
+        let fingerprint = gpg.key_info(GPG_KEY_ID).unwrap().fingerprint;
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("team", &[GPG_KEY_ID.to_owned()])
+            .unwrap();
+        let contents = store.key_id_file("team").unwrap().unwrap();
+        let signature = gpg.sign_detached(&[GPG_KEY_ID], &contents).unwrap();
+        store.set_key_id_signature("team", &signature).unwrap();
+        let intruded = [GPG_KEY_ID.to_owned(), "intruder@example.com".to_owned()];
+        store.set_folder_key_ids("team", &intruded).unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("hunter2".as_bytes()),
+        };
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default())
+            .with_signing_keys(vec![fingerprint]);
+        let result = handler.insert("team/db", GPG_KEY_ID);
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Invalid(_),
+                ..
+            })
+        ));
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let result = handler.insert_file("team/db", GPG_KEY_ID, file.path().to_str().unwrap());
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Invalid(_),
+                ..
+            })
+        ));
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn save_should_refuse_if_gpg_id_is_signed_by_untrusted_key() {
+        import_keys();
+        let store = InMemoryStore::new();
+        let contents = store.key_id_file("").unwrap().unwrap();
+        let signature = Gpg::new().sign_detached(&[GPG_KEY_ID], &contents).unwrap();
+        store.set_key_id_signature("", &signature).unwrap();
+        let handler = signing_handler(store, "0".repeat(40));
+        let result = handler.save("api", b"token");
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::UntrustedSigner(_),
+                ..
+            })
+        ));
     }
 
-    impl FileSystemOperator for FakeFsOps {
-        fn home_dir(&self) -> Option<std::path::PathBuf> {
-            Some(PathBuf::from_str(&self.home).unwrap())
-        }
-
-        fn mkdir<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
-            std::fs::create_dir(path.as_ref().to_str().unwrap()).unwrap();
-            Ok(())
-        }
+    #[test]
+    fn backup_restore_should_only_sign_untrusted_gpg_id_files_when_asked() {
+        import_keys();
+        let gpg = Gpg::new();
+        let fingerprint = gpg.key_info(GPG_KEY_ID).unwrap().fingerprint;
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("team", &["intruder@example.com".to_owned()])
+            .unwrap();
+        store
+            .insert("team/db", &gpg.encrypt(&[GPG_KEY_ID], b"hunter2").unwrap())
+            .unwrap();
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup.tar.gpg");
+        let backup_path = backup_path.to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        Handler::new(store, secret_reader, FakeFsOps::default())
+            .backup_create(backup_path, GPG_KEY_ID)
+            .unwrap();
 
-        fn touch<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<File> {
-            Ok(std::fs::File::create(path).unwrap())
-        }
+        // The store restored into has a signed `.gpg-id` file of its own.
+        let restored = InMemoryStore::new();
+        let contents = restored.key_id_file("").unwrap().unwrap();
+        let signature = gpg.sign_detached(&[GPG_KEY_ID], &contents).unwrap();
+        restored.set_key_id_signature("", &signature).unwrap();
+        let handler = signing_handler(restored.clone(), fingerprint);
+        let result = handler.backup_restore(backup_path, false, false);
+        assert!(matches!(
+            result,
+            Err(Error::KeyIdSignature {
+                source: SignatureError::Missing,
+                ..
+            })
+        ));
+        assert!(restored.list().unwrap().is_empty());
+        assert_eq!(restored.folder_key_ids("team").unwrap(), None);
+        handler.backup_restore(backup_path, false, true).unwrap();
+        assert!(restored.key_id_signature("team").unwrap().is_some());
     }
 
     #[test]
-    fn should_encrypt_and_store_given_value() {
+    fn import_should_encrypt_entries_and_rename_conflicts() {
         import_keys();
-        let gpg = Gpg::new();
-        let name = "name".to_owned();
-        let args = Args {
-            action: Action::Insert {
-                name,
-                key_id: GPG_KEY_ID.to_owned(),
-            },
-        };
-        let input = "password\n";
         let secret_reader = FakeSecretReader {
-            secret: RefCell::new(input.as_bytes()),
+            secret: RefCell::new("".as_bytes()),
         };
         let store = InMemoryStore::new();
+        store.insert("keepass/Mail", b"existing").unwrap();
         let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
-        if let HandlerResult::Insert(name) = handle(&handler, &args).expect("expected a result") {
-            let ciphertext = store.get(name).unwrap();
-            let plaintext = gpg.decrypt(&ciphertext).unwrap();
-            assert_eq!(&*plaintext, input.trim());
+        let args = ExportArgs {
+            file: concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/resources/import/keepass.xml"
+            )
+            .to_owned(),
+            options: ImportOptions {
+                dry_run: false,
+                on_conflict: ConflictStrategy::Rename,
+                prefix: Some("keepass".to_owned()),
+            },
+        };
+        if let HandlerResult::Import(report) = handler
+            .import(Format::KeePass, &args)
+            .expect("expected a result")
+        {
+            assert_eq!(
+                report.imported,
+                vec!["keepass/Mail-1", "keepass/Servers/db-primary"]
+            );
+            assert_eq!(
+                report.renamed,
+                vec![("keepass/Mail".to_owned(), "keepass/Mail-1".to_owned())]
+            );
         } else {
-            panic!("got unexpected handle result")
+            panic!("expected an import result");
         }
+        assert_eq!(store.get("keepass/Mail").unwrap(), b"existing");
+        let db = handler.decrypt_entry("keepass/Servers/db-primary").unwrap();
+        assert_eq!(&*db, "hunter2\nusername: postgres\nport: 5432");
     }
 
     #[test]
-    fn should_retrieve_entry_decrypted() {
+    fn import_dry_run_should_not_change_the_store() {
         import_keys();
-        let name = "name".to_string();
-        let retrieve_args = Args {
-            action: Action::Retrieve { name: name.clone() },
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
         };
-        let input = "password\n";
+        let store = InMemoryStore::new();
+        let handler = Handler::new(store.clone(), secret_reader, FakeFsOps::default());
+        let args = ExportArgs {
+            file: concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/resources/import/chrome.csv"
+            )
+            .to_owned(),
+            options: ImportOptions {
+                dry_run: true,
+                on_conflict: ConflictStrategy::Skip,
+                prefix: None,
+            },
+        };
+        let result = handler
+            .import(Format::Chrome, &args)
+            .expect("expected a result");
+        assert!(
+            result
+                == HandlerResult::Import(ImportReport {
+                    dry_run: true,
+                    imported: vec!["mail.example.com".to_owned()],
+                    skipped: vec!["mail.example.com".to_owned()],
+                    ..ImportReport::default()
+                })
+        );
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn find_should_return_matching_entry_names() {
+        import_keys();
         let secret_reader = FakeSecretReader {
-            secret: RefCell::new(input.as_bytes()),
+            secret: RefCell::new("".as_bytes()),
         };
         let store = InMemoryStore::new();
+        store.insert("prod/db", b"").unwrap();
+        store.insert("prod/api", b"").unwrap();
+        store.insert("dev/db", b"").unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Find {
+                patterns: vec!["*/db".to_owned()],
+            },
+        };
         let handler = Handler::new(store, secret_reader, FakeFsOps::default());
-        handler.insert(&name, GPG_KEY_ID).unwrap();
-        if let HandlerResult::Retrieve(value) =
-            handle(&handler, &retrieve_args).expect("expected a result")
+        let result = handle(&handler, &args).expect("expected a result");
+        assert!(result == HandlerResult::Find(vec!["dev/db".to_owned(), "prod/db".to_owned()]));
+    }
+
+    #[test]
+    fn grep_should_return_entries_whose_contents_match() {
+        import_keys();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let gpg = Gpg::new();
+        let store = InMemoryStore::new();
+        let db = gpg
+            .encrypt(&[GPG_KEY_ID], b"password\nhost: db.local")
+            .unwrap();
+        let api = gpg.encrypt(&[GPG_KEY_ID], b"token").unwrap();
+        store.insert("prod/db", &db).unwrap();
+        store.insert("prod/api", &api).unwrap();
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Grep {
+                pattern: "DB.LOCAL".to_owned(),
+                regex: false,
+                ignore_case: true,
+            },
+        };
+        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
+        if let HandlerResult::Grep { matches, .. } =
+            handle(&handler, &args).expect("expected a result")
         {
-            assert_eq!(&*value, input.trim());
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].name, "prod/db");
+            assert_eq!(&*matches[0].lines[0].line, "host: db.local");
             return;
         }
         panic!("got unexpected handle result");
     }
 
     #[test]
-    fn should_give_meaningful_error_if_entry_could_not_be_decrypted() {
-        let name = "name".to_string();
-        let retrieve_args = Args {
-            action: Action::Retrieve { name: name.clone() },
-        };
+    fn save_should_reject_names_outside_the_store() {
         let secret_reader = FakeSecretReader {
             secret: RefCell::new("".as_bytes()),
         };
         let store = InMemoryStore::new();
-        store.insert(name.clone(), b"").unwrap();
-        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
-        let result = handle(&handler, &retrieve_args);
-        assert!(result.is_err());
-        assert_eq!(
-            result.err().unwrap().to_string(),
-            format!(r#"The entry "{name}" could not be decrypted!"#)
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
         );
+        for name in ["../../.ssh/x", "/etc/x", "a//b"] {
+            assert!(matches!(
+                handler.save(name, b"value"),
+                Err(Error::Store(StoreError::InvalidName(_)))
+            ));
+            assert!(matches!(
+                handler.generate(name, 8),
+                Err(Error::Store(StoreError::InvalidName(_)))
+            ));
+            assert!(matches!(
+                handler.retrieve(name),
+                Err(Error::Store(StoreError::InvalidName(_)))
+            ));
+            assert!(matches!(
+                handler.retrieve_to_file(name, "/dev/null"),
+                Err(Error::Store(StoreError::InvalidName(_)))
+            ));
+        }
+        assert!(store.list().unwrap().is_empty());
     }
 
     #[test]
-    fn insert_should_give_meaningful_error_if_store_has_an_fs_error() {
-        let name = "name".to_string();
-        let retrieve_args = Args {
-            action: Action::Insert {
-                name: name.clone(),
-                key_id: GPG_KEY_ID.to_string(),
-            },
-        };
+    fn grep_should_keep_searching_past_entries_which_fail_to_decrypt() {
         let secret_reader = FakeSecretReader {
-            secret: RefCell::new("secret\n".as_bytes()),
+            secret: RefCell::new("".as_bytes()),
         };
-        let store = IoErrorStore;
-        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
-        let result = handle(&handler, &retrieve_args);
-        assert!(result.is_err());
-        let partial_error =
-            &format!("An error occurred when attempting to insert the entry `{name}`.");
-        let err = result.err().unwrap();
-        assert!(
-            err.to_string().contains(partial_error),
-            "error incorrect, got `{err}`",
-        );
+        let store = InMemoryStore::new();
+        store.insert("prod/api", b"no recipients").unwrap();
+        store
+            .insert("prod/db", b"fake-key\nhost: db.local")
+            .unwrap();
+        let handler =
+            Handler::with_backend(store, secret_reader, FakeFsOps::default(), FakeBackend);
+        let result = handler.grep("db.local", false, false).unwrap();
+        if let HandlerResult::Grep { matches, failed } = result {
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].name, "prod/db");
+            assert_eq!(failed.len(), 1);
+            assert_eq!(failed[0].name, "prod/api");
+            return;
+        }
+        panic!("got unexpected handle result");
     }
 
     #[test]
-    fn retrieve_should_give_meaningful_error_if_store_has_an_fs_error() {
-        let name = "name".to_string();
-        let retrieve_args = Args {
-            action: Action::Retrieve { name: name.clone() },
-        };
+    fn grep_should_give_meaningful_error_if_pattern_is_invalid() {
+        import_keys();
         let secret_reader = FakeSecretReader {
             secret: RefCell::new("".as_bytes()),
         };
-        let store = IoErrorStore;
-        let handler = Handler::new(store, secret_reader, FakeFsOps::default());
-        let result = handle(&handler, &retrieve_args);
-        assert!(result.is_err());
-        let partial_error =
-            &format!("An error occurred when attempting to retrieve the entry `{name}`.");
-        let actual_error = result.err().unwrap();
-        assert!(
-            actual_error.to_string().contains(partial_error),
-            "error incorrect, got `{actual_error}`"
-        );
+        let args = Args {
+            global: GlobalOptions::default(),
+            action: Action::Grep {
+                pattern: "(".to_owned(),
+                regex: true,
+                ignore_case: false,
+            },
+        };
+        let handler = Handler::new(InMemoryStore::new(), secret_reader, FakeFsOps::default());
+        let result = handle(&handler, &args);
+        assert!(matches!(result, Err(Error::Pattern(_))));
     }
 
     #[test]
@@ -329,6 +3475,7 @@ mod test {
         let tmpdir = tempdir().unwrap();
         let tmpdir = tmpdir.path().to_str().unwrap();
         let args = Args {
+            global: GlobalOptions::default(),
             action: Action::Initialize {
                 key_id: EXISTING_GPG_KEY.to_string(),
             },
@@ -355,6 +3502,7 @@ mod test {
         let tmpdir = tempdir().unwrap();
         let tmpdir = tmpdir.path().to_str().unwrap();
         let args = Args {
+            global: GlobalOptions::default(),
             action: Action::Initialize {
                 key_id: EXISTING_GPG_KEY.to_string(),
             },
@@ -383,6 +3531,7 @@ mod test {
         let tmpdir = tempdir().unwrap();
         let tmpdir = tmpdir.path().to_str().unwrap();
         let args = Args {
+            global: GlobalOptions::default(),
             action: Action::Initialize {
                 key_id: EXISTING_GPG_KEY.to_string(),
             },
@@ -399,10 +3548,319 @@ mod test {
         let maybe_error = result.as_ref().err();
         assert!(result.is_ok(), "expected result, got {maybe_error:?}");
         assert!(result.ok().unwrap() == HandlerResult::Initialize());
-        let key_id = std::fs::read_to_string(&format!(
+        let contents = std::fs::read_to_string(format!(
             "{tmpdir}/{PASSWORD_STORE_DIRECTORY}/{GPG_ID_LIST_FILE}"
         ))
         .unwrap();
-        assert_eq!(key_id, EXISTING_GPG_KEY);
+        let fingerprint = Gpg::new().key_info(EXISTING_GPG_KEY).unwrap().fingerprint;
+        assert_eq!(contents, format!("# {EXISTING_GPG_KEY}\n{fingerprint}\n"));
+        assert_eq!(store::parse_key_ids(&contents), vec![fingerprint]);
+    }
+
+    #[test]
+    fn handler_should_encrypt_and_decrypt_with_the_given_backend() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("hunter2\n".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        handler.insert("api", GPG_KEY_ID).unwrap();
+        assert_eq!(
+            store.get("api").unwrap(),
+            format!("{GPG_KEY_ID}\nhunter2").into_bytes()
+        );
+        let retrieved = handler.decrypt_entry("api").unwrap();
+        assert_eq!(&*retrieved, "hunter2");
+        handler.save("db", b"secret").unwrap();
+        assert_eq!(
+            store.get("db").unwrap(),
+            format!("{GPG_KEY_ID}\nsecret").into_bytes()
+        );
+    }
+
+    #[test]
+    fn insert_should_encrypt_to_the_keys_of_the_folder() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("hunter2\n".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let team = vec!["fake-alice".to_owned(), "fake-bob".to_owned()];
+        store.set_folder_key_ids("team", &team).unwrap();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        assert!(handler.insert("team/db", "fake-mallory").is_err());
+        assert!(handler.insert("team/db", GPG_KEY_ID).is_err());
+        assert!(store.get("team/db").is_err());
+        handler.insert("team/db", "fake-bob").unwrap();
+        assert_eq!(
+            store.get("team/db").unwrap(),
+            b"fake-alice,fake-bob\nhunter2"
+        );
+    }
+
+    #[test]
+    fn files_should_be_streamed_into_and_out_of_entries() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("dump.sql");
+        std::fs::write(&input, b"create table users;").unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        handler
+            .insert_file("db/dump", GPG_KEY_ID, input.to_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            store.get("db/dump").unwrap(),
+            format!("{GPG_KEY_ID}\ncreate table users;").into_bytes()
+        );
+        let output = dir.path().join("restored.sql");
+        let output = output.to_str().unwrap();
+        let result = handler.retrieve_to_file("db/dump", output).unwrap();
+        assert!(result == HandlerResult::RetrieveFile(output.to_owned()));
+        assert_eq!(std::fs::read(output).unwrap(), b"create table users;");
+        let mode = std::fs::metadata(output).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        store.insert("broken", b"no recipients").unwrap();
+        assert!(matches!(
+            handler.retrieve_to_file("broken", output),
+            Err(Error::Decrypt { .. })
+        ));
+        assert_eq!(std::fs::read(output).unwrap(), b"create table users;");
+        let broken = dir.path().join("broken.sql");
+        let broken = broken.to_str().unwrap();
+        assert!(handler.retrieve_to_file("broken", broken).is_err());
+        assert!(!Path::new(broken).exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn binary_entries_should_only_be_retrieved_to_files() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("keystore.p12");
+        std::fs::write(&input, [0x30, 0x82, 0xff, 0xfe]).unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        handler
+            .insert_file("keystore", GPG_KEY_ID, input.to_str().unwrap())
+            .unwrap();
+        store.insert("api", b"fake-key\nhunter2").unwrap();
+        assert!(matches!(
+            handler.retrieve("keystore"),
+            Err(Error::BinaryEntry { name }) if name == "keystore"
+        ));
+        match handler.grep("hunter", false, false).unwrap() {
+            HandlerResult::Grep { matches, failed } => {
+                assert!(failed.is_empty());
+                assert_eq!(matches.len(), 1);
+                assert_eq!(matches[0].name, "api");
+            }
+            _ => panic!("expected grep results"),
+        }
+        let output = dir.path().join("restored.p12");
+        let output = output.to_str().unwrap();
+        handler.retrieve_to_file("keystore", output).unwrap();
+        assert_eq!(std::fs::read(output).unwrap(), [0x30, 0x82, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn initialize_should_write_the_recipients_file_of_the_backend() {
+        let tmpdir = tempdir().unwrap();
+        let tmpdir = tmpdir.path().to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let fs_ops = FakeFsOps {
+            home: tmpdir.to_string(),
+        };
+        let handler =
+            Handler::with_backend(InMemoryStore::new(), secret_reader, fs_ops, FakeBackend);
+        assert!(matches!(
+            handler.initialize("unknown-key"),
+            Err(Error::Crypto(CryptoError::KeyNotFound(_)))
+        ));
+        handler.initialize("fake-key").unwrap();
+        let store_path = format!("{tmpdir}/{PASSWORD_STORE_DIRECTORY}");
+        let recipients = std::fs::read_to_string(format!("{store_path}/.fake-recipients")).unwrap();
+        assert_eq!(recipients, "fake-key\n");
+        assert!(!Path::new(&format!("{store_path}/{GPG_ID_LIST_FILE}")).exists());
+    }
+
+    #[test]
+    fn ambiguous_recipients_should_be_resolved_with_a_warning() {
+        let tmpdir = tempdir().unwrap();
+        let tmpdir = tmpdir.path().to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("hunter2\n".as_bytes()),
+        };
+        let fs_ops = FakeFsOps {
+            home: tmpdir.to_string(),
+        };
+        let store = InMemoryStore::new();
+        let handler = Handler::with_backend(store.clone(), secret_reader, fs_ops, FakeBackend);
+        handler.initialize("team").unwrap();
+        let store_path = format!("{tmpdir}/{PASSWORD_STORE_DIRECTORY}");
+        let recipients = std::fs::read_to_string(format!("{store_path}/.fake-recipients")).unwrap();
+        assert_eq!(recipients, "# team\nfake-alice\n");
+        let warnings = handler.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("fake-alice was used rather than fake-bob"));
+        store
+            .set_folder_key_ids("", &["fake-alice".to_owned()])
+            .unwrap();
+        handler.insert("api", "fake-alice").unwrap();
+        assert_eq!(store.get("api").unwrap(), b"fake-alice\nhunter2");
+        assert!(handler.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn encrypting_should_warn_of_recipients_about_to_expire() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("team", &["expiring-key".to_owned()])
+            .unwrap();
+        let handler =
+            Handler::with_backend(store, secret_reader, FakeFsOps::default(), FakeBackend);
+        handler.save("team/api", b"hunter2").unwrap();
+        handler.save("team/db", b"hunter2").unwrap();
+        let warnings = handler.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("The key expiring-key expires in 9 days."));
+        let handler = handler.with_expiry_warning(Duration::from_secs(5 * 24 * 60 * 60));
+        handler.save("team/api", b"hunter2").unwrap();
+        assert!(handler.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn keys_rotate_should_replace_the_key_and_reencrypt_affected_entries() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        let team = vec!["fake-old".to_owned(), "fake-carol".to_owned()];
+        store.set_folder_key_ids("team", &team).unwrap();
+        store
+            .insert("team/api", b"fake-old,fake-carol\nhunter2")
+            .unwrap();
+        store.insert("other", b"fake-dave\nletmein").unwrap();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        assert!(handler.keys_rotate("fake-nobody", "fake-new").is_err());
+        let result = handler.keys_rotate("fake-old", "fake-new").unwrap();
+        assert!(
+            result
+                == HandlerResult::KeysRotate {
+                    folders: 1,
+                    entries: 1
+                }
+        );
+        assert_eq!(
+            store.folder_key_ids("team").unwrap().unwrap(),
+            vec!["fake-new", "fake-carol"]
+        );
+        assert_eq!(
+            store.get("team/api").unwrap(),
+            b"fake-new,fake-carol\nhunter2"
+        );
+        assert_eq!(store.get("other").unwrap(), b"fake-dave\nletmein");
+    }
+
+    #[test]
+    fn gpg_id_files_should_be_written_with_fingerprints_and_encrypted_to_as_written() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let store = InMemoryStore::new();
+        store
+            .set_folder_key_ids("team", &["fake-old".to_owned()])
+            .unwrap();
+        store
+            .set_folder_key_ids("ops", &["team".to_owned()])
+            .unwrap();
+        let handler = Handler::with_backend(
+            store.clone(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        handler.save("ops/db", b"hunter2").unwrap();
+        assert_eq!(store.get("ops/db").unwrap(), b"team\nhunter2");
+        handler.keys_rotate("fake-old", "team").unwrap();
+        assert_eq!(
+            store.key_id_file("team").unwrap().unwrap(),
+            b"# team\nfake-alice\n"
+        );
+        handler.save("team/api", b"hunter2").unwrap();
+        assert_eq!(store.get("team/api").unwrap(), b"fake-alice\nhunter2");
+    }
+
+    #[test]
+    fn openpgp_only_commands_should_fail_with_other_backends() {
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::with_backend(
+            InMemoryStore::new(),
+            secret_reader,
+            FakeFsOps::default(),
+            FakeBackend,
+        );
+        let result = handler.audit_recipients(None, false);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Auditing recipients is only supported in OpenPGP stores."
+        );
+        let result = handler.keys_list();
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Managing keys is only supported in OpenPGP stores."
+        );
+    }
+
+    #[test]
+    fn keys_import_should_fail_if_file_has_no_keys() {
+        import_keys();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "not a key").unwrap();
+        let path = file.path().to_str().unwrap();
+        let secret_reader = FakeSecretReader {
+            secret: RefCell::new("".as_bytes()),
+        };
+        let handler = Handler::new(InMemoryStore::new(), secret_reader, FakeFsOps::default());
+        let result = handler.keys_import(path);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            format!("No keys were found in `{path}`.")
+        );
     }
 }
@@ -0,0 +1,46 @@
+use std::{fmt, time::SystemTime};
+
+/// How far a key is trusted to belong to who its user ids say, the
+/// validity gpg gives it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trust {
+    Unknown,
+    Never,
+    Marginal,
+    Full,
+    Ultimate,
+}
+
+impl Trust {
+    /// Whether gpg will encrypt to the key without being told to trust it.
+    pub fn is_trusted(self) -> bool {
+        matches!(self, Trust::Full | Trust::Ultimate)
+    }
+}
+
+impl fmt::Display for Trust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let trust = match self {
+            Trust::Unknown => "unknown",
+            Trust::Never => "never",
+            Trust::Marginal => "marginal",
+            Trust::Full => "full",
+            Trust::Ultimate => "ultimate",
+        };
+        f.write_str(trust)
+    }
+}
+
+/// What is known of a key in the keyring.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyInfo {
+    pub fingerprint: String,
+    /// The primary user id, such as `Name <email>`.
+    pub user_id: Option<String>,
+    /// When the key can no longer be encrypted to, as the last of its
+    /// encryption subkeys or the primary key expires, if ever.
+    pub expires: Option<SystemTime>,
+    pub expired: bool,
+    pub revoked: bool,
+    pub trust: Trust,
+}
@@ -1,28 +1,309 @@
+pub mod armor;
+pub mod audit;
+pub mod backup;
+pub mod browser;
 pub mod cli;
+pub mod config;
+pub mod crypto;
+pub mod dotenv;
+pub mod entry;
+pub mod error;
+pub mod find;
 pub mod fs;
+pub mod fsck;
+pub mod fuzzy;
+pub mod generate;
+#[cfg(feature = "gpg")]
 pub mod gpg;
+pub mod grep;
+pub mod import;
 pub mod input;
+pub mod keys;
+pub mod message;
 mod output;
+pub mod picker;
 pub mod secrets;
 pub mod store;
+pub mod template;
+pub mod tree;
 
+use audit::EntryAudit;
 use clap::Parser;
+#[cfg(feature = "age")]
+use cli::Action;
 use cli::Args;
-use fs::FileSystemOperations;
-use input::{handle, Handler, PASSWORD_STORE_DIRECTORY};
+use config::{StoreConfig, UserConfig, CONFIG_FILE, USER_CONFIG_FILE};
+#[cfg(feature = "age")]
+use crypto::age::{Age, AGE_RECIPIENTS_FILE};
+#[cfg(feature = "sequoia")]
+use crypto::sequoia::Sequoia;
+use crypto::CryptoBackend;
+use error::{Error, InitError, EXIT_FAILURE, EXIT_USAGE};
+use fs::{FileSystemOperations, FileSystemOperator};
+use fsck::Status;
+#[cfg(feature = "gpg")]
+use gpg::Gpg;
+use input::{handle, Handler, HandlerResult, PASSWORD_STORE_DIRECTORY};
 use output::{write_result, TerminalOutput};
+#[cfg(any(feature = "gpg", feature = "sequoia"))]
+use secrets::FdSecretReader;
+#[cfg(feature = "gpg")]
+use secrets::PassphrasePrompt;
 use secrets::StdinSecretReader;
-use std::io;
+#[cfg(feature = "sequoia")]
+use secrets::{Pinentry, SecretReader, ZeroizedString};
+#[cfg(feature = "age")]
+use std::path::{Path, PathBuf};
+use std::{env, error::Error as _, io, process::ExitCode};
 use store::OnDiskStore;
 
-fn main() -> anyhow::Result<()> {
-    let store_dir = format!("{}/{}", "/home/chaospie", PASSWORD_STORE_DIRECTORY);
-    let output = TerminalOutput::new(io::stdout());
-    let handler = Handler::new(
+#[cfg(not(any(feature = "gpg", feature = "age", feature = "sequoia")))]
+compile_error!("sec needs at least one of the `gpg`, `age` or `sequoia` features.");
+
+/// Fingerprints, separated by whitespace, of the keys trusted to sign
+/// `.gpg-id` files, as with `PASSWORD_STORE_SIGNING_KEY` in `pass`.
+const SIGNING_KEY_VARIABLE: &str = "SEC_SIGNING_KEY";
+/// Path of the file of `age` identities entries are decrypted with.
+#[cfg(feature = "age")]
+const AGE_IDENTITIES_VARIABLE: &str = "SEC_AGE_IDENTITIES";
+/// Key files, or directories of them, separated as in `PATH`, to encrypt and
+/// decrypt with in place of the GnuPG keyring.
+#[cfg(feature = "sequoia")]
+const KEYRING_VARIABLE: &str = "SEC_KEYRING";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            report(&err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<u8, Error> {
+    let home_dir = FileSystemOperations
+        .home_dir()
+        .ok_or(InitError::HomeDirectoryNotFound)?;
+    let store_dir = format!("{}/{}", home_dir.display(), PASSWORD_STORE_DIRECTORY);
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(err) => return Ok(usage(&err)),
+    };
+    let signing_keys = env::var(SIGNING_KEY_VARIABLE)
+        .map(|keys| keys.split_whitespace().map(ToOwned::to_owned).collect())
+        .unwrap_or_default();
+    let config = StoreConfig::load(&store_dir).map_err(|source| Error::Config {
+        path: format!("{store_dir}/{CONFIG_FILE}"),
+        source,
+    })?;
+    let config_dir = dirs::config_dir().unwrap_or_else(|| home_dir.join(".config"));
+    let user_config_path = config_dir.join(USER_CONFIG_FILE);
+    let user_config =
+        UserConfig::load(&user_config_path, &store_dir).map_err(|source| Error::Config {
+            path: user_config_path.display().to_string(),
+            source,
+        })?;
+    #[cfg(feature = "age")]
+    if uses_age(&store_dir, &args) {
+        reject_gpg_home(&args, &user_config, "age")?;
+        if config.armor {
+            return Err(Error::UnsupportedSetting {
+                setting: "armor".to_owned(),
+                backend: "age",
+            });
+        }
+        let identity_file = env::var_os(AGE_IDENTITIES_VARIABLE)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| config_dir.join("sec/age-identities"));
+        let store =
+            OnDiskStore::new(store_dir).with_file_names(Age::ENTRY_EXTENSION, Age::RECIPIENTS_FILE);
+        let handler = Handler::with_backend(
+            store,
+            StdinSecretReader,
+            FileSystemOperations,
+            Age::new(identity_file),
+        )
+        .with_signing_keys(signing_keys)
+        .with_expiry_warning(config.expiry_warning());
+        return execute(&handler, &args);
+    }
+    #[cfg(feature = "sequoia")]
+    if let Some(keyring) = env::var_os(KEYRING_VARIABLE) {
+        reject_gpg_home(&args, &user_config, "Sequoia")?;
+        let pinentry = args.global.pinentry();
+        let passphrase_reader = args.global.passphrase_fd.map(FdSecretReader::new);
+        let backend = Sequoia::from_paths(env::split_paths(&keyring))?
+            .with_passphrase(move |fingerprint| {
+                key_passphrase(pinentry, passphrase_reader.as_ref(), fingerprint)
+            })
+            .with_armor(config.armor);
+        let handler = Handler::with_backend(
+            OnDiskStore::new(store_dir),
+            StdinSecretReader,
+            FileSystemOperations,
+            backend,
+        )
+        .with_signing_keys(signing_keys)
+        .with_expiry_warning(config.expiry_warning());
+        return execute(&handler, &args);
+    }
+    execute_with_gpg(store_dir, &args, &config, &user_config, signing_keys)
+}
+
+/// Execute the command against a store encrypted through gpg-agent, the
+/// default backend.
+#[cfg(feature = "gpg")]
+fn execute_with_gpg(
+    store_dir: String,
+    args: &Args,
+    config: &StoreConfig,
+    user_config: &UserConfig,
+    signing_keys: Vec<String>,
+) -> Result<u8, Error> {
+    let mut gpg = Gpg::new()
+        .with_pinentry(args.global.pinentry())
+        .with_armor(config.armor);
+    if let Some(gpg_home) = args
+        .global
+        .gpg_home
+        .clone()
+        .or_else(|| user_config.gpg_home.clone())
+    {
+        gpg = gpg.with_home_dir(gpg_home);
+    }
+    // Passphrases of keys and of symmetric entries are read from the same
+    // descriptor, a line each in the order they are asked for.
+    let passphrase_reader = args.global.passphrase_fd.map(FdSecretReader::new);
+    gpg = match passphrase_reader.clone() {
+        Some(reader) => gpg.with_passphrase_reader(reader),
+        None => gpg.with_passphrase_reader(PassphrasePrompt("Enter your GPG passphrase: ")),
+    };
+    let handler = Handler::with_backend(
         OnDiskStore::new(store_dir),
         StdinSecretReader,
         FileSystemOperations,
-    );
-    let result = handle(&handler, &Args::parse())?;
-    write_result(result, output)
+        gpg,
+    )
+    .with_signing_keys(signing_keys)
+    .with_passphrase_reader(match passphrase_reader {
+        Some(reader) => Box::new(reader),
+        None => Box::new(PassphrasePrompt("Enter the passphrase of the entry: ")),
+    })
+    .with_expiry_warning(config.expiry_warning());
+    execute(&handler, args)
+}
+
+#[cfg(not(feature = "gpg"))]
+fn execute_with_gpg(
+    _store_dir: String,
+    _args: &Args,
+    _config: &StoreConfig,
+    _user_config: &UserConfig,
+    _signing_keys: Vec<String>,
+) -> Result<u8, Error> {
+    Err(anyhow::anyhow!("This store needs sec to be built with the `gpg` feature.").into())
+}
+
+/// Print the help, version or usage error clap parsed the arguments into,
+/// returning the code to exit with.
+fn usage(err: &clap::Error) -> u8 {
+    // Failing to print help to a closed stdout is not worth reporting.
+    let _ = err.print();
+    if err.use_stderr() {
+        EXIT_USAGE
+    } else {
+        0
+    }
+}
+
+/// Refuse a GnuPG home directory, from `--gpg-home` or the user config, when
+/// the store is encrypted by a backend which does not read one, rather than
+/// silently ignoring it.
+#[cfg(any(feature = "age", feature = "sequoia"))]
+fn reject_gpg_home(args: &Args, config: &UserConfig, backend: &'static str) -> Result<(), Error> {
+    let setting = if args.global.gpg_home.is_some() {
+        "--gpg-home"
+    } else if config.gpg_home.is_some() {
+        "gpg-home"
+    } else {
+        return Ok(());
+    };
+    Err(Error::UnsupportedSetting {
+        setting: setting.to_owned(),
+        backend,
+    })
+}
+
+/// The passphrase of the secret key `fingerprint`, asked for as
+/// `--pinentry` says, or read from `passphrase_reader`, the `--passphrase-fd`.
+/// There is no gpg-agent, so `ask` prompts on the terminal as `loopback` does.
+#[cfg(feature = "sequoia")]
+fn key_passphrase(
+    pinentry: Pinentry,
+    passphrase_reader: Option<&FdSecretReader>,
+    fingerprint: &str,
+) -> io::Result<ZeroizedString> {
+    match (pinentry, passphrase_reader) {
+        (Pinentry::Cancel, _) => Err(io::Error::other(format!(
+            "the passphrase of key {fingerprint} is not asked for with `--pinentry cancel`"
+        ))),
+        (_, Some(reader)) => reader
+            .read_secret()
+            .and_then(|secret| Ok(secret.into_zeroized_string()?))
+            .map_err(io::Error::other),
+        (_, None) => {
+            rpassword::prompt_password(format!("Enter the passphrase of key {fingerprint}: "))
+                .map(ZeroizedString::new)
+        }
+    }
+}
+
+/// Whether the store is encrypted with `age`, as it has a `.age-recipients`
+/// file or is being initialized with an X25519 recipient.
+#[cfg(feature = "age")]
+fn uses_age(store_dir: &str, args: &Args) -> bool {
+    match &args.action {
+        Action::Initialize { key_id } => crypto::age::is_recipient(key_id),
+        _ => Path::new(store_dir).join(AGE_RECIPIENTS_FILE).is_file(),
+    }
+}
+
+fn execute<C: CryptoBackend>(
+    handler: &Handler<FileSystemOperations, StdinSecretReader, OnDiskStore, C>,
+    args: &Args,
+) -> Result<u8, Error> {
+    let output = TerminalOutput::new(io::stdout());
+    let result = handle(handler, args);
+    for warning in handler.take_warnings() {
+        eprintln!("Warning: {warning}");
+    }
+    let result = result?;
+    let code = match result {
+        HandlerResult::Run(code) => code,
+        HandlerResult::Import(ref report) if !report.failed.is_empty() => EXIT_FAILURE,
+        HandlerResult::Grep { ref failed, .. } if !failed.is_empty() => EXIT_FAILURE,
+        HandlerResult::Fsck(ref issues)
+            if issues.iter().any(|issue| issue.status != Status::Repaired) =>
+        {
+            EXIT_FAILURE
+        }
+        HandlerResult::AuditRecipients { ref entries, .. }
+            if !entries.iter().all(EntryAudit::is_compliant) =>
+        {
+            EXIT_FAILURE
+        }
+        _ => 0,
+    };
+    write_result(result, output)?;
+    Ok(code)
+}
+
+fn report(err: &Error) {
+    eprintln!("Error: {err}");
+    let mut source = err.source();
+    while let Some(cause) = source {
+        eprintln!("  Caused by: {cause}");
+        source = cause.source();
+    }
 }
@@ -0,0 +1,292 @@
+use crate::armor::{self, ArmorError};
+use thiserror::Error;
+
+/// Public-key encrypted session key packet.
+const PKESK_TAG: u8 = 1;
+/// Symmetric-key encrypted session key packet.
+const SKESK_TAG: u8 = 3;
+/// Symmetrically encrypted data packet.
+const SED_TAG: u8 = 9;
+/// Marker packet, which is ignored.
+const MARKER_TAG: u8 = 10;
+/// Symmetrically encrypted integrity protected data packet.
+const SEIPD_TAG: u8 = 18;
+/// AEAD encrypted data packet.
+const AEAD_TAG: u8 = 20;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MessageError {
+    #[error("the file is empty")]
+    Empty,
+    #[error("the message ends part way through a packet")]
+    Truncated,
+    #[error("byte {0} is not the start of an OpenPGP packet")]
+    InvalidPacket(usize),
+    #[error("unexpected packet with tag {0}")]
+    UnexpectedPacket(u8),
+    #[error("the message has no encrypted data")]
+    NotEncrypted,
+    #[error("unsupported version {0} of a session key packet")]
+    UnsupportedVersion(u8),
+    #[error("the ASCII armor is invalid, {0}")]
+    Armor(#[from] ArmorError),
+}
+
+/// What can be read of an encrypted OpenPGP message without decrypting it.
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    /// The long key ids, in upper case hex, of the keys the message is
+    /// encrypted to. An all zero id is a hidden recipient.
+    pub recipients: Vec<String>,
+    /// Whether the message can be decrypted with a passphrase.
+    pub symmetric: bool,
+    /// The length in bytes of the message, anything past this is not part
+    /// of it.
+    pub len: usize,
+}
+
+impl Message {
+    /// Whether the message is encrypted with a passphrase and to no key.
+    pub fn is_symmetric_only(&self) -> bool {
+        self.symmetric && self.recipients.is_empty()
+    }
+}
+
+/// Read the session key packets of the binary OpenPGP message at the start
+/// of `bytes`, checking it is followed by encrypted data. Trailing bytes
+/// are not an error, compare [`Message::len`] to check for them.
+pub fn inspect(bytes: &[u8]) -> Result<Message, MessageError> {
+    read_session_keys(bytes, true)
+}
+
+/// As [`inspect_entry`], for only the start of an entry, such as its first
+/// few kilobytes. The session key packets are read up to the encrypted data,
+/// which need not be there, and the length is theirs alone.
+pub fn inspect_head(bytes: &[u8]) -> Result<Message, MessageError> {
+    if armor::is_armored(bytes) {
+        read_session_keys(&armor::dearmor_head(bytes)?, false)
+    } else {
+        read_session_keys(bytes, false)
+    }
+}
+
+/// Read the session key packets at the start of `bytes` up to the encrypted
+/// data, which is only read past if the `whole` message is there.
+fn read_session_keys(bytes: &[u8], whole: bool) -> Result<Message, MessageError> {
+    if bytes.is_empty() {
+        return Err(MessageError::Empty);
+    }
+    let mut message = Message {
+        recipients: Vec::new(),
+        symmetric: false,
+        len: 0,
+    };
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let header = bytes[offset];
+        let is_data = matches!(tag(header), SED_TAG | SEIPD_TAG | AEAD_TAG);
+        if !whole && header & 0x80 != 0 && is_data {
+            message.len = offset;
+            return Ok(message);
+        }
+        let packet = read_packet(bytes, offset)?;
+        match packet.tag {
+            PKESK_TAG => message.recipients.push(recipient(packet.body)?),
+            SKESK_TAG => message.symmetric = true,
+            MARKER_TAG => {}
+            SED_TAG | SEIPD_TAG | AEAD_TAG => {
+                message.len = packet.end;
+                return Ok(message);
+            }
+            tag => return Err(MessageError::UnexpectedPacket(tag)),
+        }
+        offset = packet.end;
+    }
+    Err(MessageError::NotEncrypted)
+}
+
+/// As [`inspect`], for an entry which may be ASCII armored. The length is
+/// that of the binary message.
+pub fn inspect_entry(bytes: &[u8]) -> Result<Message, MessageError> {
+    if armor::is_armored(bytes) {
+        inspect(&armor::dearmor(bytes)?)
+    } else {
+        inspect(bytes)
+    }
+}
+
+struct Packet<'a> {
+    tag: u8,
+    /// The body, or for packets split into partial lengths its first part.
+    body: &'a [u8],
+    end: usize,
+}
+
+fn read_packet(bytes: &[u8], start: usize) -> Result<Packet<'_>, MessageError> {
+    let header = bytes[start];
+    if header & 0x80 == 0 {
+        return Err(MessageError::InvalidPacket(start));
+    }
+    let tag = tag(header);
+    let mut offset = start + 1;
+    let (body_start, mut end, mut partial) = if header & 0x40 != 0 {
+        let (length, partial) = new_format_length(bytes, &mut offset)?;
+        (offset, offset + length, partial)
+    } else {
+        let length = match header & 0x03 {
+            0 => be_length(bytes, &mut offset, 1)?,
+            1 => be_length(bytes, &mut offset, 2)?,
+            2 => be_length(bytes, &mut offset, 4)?,
+            // An indeterminate length runs to the end of the input.
+            _ => bytes.len() - offset,
+        };
+        (offset, offset + length, false)
+    };
+    if end > bytes.len() {
+        return Err(MessageError::Truncated);
+    }
+    let body = &bytes[body_start..end];
+    while partial {
+        offset = end;
+        let (length, more) = new_format_length(bytes, &mut offset)?;
+        end = offset + length;
+        if end > bytes.len() {
+            return Err(MessageError::Truncated);
+        }
+        partial = more;
+    }
+    Ok(Packet { tag, body, end })
+}
+
+/// The tag of the packet whose header starts with `header`, in either the
+/// new or the old format.
+fn tag(header: u8) -> u8 {
+    if header & 0x40 != 0 {
+        header & 0x3f
+    } else {
+        (header >> 2) & 0x0f
+    }
+}
+
+/// Read a new format length at `offset`, returning it and whether it is a
+/// partial length, so another length follows the bytes it covers.
+fn new_format_length(bytes: &[u8], offset: &mut usize) -> Result<(usize, bool), MessageError> {
+    let first = *bytes.get(*offset).ok_or(MessageError::Truncated)? as usize;
+    *offset += 1;
+    match first {
+        0..=191 => Ok((first, false)),
+        192..=223 => {
+            let second = *bytes.get(*offset).ok_or(MessageError::Truncated)? as usize;
+            *offset += 1;
+            Ok((((first - 192) << 8) + second + 192, false))
+        }
+        255 => Ok((be_length(bytes, offset, 4)?, false)),
+        _ => Ok((1 << (first & 0x1f), true)),
+    }
+}
+
+fn be_length(bytes: &[u8], offset: &mut usize, width: usize) -> Result<usize, MessageError> {
+    let field = bytes
+        .get(*offset..*offset + width)
+        .ok_or(MessageError::Truncated)?;
+    *offset += width;
+    Ok(field
+        .iter()
+        .fold(0, |length, byte| (length << 8) | *byte as usize))
+}
+
+fn recipient(body: &[u8]) -> Result<String, MessageError> {
+    match body.first() {
+        Some(3) => body
+            .get(1..9)
+            .map(|key_id| key_id.iter().map(|byte| format!("{byte:02X}")).collect())
+            .ok_or(MessageError::Truncated),
+        Some(version) => Err(MessageError::UnsupportedVersion(*version)),
+        None => Err(MessageError::Truncated),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{inspect, inspect_entry, inspect_head, Message, MessageError};
+    use crate::armor::{self, ArmorError};
+
+    /// A new format PKESK packet to `0123456789ABCDEF`, then an old format
+    /// SED packet with a one byte length.
+    const MESSAGE: &[u8] = &[
+        0xc1, 0x0b, 0x03, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x12, 0x00, 0xa4, 0x03,
+        0x01, 0x02, 0x03,
+    ];
+
+    #[test]
+    fn should_read_recipients_and_length_of_message() {
+        assert_eq!(
+            inspect(MESSAGE).unwrap(),
+            Message {
+                recipients: vec!["0123456789ABCDEF".to_owned()],
+                symmetric: false,
+                len: MESSAGE.len(),
+            }
+        );
+    }
+
+    #[test]
+    fn length_should_exclude_trailing_bytes() {
+        let mut bytes = MESSAGE.to_vec();
+        bytes.extend_from_slice(b"left over from a longer value");
+        assert_eq!(inspect(&bytes).unwrap().len, MESSAGE.len());
+    }
+
+    #[test]
+    fn should_detect_symmetric_messages() {
+        // A SKESK packet followed by a SEIPD packet split into a partial
+        // length of two bytes and a final length of one.
+        let bytes = [0xc3, 0x02, 0x04, 0x09, 0xd2, 0xe1, 0x01, 0x02, 0x01, 0x03];
+        let message = inspect(&bytes).unwrap();
+        assert!(message.symmetric);
+        assert!(message.recipients.is_empty());
+        assert_eq!(message.len, bytes.len());
+    }
+
+    #[test]
+    fn entries_should_be_inspected_whether_armored_or_not() {
+        let armored = armor::armor(MESSAGE);
+        assert_eq!(inspect_entry(&armored), inspect(MESSAGE));
+        assert_eq!(inspect_entry(MESSAGE), inspect(MESSAGE));
+        assert_eq!(
+            inspect_entry(&armored[..armored.len() - 10]),
+            Err(MessageError::Armor(ArmorError::MissingFooter))
+        );
+    }
+
+    #[test]
+    fn heads_of_entries_should_be_inspected_up_to_the_encrypted_data() {
+        let head = &MESSAGE[..14];
+        let message = inspect_head(head).unwrap();
+        assert_eq!(message.recipients, vec!["0123456789ABCDEF".to_owned()]);
+        assert_eq!(message.len, 13);
+        let armored = armor::armor(MESSAGE);
+        assert_eq!(inspect_head(&armored[..armored.len() - 10]), Ok(message));
+        assert_eq!(inspect_head(&MESSAGE[..5]), Err(MessageError::Truncated));
+        assert_eq!(
+            inspect_head(b"hunter2"),
+            Err(MessageError::InvalidPacket(0))
+        );
+    }
+
+    #[test]
+    fn should_reject_what_is_not_an_encrypted_message() {
+        assert_eq!(inspect(b""), Err(MessageError::Empty));
+        assert_eq!(inspect(b"hunter2"), Err(MessageError::InvalidPacket(0)));
+        assert_eq!(
+            inspect(&MESSAGE[..MESSAGE.len() - 1]),
+            Err(MessageError::Truncated)
+        );
+        assert_eq!(inspect(&MESSAGE[..13]), Err(MessageError::NotEncrypted));
+        // A literal data packet, as in a signed or plain message.
+        assert_eq!(
+            inspect(&[0xcb, 0x01, 0x62]),
+            Err(MessageError::UnexpectedPacket(11))
+        );
+    }
+}
@@ -1,5 +1,21 @@
-use crate::input::HandlerResult;
+use crate::{
+    audit::EntryAudit,
+    fsck::{Issue, Status},
+    grep::GrepMatch,
+    import::ImportReport,
+    input::HandlerResult,
+    keys::KeyInfo,
+    tree::EntryTree,
+};
 use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TREE_ROOT: &str = "Password Store";
+const HIGHLIGHT_START: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+/// Follows the names of entries encrypted with a passphrase rather than to
+/// any key.
+const SYMMETRIC_MARKER: &str = " (passphrase)";
 
 pub fn write_result<W: Write>(
     result: HandlerResult,
@@ -8,8 +24,289 @@ pub fn write_result<W: Write>(
     match result {
         HandlerResult::Initialize() => output.write("Store initialized."),
         HandlerResult::Insert(_) => output.write("Secret saved."),
+        HandlerResult::Browse() | HandlerResult::Run(_) => Ok(()),
         HandlerResult::Retrieve(value) => output.write(value.as_ref()),
+        HandlerResult::List { names, symmetric } => {
+            let names = names
+                .into_iter()
+                .map(|name| {
+                    if symmetric.contains(&name) {
+                        format!("{name}{SYMMETRIC_MARKER}")
+                    } else {
+                        name
+                    }
+                })
+                .collect::<Vec<_>>();
+            write_tree(&names, &mut output)
+        }
+        HandlerResult::Find(names) => write_tree(&names, &mut output),
+        HandlerResult::Grep { matches, failed } => {
+            write_grep_matches(&matches, &mut output)?;
+            if !failed.is_empty() {
+                output.write(&format!("Failed to search {} entries:\n", failed.len()))?;
+                for failure in &failed {
+                    output.write(&format!("    {}: {}\n", failure.name, failure.reason))?;
+                }
+            }
+            Ok(())
+        }
+        HandlerResult::Render(path) => output.write(&format!("Rendered {path}.")),
+        HandlerResult::RetrieveFile(path) => output.write(&format!("Decrypted to {path}.")),
+        HandlerResult::EnvExport(lines) => {
+            for line in &lines {
+                output.write(line)?;
+                output.write("\n")?;
+            }
+            Ok(())
+        }
+        HandlerResult::EnvImport(names) => {
+            output.write(&format!("Imported {} entries.", names.len()))
+        }
+        HandlerResult::Import(report) => write_import_report(&report, &mut output),
+        HandlerResult::BackupCreate { path, entries } => {
+            output.write(&format!("Backed up {entries} entries to {path}."))
+        }
+        HandlerResult::BackupRestore(entries) => {
+            output.write(&format!("Restored {entries} entries."))
+        }
+        HandlerResult::CheckTemplate(count) => {
+            output.write(&format!("All {count} references resolve."))
+        }
+        HandlerResult::Fsck(issues) => write_fsck_issues(&issues, &mut output),
+        HandlerResult::AuditRecipients {
+            entries,
+            json: true,
+        } => output.write(&format!("{}\n", serde_json::to_string_pretty(&entries)?)),
+        HandlerResult::AuditRecipients { entries, .. } => write_audit_table(&entries, &mut output),
+        HandlerResult::KeysList(keys) => write_keys_table(&keys, &mut output),
+        HandlerResult::KeysImport(fingerprints) => {
+            output.write(&format!("Imported {} keys:\n", fingerprints.len()))?;
+            for fingerprint in &fingerprints {
+                output.write(&format!("    {fingerprint}\n"))?;
+            }
+            Ok(())
+        }
+        HandlerResult::KeysExport(key) => output.write(&key),
+        HandlerResult::KeysRotate { folders, entries } => output.write(&format!(
+            "Replaced the key in {folders} .gpg-id files and re-encrypted {entries} entries."
+        )),
+        HandlerResult::Convert { encoding, entries } => output.write(&format!(
+            "Converted {entries} entries to {encoding} messages."
+        )),
+    }
+}
+
+/// Write entry names as a tree.
+fn write_tree<W: Write>(names: &[String], output: &mut TerminalOutput<W>) -> anyhow::Result<()> {
+    for line in EntryTree::new(names).render(TREE_ROOT) {
+        output.write(&line)?;
+        output.write("\n")?;
+    }
+    Ok(())
+}
+
+/// Write each matching entry name followed by its matching lines, with the
+/// matched text highlighted. Lines are written piece by piece so plaintext is
+/// never copied outside of a zeroized buffer.
+fn write_grep_matches<W: Write>(
+    matches: &[GrepMatch],
+    output: &mut TerminalOutput<W>,
+) -> anyhow::Result<()> {
+    for found in matches {
+        output.write(&found.name)?;
+        output.write(":\n")?;
+        for matched in &found.lines {
+            let line = &*matched.line;
+            let mut position = 0;
+            output.write("    ")?;
+            for range in &matched.ranges {
+                output.write(&line[position..range.start])?;
+                output.write(HIGHLIGHT_START)?;
+                output.write(&line[range.clone()])?;
+                output.write(HIGHLIGHT_END)?;
+                position = range.end;
+            }
+            output.write(&line[position..])?;
+            output.write("\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write how many entries were, or in a dry run would be, imported followed
+/// by the entries which were skipped, renamed or failed.
+fn write_import_report<W: Write>(
+    report: &ImportReport,
+    output: &mut TerminalOutput<W>,
+) -> anyhow::Result<()> {
+    let verb = if report.dry_run {
+        "Would import"
+    } else {
+        "Imported"
+    };
+    output.write(&format!("{verb} {} entries.\n", report.imported.len()))?;
+    if !report.skipped.is_empty() {
+        output.write(&format!(
+            "Skipped {} existing entries:\n",
+            report.skipped.len()
+        ))?;
+        for name in &report.skipped {
+            output.write(&format!("    {name}\n"))?;
+        }
+    }
+    if !report.renamed.is_empty() {
+        output.write(&format!("Renamed {} entries:\n", report.renamed.len()))?;
+        for (name, renamed) in &report.renamed {
+            output.write(&format!("    {name} -> {renamed}\n"))?;
+        }
+    }
+    if !report.failed.is_empty() {
+        output.write(&format!(
+            "Failed to import {} entries:\n",
+            report.failed.len()
+        ))?;
+        for failure in &report.failed {
+            output.write(&format!("    {}: {}\n", failure.name, failure.reason))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write each problem found in the store, and whether it was repaired,
+/// followed by a count of both.
+fn write_fsck_issues<W: Write>(
+    issues: &[Issue],
+    output: &mut TerminalOutput<W>,
+) -> anyhow::Result<()> {
+    if issues.is_empty() {
+        return output.write("No problems found.");
+    }
+    for issue in issues {
+        output.write(&format!("{}: {}", issue.path, issue.problem))?;
+        match &issue.status {
+            Status::Found => output.write("\n")?,
+            Status::Repaired => output.write(" (repaired)\n")?,
+            Status::RepairFailed(reason) => {
+                output.write(&format!(" (repair failed: {reason})\n"))?
+            }
+        }
+    }
+    let repaired = issues
+        .iter()
+        .filter(|issue| issue.status == Status::Repaired)
+        .count();
+    output.write(&format!(
+        "Found {} problems, repaired {repaired}.",
+        issues.len()
+    ))
+}
+
+/// Write a row per recipient of each entry, with the entry's name on its
+/// first row, followed by anything wrong with the entry's recipients.
+fn write_audit_table<W: Write>(
+    entries: &[EntryAudit],
+    output: &mut TerminalOutput<W>,
+) -> anyhow::Result<()> {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.len())
+        .chain(Some("ENTRY".len()))
+        .max()
+        .unwrap_or_default();
+    let key_id_width = entries
+        .iter()
+        .flat_map(|entry| &entry.recipients)
+        .map(|recipient| recipient.key_id.len())
+        .chain(Some("KEY ID".len()))
+        .max()
+        .unwrap_or_default();
+    let row = |name: &str, key_id: &str, status: &str, user_id: &str| {
+        let row = format!("{name:name_width$}  {key_id:key_id_width$}  {status:7}  {user_id}");
+        format!("{}\n", row.trim_end())
+    };
+    output.write(&row("ENTRY", "KEY ID", "STATUS", "USER ID"))?;
+    for entry in entries {
+        if entry.recipients.is_empty() {
+            output.write(&row(&entry.name, "", "", ""))?;
+        }
+        for (i, recipient) in entry.recipients.iter().enumerate() {
+            let name = if i == 0 { entry.name.as_str() } else { "" };
+            output.write(&row(
+                name,
+                &recipient.key_id,
+                &recipient.status.to_string(),
+                recipient.user_id.as_deref().unwrap_or_default(),
+            ))?;
+        }
+        if let Some(error) = &entry.error {
+            output.write(&format!("    {error}\n"))?;
+        }
+        if !entry.missing.is_empty() {
+            output.write(&format!(
+                "    not encrypted to {}\n",
+                entry.missing.join(", ")
+            ))?;
+        }
+        if !entry.unexpected.is_empty() {
+            output.write(&format!(
+                "    encrypted to keys not in its .gpg-id file, {}\n",
+                entry.unexpected.join(", ")
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a row for each key, with when it expires and how far it is
+/// trusted.
+fn write_keys_table<W: Write>(
+    keys: &[KeyInfo],
+    output: &mut TerminalOutput<W>,
+) -> anyhow::Result<()> {
+    let fingerprint_width = keys
+        .iter()
+        .map(|key| key.fingerprint.len())
+        .chain(Some("FINGERPRINT".len()))
+        .max()
+        .unwrap_or_default();
+    let row = |fingerprint: &str, expires: &str, trust: &str, user_id: &str| {
+        let row = format!("{fingerprint:fingerprint_width$}  {expires:10}  {trust:8}  {user_id}");
+        format!("{}\n", row.trim_end())
+    };
+    output.write(&row("FINGERPRINT", "EXPIRES", "TRUST", "USER ID"))?;
+    for key in keys {
+        output.write(&row(
+            &key.fingerprint,
+            &key.expires.map_or_else(|| "never".to_owned(), format_date),
+            &key.trust.to_string(),
+            key.user_id.as_deref().unwrap_or_default(),
+        ))?;
     }
+    Ok(())
+}
+
+/// Format `time` as a `YYYY-MM-DD` date in UTC.
+fn format_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86_400) as i64;
+    // Converts days since the epoch to a civil date, from Howard Hinnant's
+    // `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
 }
 
 pub struct TerminalOutput<W> {
@@ -29,10 +326,16 @@ impl<W: Write> TerminalOutput<W> {
 #[cfg(test)]
 mod test {
     use crate::{
+        audit::{EntryAudit, KeyStatus, Recipient},
+        fsck::{Issue, Problem, Status},
+        grep::{GrepFailure, GrepMatch, MatchedLine},
+        import::ImportReport,
         input::HandlerResult,
-        output::{write_result, TerminalOutput},
+        keys::{KeyInfo, Trust},
+        output::{format_date, write_result, TerminalOutput},
         secrets::ZeroizedString,
     };
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn result_of_insert_should_write_success_if_secret_saved() {
@@ -64,4 +367,230 @@ mod test {
         let message = std::str::from_utf8(&buf).unwrap();
         assert_eq!(message, "Store initialized.");
     }
+
+    #[test]
+    fn result_of_grep_should_write_names_and_highlighted_lines() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::Grep {
+            matches: vec![GrepMatch {
+                name: "prod/db".to_owned(),
+                lines: vec![MatchedLine {
+                    line: ZeroizedString::new("host: db.local".to_owned()),
+                    ranges: vec![0..4, 6..8],
+                }],
+            }],
+            failed: vec![GrepFailure {
+                name: "prod/api".to_owned(),
+                reason: "decryption failed".to_owned(),
+            }],
+        };
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "prod/db:\n    \x1b[1;31mhost\x1b[0m: \x1b[1;31mdb\x1b[0m.local\n\
+             Failed to search 1 entries:\n    prod/api: decryption failed\n"
+        );
+    }
+
+    #[test]
+    fn result_of_list_should_write_names_as_tree() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::List {
+            names: vec!["api".to_owned(), "prod/db".to_owned()],
+            symmetric: Vec::new(),
+        };
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(message, "Password Store\n├── api\n└── prod\n    └── db\n");
+    }
+
+    #[test]
+    fn result_of_list_should_mark_symmetric_entries() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::List {
+            names: vec!["api".to_owned(), "shared/vpn".to_owned()],
+            symmetric: vec!["shared/vpn".to_owned()],
+        };
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "Password Store\n├── api\n└── shared\n    └── vpn (passphrase)\n"
+        );
+    }
+
+    #[test]
+    fn result_of_render_should_write_output_path() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::Render("app.conf".to_owned());
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(message, "Rendered app.conf.");
+    }
+
+    #[test]
+    fn result_of_env_export_should_write_one_line_per_variable() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::EnvExport(vec![
+            ZeroizedString::new("DB_USER=admin".to_owned()),
+            ZeroizedString::new("DB_PASS=hunter2".to_owned()),
+        ]);
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(message, "DB_USER=admin\nDB_PASS=hunter2\n");
+    }
+
+    #[test]
+    fn result_of_backup_create_should_write_entry_count_and_path() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::BackupCreate {
+            path: "backup.tar.gpg".to_owned(),
+            entries: 3,
+        };
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(message, "Backed up 3 entries to backup.tar.gpg.");
+    }
+
+    #[test]
+    fn result_of_fsck_should_write_each_issue_and_whether_it_was_repaired() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let mut trailing = Issue::new("api.gpg", Problem::TrailingData(9));
+        trailing.status = Status::Repaired;
+        let issues = vec![trailing, Issue::new("notes.txt", Problem::StrayFile)];
+        write_result(HandlerResult::Fsck(issues), output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "api.gpg: has 9 bytes of trailing data (repaired)\n\
+             notes.txt: is not an entry or .gpg-id file\n\
+             Found 2 problems, repaired 1."
+        );
+    }
+
+    fn audited_entries() -> Vec<EntryAudit> {
+        vec![
+            EntryAudit {
+                name: "api".to_owned(),
+                expected: vec!["alice@example.com".to_owned()],
+                recipients: vec![Recipient {
+                    key_id: "A1A1A1A1A1A1A1A1".to_owned(),
+                    user_id: Some("Alice <alice@example.com>".to_owned()),
+                    status: KeyStatus::Valid,
+                }],
+                ..EntryAudit::default()
+            },
+            EntryAudit {
+                name: "prod/db".to_owned(),
+                expected: vec!["bob@example.com".to_owned()],
+                recipients: vec![Recipient {
+                    key_id: "C1C1C1C1C1C1C1C1".to_owned(),
+                    user_id: None,
+                    status: KeyStatus::Expired,
+                }],
+                missing: vec!["bob@example.com".to_owned()],
+                unexpected: vec!["C1C1C1C1C1C1C1C1".to_owned()],
+                ..EntryAudit::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn result_of_audit_should_write_a_row_per_recipient_and_any_mismatches() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::AuditRecipients {
+            entries: audited_entries(),
+            json: false,
+        };
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "ENTRY    KEY ID            STATUS   USER ID\n\
+             api      A1A1A1A1A1A1A1A1  valid    Alice <alice@example.com>\n\
+             prod/db  C1C1C1C1C1C1C1C1  expired\n\
+             \x20   not encrypted to bob@example.com\n\
+             \x20   encrypted to keys not in its .gpg-id file, C1C1C1C1C1C1C1C1\n"
+        );
+    }
+
+    #[test]
+    fn result_of_audit_as_json_should_list_each_entry() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::AuditRecipients {
+            entries: audited_entries(),
+            json: true,
+        };
+        write_result(result, output).unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(report[1]["name"], "prod/db");
+        assert_eq!(report[1]["recipients"][0]["status"], "expired");
+        assert_eq!(report[1]["missing"][0], "bob@example.com");
+    }
+
+    #[test]
+    fn result_of_import_should_write_summary_and_failures() {
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let mut report = ImportReport {
+            dry_run: true,
+            imported: vec!["api".to_owned(), "db-1".to_owned()],
+            skipped: vec!["web".to_owned()],
+            renamed: vec![("db".to_owned(), "db-1".to_owned())],
+            ..ImportReport::default()
+        };
+        report.fail("prod/db", "could not be decrypted");
+        write_result(HandlerResult::Import(report), output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "Would import 2 entries.\n\
+             Skipped 1 existing entries:\n    web\n\
+             Renamed 1 entries:\n    db -> db-1\n\
+             Failed to import 1 entries:\n    prod/db: could not be decrypted\n"
+        );
+    }
+
+    #[test]
+    fn result_of_keys_list_should_write_expiry_and_trust_of_each_key() {
+        let key = |fingerprint: &str, expires: Option<u64>, trust: Trust| KeyInfo {
+            fingerprint: fingerprint.to_owned(),
+            user_id: Some(format!("{fingerprint} <{fingerprint}@example.com>")),
+            expires: expires.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            expired: false,
+            revoked: false,
+            trust,
+        };
+        let mut buf = Vec::new();
+        let output = TerminalOutput::new(&mut buf);
+        let result = HandlerResult::KeysList(vec![
+            key("ALICE", Some(1_735_689_600), Trust::Ultimate),
+            key("BOB", None, Trust::Unknown),
+        ]);
+        write_result(result, output).unwrap();
+        let message = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(
+            message,
+            "FINGERPRINT  EXPIRES     TRUST     USER ID\n\
+             ALICE        2025-01-01  ultimate  ALICE <ALICE@example.com>\n\
+             BOB          never       unknown   BOB <BOB@example.com>\n"
+        );
+    }
+
+    #[test]
+    fn dates_should_be_formatted_in_utc() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format_date(leap_day), "2000-02-29");
+    }
 }
@@ -0,0 +1,307 @@
+use crate::fuzzy;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+
+/// The result of handling a single key press in the [`Picker`].
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pending,
+    Selected(String),
+    Cancelled,
+}
+
+/// Interactive fuzzy finder state over a list of entry names.
+///
+/// Typing filters the names incrementally, ranked by [`fuzzy::score`], the
+/// arrow keys move the selection and enter picks the selected name.
+pub struct Picker {
+    names: Vec<String>,
+    query: String,
+    matches: Vec<usize>,
+    selected: usize,
+    /// The index of the first match shown, so the selection stays on screen.
+    offset: usize,
+}
+
+impl Picker {
+    pub fn new(names: Vec<String>) -> Self {
+        let mut picker = Self {
+            names,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            offset: 0,
+        };
+        picker.filter();
+        picker
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// The index of the first match to show in `rows` rows, scrolled just
+    /// far enough to keep the selection in view.
+    pub fn scroll(&mut self, rows: usize) -> usize {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if rows > 0 && self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+        self.offset
+    }
+
+    /// The names matching the current query, best match first.
+    pub fn matches(&self) -> impl Iterator<Item = &str> {
+        self.matches.iter().map(|&i| self.names[i].as_str())
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Outcome::Cancelled,
+            KeyCode::Char('c') if ctrl => return Outcome::Cancelled,
+            KeyCode::Enter => {
+                if let Some(&i) = self.matches.get(self.selected) {
+                    return Outcome::Selected(self.names[i].clone());
+                }
+            }
+            KeyCode::Up => self.move_up(),
+            KeyCode::Char('p') if ctrl => self.move_up(),
+            KeyCode::Down => self.move_down(),
+            KeyCode::Char('n') if ctrl => self.move_down(),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+        Outcome::Pending
+    }
+
+    fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    fn move_down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    fn filter(&mut self) {
+        let mut scored = self
+            .names
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| fuzzy::score(&self.query, name).map(|score| (score, i)))
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, i), (b, j)| b.cmp(a).then(i.cmp(j)));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.offset = 0;
+    }
+}
+
+/// Run the [`Picker`] on the terminal, drawing to stderr so stdout is left
+/// free for the retrieved secret. Returns [`None`] if the user cancelled.
+pub fn pick(names: Vec<String>) -> anyhow::Result<Option<String>> {
+    let mut picker = Picker::new(names);
+    let _screen = RawScreen::enter(io::stderr())?;
+    run(&mut picker, &mut io::stderr())
+}
+
+/// Raw mode and the alternate screen of `out`, for as long as this lives.
+/// The terminal is restored on drop, so an error part way through drawing
+/// does not leave it in raw mode.
+pub struct RawScreen<W: Write> {
+    out: W,
+}
+
+impl<W: Write> RawScreen<W> {
+    pub fn enter(out: W) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut screen = Self { out };
+        execute!(screen.out, EnterAlternateScreen, Hide)?;
+        Ok(screen)
+    }
+}
+
+impl<W: Write> Drop for RawScreen<W> {
+    fn drop(&mut self) {
+        // Nothing more can be done if the terminal cannot be restored.
+        let _ = execute!(self.out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run<W: Write>(picker: &mut Picker, out: &mut W) -> anyhow::Result<Option<String>> {
+    loop {
+        draw(picker, out)?;
+        if let Event::Key(key) = event::read()? {
+            match picker.handle_key(key) {
+                Outcome::Pending => {}
+                Outcome::Selected(name) => return Ok(Some(name)),
+                Outcome::Cancelled => return Ok(None),
+            }
+        }
+    }
+}
+
+fn draw<W: Write>(picker: &mut Picker, out: &mut W) -> anyhow::Result<()> {
+    let (_, rows) = terminal::size()?;
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Clear(ClearType::All),
+        Print(format!("> {}", picker.query()))
+    )?;
+    let visible = rows.saturating_sub(1) as usize;
+    let offset = picker.scroll(visible);
+    let selected = picker.selected();
+    for (row, (i, name)) in picker
+        .matches()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .enumerate()
+    {
+        queue!(out, MoveTo(0, row as u16 + 1))?;
+        if i == selected {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(name),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(name))?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Outcome, Picker};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_query(picker: &mut Picker, query: &str) {
+        for c in query.chars() {
+            assert_eq!(picker.handle_key(key(KeyCode::Char(c))), Outcome::Pending);
+        }
+    }
+
+    fn picker() -> Picker {
+        Picker::new(vec![
+            "api".to_owned(),
+            "dev/db".to_owned(),
+            "prod/db".to_owned(),
+        ])
+    }
+
+    #[test]
+    fn should_show_all_names_for_empty_query() {
+        let picker = picker();
+        assert_eq!(
+            picker.matches().collect::<Vec<_>>(),
+            vec!["api", "dev/db", "prod/db"]
+        );
+    }
+
+    #[test]
+    fn typing_should_filter_names_incrementally() {
+        let mut picker = picker();
+        type_query(&mut picker, "db");
+        assert_eq!(
+            picker.matches().collect::<Vec<_>>(),
+            vec!["dev/db", "prod/db"]
+        );
+        type_query(&mut picker, "p");
+        assert!(picker.matches().next().is_none());
+    }
+
+    #[test]
+    fn backspace_should_widen_the_filter() {
+        let mut picker = picker();
+        type_query(&mut picker, "pr");
+        assert_eq!(picker.matches().collect::<Vec<_>>(), vec!["prod/db"]);
+        picker.handle_key(key(KeyCode::Backspace));
+        assert_eq!(picker.query(), "p");
+        assert_eq!(picker.matches().count(), 2);
+    }
+
+    #[test]
+    fn enter_should_select_the_best_match() {
+        let mut picker = picker();
+        type_query(&mut picker, "prod");
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Enter)),
+            Outcome::Selected("prod/db".to_owned())
+        );
+    }
+
+    #[test]
+    fn arrow_keys_should_move_the_selection() {
+        let mut picker = picker();
+        picker.handle_key(key(KeyCode::Down));
+        picker.handle_key(key(KeyCode::Down));
+        picker.handle_key(key(KeyCode::Down));
+        assert_eq!(picker.selected(), 2);
+        picker.handle_key(key(KeyCode::Up));
+        assert_eq!(
+            picker.handle_key(key(KeyCode::Enter)),
+            Outcome::Selected("dev/db".to_owned())
+        );
+    }
+
+    #[test]
+    fn scroll_should_follow_the_selection_past_the_last_visible_row() {
+        let mut picker = picker();
+        assert_eq!(picker.scroll(2), 0);
+        picker.handle_key(key(KeyCode::Down));
+        assert_eq!(picker.scroll(2), 0);
+        picker.handle_key(key(KeyCode::Down));
+        assert_eq!(picker.scroll(2), 1);
+        picker.handle_key(key(KeyCode::Up));
+        assert_eq!(picker.scroll(2), 1);
+        picker.handle_key(key(KeyCode::Up));
+        assert_eq!(picker.scroll(2), 0);
+        type_query(&mut picker, "d");
+        assert_eq!(picker.scroll(2), 0);
+    }
+
+    #[test]
+    fn enter_should_do_nothing_if_nothing_matches() {
+        let mut picker = picker();
+        type_query(&mut picker, "zzz");
+        assert_eq!(picker.handle_key(key(KeyCode::Enter)), Outcome::Pending);
+    }
+
+    #[test]
+    fn escape_and_ctrl_c_should_cancel() {
+        let mut picker = picker();
+        assert_eq!(picker.handle_key(key(KeyCode::Esc)), Outcome::Cancelled);
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(picker.handle_key(ctrl_c), Outcome::Cancelled);
+    }
+}
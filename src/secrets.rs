@@ -1,10 +1,23 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    ops::{Deref, DerefMut},
+    os::unix::io::RawFd,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+};
 
 use anyhow::Context;
-use zeroize::ZeroizeOnDrop;
+use thiserror::Error;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A secret which is not UTF-8 text, such as the contents of a binary file.
+#[derive(Error, Debug)]
+#[error("the secret is not UTF-8 text")]
+pub struct NotUtf8Error;
 
 /// [`String`] whose memory is zeroed out when dropped.
-#[derive(Clone, PartialEq, ZeroizeOnDrop)]
+#[derive(Clone, Default, PartialEq, ZeroizeOnDrop)]
 pub struct ZeroizedString(String);
 
 impl ZeroizedString {
@@ -30,12 +43,16 @@ impl ZeroizedByteVec {
         Self(inner)
     }
 
-    pub fn into_zeroized_string(self) -> ZeroizedString {
-        ZeroizedString::new(
-            std::str::from_utf8(self.0.clone().as_ref())
-                .expect("only utf-8 secrets are supported")
-                .to_string(),
-        )
+    /// The secret as text, unless it is not UTF-8. Either way no copy of it
+    /// is left behind.
+    pub fn into_zeroized_string(mut self) -> Result<ZeroizedString, NotUtf8Error> {
+        match String::from_utf8(std::mem::take(&mut self.0)) {
+            Ok(text) => Ok(ZeroizedString::new(text)),
+            Err(e) => {
+                e.into_bytes().zeroize();
+                Err(NotUtf8Error)
+            }
+        }
     }
 }
 
@@ -77,9 +94,98 @@ impl SecretReader for StdinSecretReader {
     }
 }
 
+/// Reads a passphrase from the terminal without echoing it back, after
+/// showing the given prompt.
+pub struct PassphrasePrompt(pub &'static str);
+
+impl SecretReader for PassphrasePrompt {
+    fn read_secret(&self) -> anyhow::Result<ZeroizedByteVec> {
+        Ok(ZeroizedByteVec::new(
+            rpassword::prompt_password(self.0)
+                .with_context(|| "failed to read the passphrase")?
+                .into_bytes(),
+        ))
+    }
+}
+
+/// Reads a secret from each line of an open file descriptor in turn, as with
+/// `gpg --passphrase-fd`. Clones read from the same descriptor, each secret
+/// carrying on from where the last one stopped.
+#[derive(Clone)]
+pub struct FdSecretReader {
+    fd: RawFd,
+    /// The descriptor, opened by the first read and kept, so lines already
+    /// buffered are not lost.
+    reader: Arc<Mutex<Option<BufReader<File>>>>,
+}
+
+impl FdSecretReader {
+    pub fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            reader: Arc::default(),
+        }
+    }
+}
+
+impl SecretReader for FdSecretReader {
+    fn read_secret(&self) -> anyhow::Result<ZeroizedByteVec> {
+        let fd = self.fd;
+        let mut reader = self.reader.lock().unwrap_or_else(PoisonError::into_inner);
+        if reader.is_none() {
+            let file = File::open(format!("/dev/fd/{fd}"))
+                .with_context(|| format!("failed to open file descriptor {fd}"))?;
+            *reader = Some(BufReader::new(file));
+        }
+        let mut line = ZeroizedByteVec::new(Vec::new());
+        reader
+            .as_mut()
+            .expect("the descriptor was opened above")
+            .read_until(b'\n', &mut line)
+            .with_context(|| format!("failed to read from file descriptor {fd}"))?;
+        while line
+            .last()
+            .is_some_and(|&byte| byte == b'\n' || byte == b'\r')
+        {
+            line.pop();
+        }
+        Ok(line)
+    }
+}
+
+/// How the passphrase of a secret key is asked for when it is needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Pinentry {
+    /// Leave it to the pinentry gpg-agent is configured with.
+    #[default]
+    Ask,
+    /// Read it ourselves and hand it to gpg-agent, for machines without a
+    /// pinentry, such as headless servers.
+    Loopback,
+    /// Never ask, so only keys without a passphrase, or whose passphrase
+    /// gpg-agent has cached, can be used.
+    Cancel,
+}
+
+impl FromStr for Pinentry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ask" => Ok(Self::Ask),
+            "loopback" => Ok(Self::Loopback),
+            "cancel" => Ok(Self::Cancel),
+            _ => Err(format!(
+                "expected one of `ask`, `loopback` or `cancel`, got `{s}`"
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::ZeroizedByteVec;
+    use super::{FdSecretReader, Pinentry, SecretReader, ZeroizedByteVec};
+    use std::{io::Write, os::unix::io::AsRawFd};
 
     #[test]
     fn deref_for_zerozed_byte_vec_should_return_a_ref_to_the_inner_vec() {
@@ -87,4 +193,29 @@ mod test {
         let zeroized = ZeroizedByteVec::new(vec.clone());
         assert_eq!(vec, *zeroized);
     }
+
+    #[test]
+    fn fd_secret_reader_should_read_the_first_line() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"abc\r\nrest\n").unwrap();
+        let secret = FdSecretReader::new(file.as_raw_fd()).read_secret().unwrap();
+        assert_eq!(*secret, b"abc");
+    }
+
+    #[test]
+    fn fd_secret_reader_should_read_the_next_line_each_time() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"first\nsecond\n").unwrap();
+        let reader = FdSecretReader::new(file.as_raw_fd());
+        assert_eq!(*reader.read_secret().unwrap(), b"first");
+        // A clone carries on from the same place.
+        assert_eq!(*reader.clone().read_secret().unwrap(), b"second");
+        assert!(reader.read_secret().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pinentry_should_parse_from_its_name() {
+        assert_eq!("loopback".parse(), Ok(Pinentry::Loopback));
+        assert!("never".parse::<Pinentry>().is_err());
+    }
 }
@@ -1,72 +1,477 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufWriter, Read, Write},
+    fs::File,
+    io::{self, BufWriter, Read, Write},
     path::Path,
 };
 use thiserror::Error;
 
+use crate::{
+    armor, fs,
+    input::{GPG_ID_LIST_FILE, GPG_ID_SIGNATURE_FILE},
+};
+
+/// The extension of entries which are ASCII armored OpenPGP messages.
+pub const ARMORED_ENTRY_EXTENSION: &str = ".asc";
+
 #[derive(Error, Debug)]
 pub enum StoreError {
     #[error(r#"The entry "{0}" does not exist!"#)]
     EntryDoesNotExist(String),
     #[error("Encountered IO error when interacting with the filesystem")]
     IoError(#[from] std::io::Error),
+    #[error("The store is not initialized, {0}. Run `sec initialize <key-id>` to set it up.")]
+    NotInitialized(String),
+    #[error("`{0}` is not a valid entry name, names are paths within the store without empty, `.` or `..` parts.")]
+    InvalidName(String),
+}
+
+/// Check `name` names an entry, or folder, inside the store, so it can never
+/// be used to read or write outside of it.
+pub fn validate_name(name: &str) -> Result<(), StoreError> {
+    let is_valid = !name.is_empty() && name.split('/').all(|part| !matches!(part, "" | "." | ".."));
+    if is_valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidName(name.to_owned()))
+    }
 }
 
 pub trait Store {
+    /// Reads the value of an entry, see [`Store::reader`].
+    type Reader: Read + Send;
+    /// Writes a new value of an entry, see [`Store::writer`].
+    type Writer: Write + Send;
+
     fn insert<S: AsRef<str>>(&self, name: S, value: &[u8]) -> Result<(), StoreError>;
     fn get<S: AsRef<str>>(&self, name: S) -> Result<Vec<u8>, StoreError>;
+    /// A handle to read the value of the entry `name` through, so a large
+    /// value need not be held in memory.
+    fn reader<S: AsRef<str>>(&self, name: S) -> Result<Self::Reader, StoreError>;
+    /// A handle to write a new value of the entry `name` through. The entry
+    /// is only replaced once the handle is given to [`Store::commit`], so a
+    /// value which fails part way through is never seen.
+    fn writer<S: AsRef<str>>(&self, name: S) -> Result<Self::Writer, StoreError>;
+    /// Replace the value of the entry with what was written to `writer`.
+    fn commit(&self, writer: Self::Writer) -> Result<(), StoreError>;
+    /// The key ids listed in the store's `.gpg-id` file.
+    fn key_ids(&self) -> Result<Vec<String>, StoreError>;
+    /// The key ids listed in the `.gpg-id` file of `folder` itself, if it
+    /// has one.
+    fn folder_key_ids<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<String>>, StoreError>;
+    /// The folders which have their own `.gpg-id` file, sorted. The store's
+    /// own `.gpg-id` file is not included.
+    fn key_id_folders(&self) -> Result<Vec<String>, StoreError>;
+    /// Write a `.gpg-id` file listing `key_ids` into `folder`, an empty
+    /// folder being the store itself. Only tests write key ids as is, the
+    /// handler writes them resolved with [`Store::set_key_id_file`].
+    #[cfg(test)]
+    fn set_folder_key_ids<S: AsRef<str>>(
+        &self,
+        folder: S,
+        key_ids: &[String],
+    ) -> Result<(), StoreError> {
+        let mut contents = key_ids.join("\n");
+        contents.push('\n');
+        self.set_key_id_file(folder, contents.as_bytes())
+    }
+    /// Write the `.gpg-id` file of `folder` as is, comments and all.
+    fn set_key_id_file<S: AsRef<str>>(&self, folder: S, contents: &[u8]) -> Result<(), StoreError>;
+    /// The contents of the `.gpg-id` file of `folder` itself, as written,
+    /// an empty folder being the store itself.
+    fn key_id_file<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<u8>>, StoreError>;
+    /// The detached signature of the `.gpg-id` file of `folder`, if it has
+    /// one.
+    fn key_id_signature<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<u8>>, StoreError>;
+    /// Write the detached signature of the `.gpg-id` file of `folder`.
+    fn set_key_id_signature<S: AsRef<str>>(
+        &self,
+        folder: S,
+        signature: &[u8],
+    ) -> Result<(), StoreError>;
+    /// The folder whose `.gpg-id` file applies to the entry `name`. As with
+    /// `pass`, this is the nearest folder with its own, falling back to the
+    /// store itself as an empty folder.
+    fn key_id_folder<S: AsRef<str>>(&self, name: S) -> Result<String, StoreError> {
+        let mut folder = name.as_ref();
+        while let Some((parent, _)) = folder.rsplit_once('/') {
+            if self.folder_key_ids(parent)?.is_some() {
+                return Ok(parent.to_owned());
+            }
+            folder = parent;
+        }
+        Ok(String::new())
+    }
+    /// The key ids the entry `name` is encrypted to, from the `.gpg-id` file
+    /// of its [`Store::key_id_folder`].
+    fn recipients<S: AsRef<str>>(&self, name: S) -> Result<Vec<String>, StoreError> {
+        match self.key_id_folder(name)?.as_str() {
+            "" => self.key_ids(),
+            folder => Ok(self.folder_key_ids(folder)?.unwrap_or_default()),
+        }
+    }
+    /// The directory the store keeps its files in, if it keeps them on disk.
+    fn root(&self) -> Option<&str> {
+        None
+    }
+    /// The names of all entries in the store, sorted.
+    fn list(&self) -> Result<Vec<String>, StoreError>;
+    fn remove<S: AsRef<str>>(&self, name: S) -> Result<(), StoreError>;
 }
 
+/// A store of entries as files under `base_dir`. Entries which are ASCII
+/// armored OpenPGP messages are saved as `.asc` files rather than `.gpg`
+/// files, and either is read as the entry.
 pub struct OnDiskStore {
     base_dir: String,
+    entry_extension: &'static str,
+    /// The extension of ASCII armored entries, if they are told apart.
+    armored_extension: Option<&'static str>,
+    recipients_file: &'static str,
 }
 
 impl OnDiskStore {
-    const GPG_FILE_POSTFIX: &'static str = ".gpg";
-
     pub fn new<S: Into<String>>(base_dir: S) -> Self {
         Self {
             base_dir: base_dir.into(),
+            entry_extension: ".gpg",
+            armored_extension: Some(ARMORED_ENTRY_EXTENSION),
+            recipients_file: GPG_ID_LIST_FILE,
         }
     }
 
-    fn build_entry_path<S: AsRef<str>>(&self, name: S) -> String {
-        format!(
-            "{}/{}{}",
-            self.base_dir,
-            name.as_ref(),
-            Self::GPG_FILE_POSTFIX
-        )
+    /// Name entry files with `entry_extension` and read recipients from
+    /// `recipients_file`, rather than `.gpg` and `.gpg-id`. Armored entries
+    /// are not told apart, as they are only for OpenPGP stores.
+    pub fn with_file_names(
+        mut self,
+        entry_extension: &'static str,
+        recipients_file: &'static str,
+    ) -> Self {
+        self.entry_extension = entry_extension;
+        self.armored_extension = None;
+        self.recipients_file = recipients_file;
+        self
+    }
+
+    /// The extensions an entry file can have.
+    fn entry_extensions(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.entry_extension).chain(self.armored_extension)
+    }
+
+    /// The extension of the file an entry holding `value` is saved as.
+    fn extension_of(&self, value: &[u8]) -> &'static str {
+        match self.armored_extension {
+            Some(extension) if armor::is_armored(value) => extension,
+            _ => self.entry_extension,
+        }
+    }
+
+    /// The path of the file of the entry `name`, in whichever format it
+    /// was saved.
+    fn find_entry_path(&self, name: &str) -> Result<Option<String>, StoreError> {
+        validate_name(name)?;
+        Ok(self
+            .entry_extensions()
+            .map(|extension| self.build_entry_path(name, extension))
+            .find(|path| Path::new(path).is_file()))
+    }
+
+    fn collect_entries(&self, dir: &Path, entries: &mut Vec<String>) -> Result<(), StoreError> {
+        for dir_entry in std::fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let is_visible = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .filter(|name| !name.starts_with('.'))
+                .is_some();
+            if !is_visible {
+                continue;
+            }
+            if path.is_dir() {
+                self.collect_entries(&path, entries)?;
+            } else if let Some(name) = self.entry_name(&path) {
+                entries.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_key_id_folders(
+        &self,
+        dir: &Path,
+        folders: &mut Vec<String>,
+    ) -> Result<(), StoreError> {
+        for dir_entry in std::fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let is_visible_dir = path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .filter(|name| !name.starts_with('.'))
+                    .is_some();
+            if !is_visible_dir {
+                continue;
+            }
+            if path.join(self.recipients_file).is_file() {
+                if let Some(folder) = path
+                    .strip_prefix(&self.base_dir)
+                    .ok()
+                    .and_then(|folder| folder.to_str())
+                {
+                    folders.push(folder.to_owned());
+                }
+            }
+            self.collect_key_id_folders(&path, folders)?;
+        }
+        Ok(())
+    }
+
+    fn entry_name(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.base_dir).ok()?.to_str()?;
+        self.entry_extensions()
+            .find_map(|extension| relative.strip_suffix(extension))
+            .map(ToOwned::to_owned)
+    }
+
+    fn build_folder_path(&self, folder: &str) -> Result<String, StoreError> {
+        match folder {
+            "" => Ok(self.base_dir.clone()),
+            folder => {
+                validate_name(folder)?;
+                Ok(format!("{}/{folder}", self.base_dir))
+            }
+        }
+    }
+
+    fn read_folder_file(
+        &self,
+        folder: &str,
+        file_name: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        let path = format!("{}/{file_name}", self.build_folder_path(folder)?);
+        if !Path::new(&path).is_file() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read(path)?))
+    }
+
+    fn build_entry_path(&self, name: &str, extension: &str) -> String {
+        format!("{}/{name}{extension}", self.base_dir)
+    }
+}
+
+/// Writes the value of an entry to a hidden file beside it, which only
+/// replaces the entry once committed, and is removed if it never is.
+pub struct OnDiskWriter {
+    name: String,
+    temp_path: String,
+    file: BufWriter<File>,
+    /// The start of the value, enough to tell whether it is ASCII armored.
+    head: Vec<u8>,
+    committed: bool,
+}
+
+impl Write for OnDiskWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        let wanted = armor::BEGIN_MESSAGE.len().saturating_sub(self.head.len());
+        self.head.extend_from_slice(&buf[..written.min(wanted)]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for OnDiskWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
     }
 }
 
 impl Store for OnDiskStore {
+    type Reader = File;
+    type Writer = OnDiskWriter;
+
     fn insert<S: AsRef<str>>(&self, name: S, value: &[u8]) -> Result<(), StoreError> {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(self.build_entry_path(name))?;
-        let mut writer = BufWriter::new(file);
-        writer.write_all(value).map_err(|e| e.into())
+        let mut writer = self.writer(name)?;
+        writer.write_all(value)?;
+        self.commit(writer)
     }
 
     fn get<S: AsRef<str>>(&self, name: S) -> Result<Vec<u8>, StoreError> {
-        let name = name.as_ref();
-        let path = self.build_entry_path(name);
-        if !Path::new(&path).exists() {
-            return Err(StoreError::EntryDoesNotExist(name.to_owned()));
-        }
+        validate_name(name.as_ref())?;
         let mut buf = Vec::new();
-        File::open(path)?.read_to_end(&mut buf)?;
+        self.reader(name)?.read_to_end(&mut buf)?;
         Ok(buf)
     }
+
+    fn reader<S: AsRef<str>>(&self, name: S) -> Result<Self::Reader, StoreError> {
+        let name = name.as_ref();
+        validate_name(name)?;
+        let path = self
+            .find_entry_path(name)?
+            .ok_or_else(|| StoreError::EntryDoesNotExist(name.to_owned()))?;
+        Ok(File::open(path)?)
+    }
+
+    fn writer<S: AsRef<str>>(&self, name: S) -> Result<Self::Writer, StoreError> {
+        let name = name.as_ref();
+        validate_name(name)?;
+        let path = self.build_entry_path(name, self.entry_extension);
+        let path = Path::new(&path);
+        if let Some(parent) = path.parent() {
+            fs::create_private_dir_all(parent)?;
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or_default();
+        let temp_path = path
+            .with_file_name(format!(".{file_name}.tmp"))
+            .to_string_lossy()
+            .into_owned();
+        Ok(OnDiskWriter {
+            name: name.to_owned(),
+            file: BufWriter::new(fs::create_private(&temp_path)?),
+            temp_path,
+            head: Vec::new(),
+            committed: false,
+        })
+    }
+
+    fn commit(&self, mut writer: Self::Writer) -> Result<(), StoreError> {
+        writer.file.flush()?;
+        writer.file.get_ref().sync_all()?;
+        let extension = self.extension_of(&writer.head);
+        std::fs::rename(
+            &writer.temp_path,
+            self.build_entry_path(&writer.name, extension),
+        )?;
+        writer.committed = true;
+        // The entry may have been saved in the other format before.
+        for other in self.entry_extensions().filter(|other| *other != extension) {
+            let path = self.build_entry_path(&writer.name, other);
+            if Path::new(&path).is_file() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn key_ids(&self) -> Result<Vec<String>, StoreError> {
+        let not_initialized = |reason: &str| StoreError::NotInitialized(reason.to_owned());
+        if !Path::new(&self.base_dir).is_dir() {
+            return Err(not_initialized("the store directory does not exist"));
+        }
+        let key_list = format!("{}/{}", self.base_dir, self.recipients_file);
+        if !Path::new(&key_list).exists() {
+            return Err(not_initialized(&format!(
+                "it has no {} file",
+                self.recipients_file
+            )));
+        }
+        let key_ids = parse_key_ids(&std::fs::read_to_string(key_list)?);
+        if key_ids.is_empty() {
+            return Err(not_initialized(&format!(
+                "its {} file lists no keys",
+                self.recipients_file
+            )));
+        }
+        Ok(key_ids)
+    }
+
+    fn folder_key_ids<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<String>>, StoreError> {
+        Ok(self
+            .key_id_file(folder)?
+            .map(|contents| parse_key_ids(&String::from_utf8_lossy(&contents))))
+    }
+
+    fn key_id_file<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<u8>>, StoreError> {
+        self.read_folder_file(folder.as_ref(), self.recipients_file)
+    }
+
+    fn key_id_signature<S: AsRef<str>>(&self, folder: S) -> Result<Option<Vec<u8>>, StoreError> {
+        self.read_folder_file(folder.as_ref(), GPG_ID_SIGNATURE_FILE)
+    }
+
+    fn set_key_id_signature<S: AsRef<str>>(
+        &self,
+        folder: S,
+        signature: &[u8],
+    ) -> Result<(), StoreError> {
+        let folder = self.build_folder_path(folder.as_ref())?;
+        Ok(fs::write_private(
+            format!("{folder}/{GPG_ID_SIGNATURE_FILE}"),
+            signature,
+        )?)
+    }
+
+    fn key_id_folders(&self) -> Result<Vec<String>, StoreError> {
+        let mut folders = Vec::new();
+        self.collect_key_id_folders(Path::new(&self.base_dir), &mut folders)?;
+        folders.sort();
+        Ok(folders)
+    }
+
+    fn set_key_id_file<S: AsRef<str>>(&self, folder: S, contents: &[u8]) -> Result<(), StoreError> {
+        let folder = self.build_folder_path(folder.as_ref())?;
+        fs::create_private_dir_all(&folder)?;
+        Ok(fs::write_private(
+            format!("{folder}/{}", self.recipients_file),
+            contents,
+        )?)
+    }
+
+    fn root(&self) -> Option<&str> {
+        Some(&self.base_dir)
+    }
+
+    fn list(&self) -> Result<Vec<String>, StoreError> {
+        let mut entries = Vec::new();
+        self.collect_entries(Path::new(&self.base_dir), &mut entries)?;
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    fn remove<S: AsRef<str>>(&self, name: S) -> Result<(), StoreError> {
+        let name = name.as_ref();
+        validate_name(name)?;
+        let path = self
+            .find_entry_path(name)?
+            .ok_or_else(|| StoreError::EntryDoesNotExist(name.to_owned()))?;
+        Ok(std::fs::remove_file(path)?)
+    }
+}
+
+/// The key ids listed in the contents of a `.gpg-id` file, one per line,
+/// skipping blank lines and `#` comments.
+pub fn parse_key_ids(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToOwned::to_owned)
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use crate::store::{OnDiskStore, Store};
-    use std::{fs::File, io::Read, path::PathBuf, str::FromStr};
+    use crate::{
+        input::GPG_ID_LIST_FILE,
+        store::{validate_name, OnDiskStore, Store, StoreError, ARMORED_ENTRY_EXTENSION},
+    };
+    use std::{
+        fs::File,
+        io::{Read, Write},
+        os::unix::fs::PermissionsExt,
+        path::PathBuf,
+        str::FromStr,
+    };
     use tempfile::tempdir;
 
     #[test]
@@ -122,4 +527,265 @@ mod test {
             format!(r#"The entry "{name}" does not exist!"#)
         );
     }
+
+    #[test]
+    fn key_ids_should_error_if_store_directory_does_not_exist() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = format!("{}/missing", tmpdir.path().to_str().unwrap());
+        let store = OnDiskStore::new(&base_dir);
+        let result = store.key_ids();
+        assert!(matches!(result, Err(StoreError::NotInitialized(_))));
+        assert!(result.err().unwrap().to_string().contains("sec initialize"));
+    }
+
+    #[test]
+    fn key_ids_should_error_if_gpg_id_file_does_not_exist() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        let result = store.key_ids();
+        assert!(matches!(result, Err(StoreError::NotInitialized(_))));
+    }
+
+    #[test]
+    fn key_ids_should_return_each_key_listed_in_gpg_id_file() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(
+            format!("{base_dir}/{GPG_ID_LIST_FILE}"),
+            "first\n\nsecond\n",
+        )
+        .unwrap();
+        let store = OnDiskStore::new(base_dir);
+        assert_eq!(store.key_ids().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn insert_should_replace_a_longer_value() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("key", b"a much longer value").unwrap();
+        store.insert("key", b"short").unwrap();
+        assert_eq!(store.get("key").unwrap(), b"short");
+    }
+
+    #[test]
+    fn insert_should_leave_entry_readable_only_by_owner() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("prod/key", b"value").unwrap();
+        let mode = |path: String| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(format!("{base_dir}/prod/key.gpg")), 0o600);
+        assert_eq!(mode(format!("{base_dir}/prod")), 0o700);
+    }
+
+    #[test]
+    fn insert_should_create_missing_parent_directories() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("prod/web/token", b"value").unwrap();
+        assert_eq!(store.get("prod/web/token").unwrap(), b"value");
+    }
+
+    #[test]
+    fn remove_should_delete_the_entry() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("key", b"value").unwrap();
+        store.remove("key").unwrap();
+        assert!(matches!(
+            store.get("key"),
+            Err(StoreError::EntryDoesNotExist(_))
+        ));
+    }
+
+    #[test]
+    fn remove_should_error_if_entry_does_not_exist() {
+        let tmpdir = tempdir().unwrap();
+        let store = OnDiskStore::new(tmpdir.path().to_str().unwrap());
+        assert!(matches!(
+            store.remove("key"),
+            Err(StoreError::EntryDoesNotExist(_))
+        ));
+    }
+
+    #[test]
+    fn names_outside_the_store_should_be_rejected() {
+        let tmpdir = tempdir().unwrap();
+        let store_dir = tmpdir.path().join("store");
+        let store = OnDiskStore::new(store_dir.to_str().unwrap());
+        for name in ["../escaped", "/abs", "a//b", "a/", "a/./b", ""] {
+            assert!(matches!(
+                store.insert(name, b"value"),
+                Err(StoreError::InvalidName(_))
+            ));
+        }
+        assert!(matches!(
+            store.set_folder_key_ids("../escaped", &["key".to_owned()]),
+            Err(StoreError::InvalidName(_))
+        ));
+        // Nor can entries outside of it be read.
+        std::fs::write(tmpdir.path().join("outside.gpg"), b"value").unwrap();
+        assert!(matches!(
+            store.get("../outside"),
+            Err(StoreError::InvalidName(_))
+        ));
+        assert!(matches!(
+            store.reader("../outside"),
+            Err(StoreError::InvalidName(_))
+        ));
+        std::fs::remove_file(tmpdir.path().join("outside.gpg")).unwrap();
+        assert!(!tmpdir.path().join("escaped.gpg").exists());
+        assert!(!tmpdir.path().join("escaped").exists());
+        assert!(validate_name("prod/db").is_ok());
+    }
+
+    #[test]
+    fn list_should_return_nested_entry_names_sorted() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("prod/db", b"value").unwrap();
+        store.insert("api", b"value").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["api", "prod/db"]);
+    }
+
+    #[test]
+    fn list_should_skip_hidden_and_non_gpg_files() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(format!("{base_dir}/{GPG_ID_LIST_FILE}"), "key").unwrap();
+        std::fs::write(format!("{base_dir}/notes.txt"), "notes").unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("key", b"value").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["key"]);
+    }
+
+    #[test]
+    fn armored_entries_should_be_saved_as_asc_files_and_read_like_any_other() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let armored = b"-----BEGIN PGP MESSAGE-----\n\n...";
+        let store = OnDiskStore::new(base_dir);
+        store.insert("api", b"value").unwrap();
+        store.insert("prod/db", armored).unwrap();
+        assert!(PathBuf::from(format!("{base_dir}/prod/db{ARMORED_ENTRY_EXTENSION}")).is_file());
+        assert_eq!(store.list().unwrap(), vec!["api", "prod/db"]);
+        assert_eq!(store.get("prod/db").unwrap(), armored);
+        store.insert("prod/db", b"value").unwrap();
+        assert!(!PathBuf::from(format!("{base_dir}/prod/db{ARMORED_ENTRY_EXTENSION}")).exists());
+        assert_eq!(store.get("prod/db").unwrap(), b"value");
+        store.insert("api", armored).unwrap();
+        store.remove("api").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["prod/db"]);
+    }
+
+    #[test]
+    fn writer_should_only_replace_the_entry_once_committed() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("prod/db", b"old").unwrap();
+        let mut writer = store.writer("prod/db").unwrap();
+        writer.write_all(b"new").unwrap();
+        assert_eq!(store.get("prod/db").unwrap(), b"old");
+        drop(writer);
+        assert_eq!(store.get("prod/db").unwrap(), b"old");
+        assert_eq!(
+            std::fs::read_dir(format!("{base_dir}/prod"))
+                .unwrap()
+                .count(),
+            1
+        );
+        let mut writer = store.writer("prod/db").unwrap();
+        writer.write_all(b"-----BEGIN PGP ").unwrap();
+        writer.write_all(b"MESSAGE-----\n").unwrap();
+        store.commit(writer).unwrap();
+        assert!(PathBuf::from(format!("{base_dir}/prod/db{ARMORED_ENTRY_EXTENSION}")).is_file());
+        assert_eq!(
+            std::fs::read_dir(format!("{base_dir}/prod"))
+                .unwrap()
+                .count(),
+            1
+        );
+        let mut value = String::new();
+        store
+            .reader("prod/db")
+            .unwrap()
+            .read_to_string(&mut value)
+            .unwrap();
+        assert_eq!(value, "-----BEGIN PGP MESSAGE-----\n");
+    }
+
+    #[test]
+    fn custom_file_names_should_be_used_for_entries_and_recipients() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(format!("{base_dir}/.age-recipients"), "age1root").unwrap();
+        let store = OnDiskStore::new(base_dir).with_file_names(".age", ".age-recipients");
+        store.insert("prod/db", b"value").unwrap();
+        std::fs::write(format!("{base_dir}/api.gpg"), "value").unwrap();
+        assert!(PathBuf::from(format!("{base_dir}/prod/db.age")).is_file());
+        assert_eq!(store.list().unwrap(), vec!["prod/db"]);
+        assert_eq!(store.key_ids().unwrap(), vec!["age1root"]);
+    }
+
+    #[test]
+    fn recipients_should_use_the_nearest_gpg_id_file() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(format!("{base_dir}/{GPG_ID_LIST_FILE}"), "root").unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store
+            .set_folder_key_ids("prod", &["prod-key".to_owned()])
+            .unwrap();
+        assert_eq!(store.recipients("api").unwrap(), vec!["root"]);
+        assert_eq!(store.recipients("prod/db").unwrap(), vec!["prod-key"]);
+        assert_eq!(
+            store.recipients("prod/web/token").unwrap(),
+            vec!["prod-key"]
+        );
+        assert_eq!(store.recipients("dev/db").unwrap(), vec!["root"]);
+    }
+
+    #[test]
+    fn key_id_file_and_signature_should_be_read_as_written() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(format!("{base_dir}/{GPG_ID_LIST_FILE}"), "# me\nroot\n").unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store
+            .set_folder_key_ids("prod", &["prod-key".to_owned()])
+            .unwrap();
+        store.set_key_id_signature("prod", b"signature").unwrap();
+        assert_eq!(store.key_id_folder("prod/web/db").unwrap(), "prod");
+        assert_eq!(store.key_id_folder("api").unwrap(), "");
+        assert_eq!(store.key_id_file("").unwrap().unwrap(), b"# me\nroot\n");
+        assert_eq!(store.key_id_signature("").unwrap(), None);
+        assert_eq!(
+            store.key_id_signature("prod").unwrap().unwrap(),
+            b"signature"
+        );
+    }
+
+    #[test]
+    fn key_id_folders_should_list_nested_folders_with_gpg_id_files() {
+        let tmpdir = tempdir().unwrap();
+        let base_dir = tmpdir.path().to_str().unwrap();
+        std::fs::write(format!("{base_dir}/{GPG_ID_LIST_FILE}"), "root").unwrap();
+        let store = OnDiskStore::new(base_dir);
+        store.insert("dev/db", b"value").unwrap();
+        store
+            .set_folder_key_ids("prod/web", &["web".to_owned()])
+            .unwrap();
+        store
+            .set_folder_key_ids("prod", &["prod".to_owned()])
+            .unwrap();
+        assert_eq!(store.key_id_folders().unwrap(), vec!["prod", "prod/web"]);
+        assert_eq!(store.folder_key_ids("dev").unwrap(), None);
+    }
 }
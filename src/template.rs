@@ -0,0 +1,181 @@
+use crate::entry::EntryRef;
+use thiserror::Error;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+const KEYWORD: &str = "sec";
+
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("Failed to read the template")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: unterminated placeholder, expected `}}}}`")]
+    Unterminated { line: usize },
+    #[error("line {line}: invalid placeholder `{placeholder}`, {reason}")]
+    InvalidPlaceholder {
+        line: usize,
+        placeholder: String,
+        reason: String,
+    },
+}
+
+/// A piece of a parsed template.
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Secret(EntryRef),
+}
+
+/// Parse a template containing placeholders such as
+/// `{{ sec "prod/db" field="password" }}`. Placeholders which do not start
+/// with `sec` are kept as text, so templates can contain other `{{ }}` syntax.
+pub fn parse(template: &str) -> Result<Vec<Segment<'_>>, TemplateError> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(OPEN) {
+        let line = line_number(template, rest, start);
+        let end = rest[start..]
+            .find(CLOSE)
+            .map(|end| start + end + CLOSE.len())
+            .ok_or(TemplateError::Unterminated { line })?;
+        let inner = rest[start + OPEN.len()..end - CLOSE.len()].trim();
+        match inner.strip_prefix(KEYWORD) {
+            Some(args) if args.is_empty() || args.starts_with(char::is_whitespace) => {
+                if start > 0 {
+                    segments.push(Segment::Text(&rest[..start]));
+                }
+                let entry =
+                    parse_args(args).map_err(|reason| TemplateError::InvalidPlaceholder {
+                        line,
+                        placeholder: rest[start..end].to_owned(),
+                        reason,
+                    })?;
+                segments.push(Segment::Secret(entry));
+            }
+            _ => segments.push(Segment::Text(&rest[..end])),
+        }
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+/// Every entry referenced by the template.
+pub fn references<'a>(segments: &'a [Segment<'_>]) -> impl Iterator<Item = &'a EntryRef> {
+    segments.iter().filter_map(|segment| match segment {
+        Segment::Secret(entry) => Some(entry),
+        Segment::Text(_) => None,
+    })
+}
+
+fn line_number(template: &str, rest: &str, offset: usize) -> usize {
+    let position = template.len() - rest.len() + offset;
+    template[..position].matches('\n').count() + 1
+}
+
+fn parse_args(args: &str) -> Result<EntryRef, String> {
+    let (name, args) = parse_quoted(args.trim_start())?;
+    let args = args.trim();
+    let field = if args.is_empty() {
+        None
+    } else {
+        let value = args
+            .strip_prefix("field=")
+            .ok_or_else(|| format!("unexpected `{args}`"))?;
+        let (field, rest) = parse_quoted(value)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("unexpected `{}`", rest.trim()));
+        }
+        Some(field.to_owned())
+    };
+    if name.is_empty() {
+        return Err("the entry name is empty".to_owned());
+    }
+    Ok(EntryRef {
+        name: name.to_owned(),
+        field,
+    })
+}
+
+/// Split a leading double quoted string from `s`, returning the unquoted
+/// value and the remainder.
+fn parse_quoted(s: &str) -> Result<(&str, &str), String> {
+    let s = s
+        .strip_prefix('"')
+        .ok_or_else(|| "expected a double quoted string".to_owned())?;
+    let end = s
+        .find('"')
+        .ok_or_else(|| "missing closing `\"`".to_owned())?;
+    Ok((&s[..end], &s[end + 1..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, references, Segment, TemplateError};
+    use crate::entry::EntryRef;
+
+    fn entry(name: &str, field: Option<&str>) -> EntryRef {
+        EntryRef {
+            name: name.to_owned(),
+            field: field.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn should_parse_text_and_placeholders() {
+        let segments =
+            parse(r#"user={{ sec "prod/db" field="user" }} pass={{sec "prod/db"}}"#).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Text("user="),
+                Segment::Secret(entry("prod/db", Some("user"))),
+                Segment::Text(" pass="),
+                Segment::Secret(entry("prod/db", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_keep_other_placeholders_as_text() {
+        let segments = parse("{{ .Values.name }} {{ secret }}").unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Text("{{ .Values.name }}"),
+                Segment::Text(" {{ secret }}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_list_references() {
+        let segments = parse(r#"{{ sec "a" }} {{ sec "b" field="x" }}"#).unwrap();
+        let names = references(&segments)
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn should_error_on_unterminated_placeholder() {
+        let result = parse("first\n{{ sec \"a\"");
+        assert!(matches!(
+            result,
+            Err(TemplateError::Unterminated { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn should_error_on_invalid_placeholder() {
+        let result = parse(r#"{{ sec prod/db }}"#);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "line 1: invalid placeholder `{{ sec prod/db }}`, expected a double quoted string"
+        );
+        assert!(parse(r#"{{ sec "a" other="b" }}"#).is_err());
+        assert!(parse(r#"{{ sec "" }}"#).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+/// Entry names arranged as a tree of path segments.
+#[derive(Default, PartialEq)]
+pub struct EntryTree {
+    children: BTreeMap<String, EntryTree>,
+    is_entry: bool,
+}
+
+/// A single node of a flattened [`EntryTree`].
+#[derive(Debug, PartialEq)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub label: String,
+    /// The full entry name, if this node is an entry rather than a folder.
+    pub name: Option<String>,
+}
+
+impl EntryTree {
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut tree = Self::default();
+        for name in names {
+            let mut node = &mut tree;
+            for segment in name.as_ref().split('/').filter(|s| !s.is_empty()) {
+                node = node.children.entry(segment.to_owned()).or_default();
+            }
+            node.is_entry = true;
+        }
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Render the tree one line per node, in the style of the `tree` command.
+    pub fn render(&self, root: &str) -> Vec<String> {
+        let mut lines = vec![root.to_owned()];
+        self.render_children("", &mut lines);
+        lines
+    }
+
+    /// Flatten the tree into rows in display order.
+    pub fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.collect_rows("", 0, &mut rows);
+        rows
+    }
+
+    fn collect_rows(&self, path: &str, depth: usize, rows: &mut Vec<TreeRow>) {
+        for (segment, child) in &self.children {
+            let name = format!("{path}{segment}");
+            rows.push(TreeRow {
+                depth,
+                label: segment.clone(),
+                name: child.is_entry.then(|| name.clone()),
+            });
+            child.collect_rows(&format!("{name}/"), depth + 1, rows);
+        }
+    }
+
+    fn render_children(&self, prefix: &str, lines: &mut Vec<String>) {
+        let count = self.children.len();
+        for (i, (segment, child)) in self.children.iter().enumerate() {
+            let is_last = i + 1 == count;
+            let (branch, indent) = if is_last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            lines.push(format!("{prefix}{branch}{segment}"));
+            child.render_children(&format!("{prefix}{indent}"), lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EntryTree, TreeRow};
+
+    #[test]
+    fn should_render_nested_entries_as_a_tree() {
+        let tree = EntryTree::new(["api", "prod/db", "prod/web/token"]);
+        assert_eq!(
+            tree.render("Password Store"),
+            vec![
+                "Password Store",
+                "├── api",
+                "└── prod",
+                "    ├── db",
+                "    └── web",
+                "        └── token",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_render_only_the_root_for_no_entries() {
+        let tree = EntryTree::new(Vec::<String>::new());
+        assert!(tree.is_empty());
+        assert_eq!(tree.render("Password Store"), vec!["Password Store"]);
+    }
+
+    #[test]
+    fn rows_should_flatten_tree_with_full_entry_names() {
+        let tree = EntryTree::new(["api", "prod/db"]);
+        assert_eq!(
+            tree.rows(),
+            vec![
+                TreeRow {
+                    depth: 0,
+                    label: "api".to_owned(),
+                    name: Some("api".to_owned()),
+                },
+                TreeRow {
+                    depth: 0,
+                    label: "prod".to_owned(),
+                    name: None,
+                },
+                TreeRow {
+                    depth: 1,
+                    label: "db".to_owned(),
+                    name: Some("prod/db".to_owned()),
+                },
+            ]
+        );
+    }
+}